@@ -1,542 +1,6 @@
 use std::collections::HashMap;
 
-const LOGLEVEL: u8 = 0;
-macro_rules! debug {
-    ( $($arg:tt)* ) => {
-        if LOGLEVEL >= 2 {
-            print!("DEBUG: ");
-            print!($($arg)*);
-        }
-    }
-}
-
-macro_rules! info {
-    ( $($arg:tt)* ) => {
-        if LOGLEVEL >= 1 {
-            print!("INFO:  ");
-            print!($($arg)*);
-        }
-    }
-}
-
-// Immediate parameter
-type Imm = isize;
-
-// Position parameter
-type Pos = usize;
-
-// Relative parameter
-// type Rel = isize;
-
-#[derive(Debug, Clone)]
-/// Program struct containing the current state of the emulator
-struct Program {
-    /// Instruction Pointer
-    ip: usize,
-
-    /// Current memory in the emulator
-    memory: Vec<isize>,
-
-    /// Lifted instructions to be executed in the emulator
-    /// HashMap is keyed by IP of the instruction
-    instructions: HashMap<usize, Opcode>,
-
-    /// Input buffer
-    input: Vec<isize>,
-
-    /// Output buffer
-    output: Vec<isize>,
-
-    /// VM has halted
-    halted: bool,
-    
-    /// Current relative address
-    relative_base: isize
-}
-
-#[derive(Clone, Copy, Eq, PartialEq)]
-enum Mode {
-    Positional(usize),
-    Immediate(isize),
-    Relative(isize)
-}
-
-impl std::fmt::Debug for Mode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Mode::Positional(addr) => write!(f, "Pos({})", addr),
-            Mode::Immediate(imm) => write!(f, "Imm({})", imm),
-            Mode::Relative(rel) => write!(f, "Rel({})", rel),
-        }
-    }
-}
-
-use Mode::*;
-
-/// Available opcodes in our computer emulator
-/// 
-/// Each opcode is appended by how the parameters should be interpretted
-///
-/// Example:
-/// AddPPP - add where all parameters are positions in memory 
-/// AddIIP - add where the two parameters are immediates and the result is a position
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Opcode {
-    Add(Mode, Mode, Mode),
-    Mul(Mode, Mode, Mode),
-    In(Mode),
-    Out(Mode),
-    JumpNonZero(Mode, Mode),
-    JumpZero(Mode, Mode),
-    LessThan(Mode, Mode, Mode),
-    Equals(Mode, Mode, Mode),
-    AdjustRelativeBase(Mode),
-    Halt
-}
-
-impl Opcode {
-    /// Returns the length of the instruction.
-    ///
-    /// This function is used during the instruction caching in order to determine if a given write
-    /// destination is in an already lifted instruction.
-    pub fn len(&self) -> usize {
-        use Opcode::*;
-        match self {
-            In(_)|Out(_)|AdjustRelativeBase(_) => 2,
-            JumpNonZero(_,_)|JumpZero(_,_) => 3,
-            LessThan(_,_,_)|Equals(_,_,_)|Add(_,_,_)|Mul(_,_,_) => 4,
-            Halt => 1
-        }
-    }
-}
-
-impl Program {
-    pub fn from_input(input: &str) -> Program {
-        // Remove new lines from input string
-        let input = input.replace("\r", "").replace("\n", "");
-        
-        let memory: Vec<isize> = input.split(',')
-                                      // Ignore empty strings from split
-                                      .filter(|x| x.len() > 0)
-                                      // Parse ints as usize
-                                      .map(|x|  x.parse::<isize>().expect(&format!("Error parsing: {}\n", x)))
-                                      // Collect into Vec<usize>
-                                      .collect();
-
-
-        // Generate a program converting the input into a Vec<usize>
-        Program {
-            ip: 0,
-            memory: memory,
-            instructions: HashMap::new(),
-            input: Vec::new(),
-            output: Vec::new(),
-            halted: false,
-            relative_base: 0
-        }
-    }
-    
-    /// Print the current memory state of the emulator
-    pub fn _print(&self) {
-        print!("IP: {:06}\n", self.ip);
-        let chunk_size = 0x8;
-        for (i, bytes) in self.memory.chunks(chunk_size).enumerate() {
-            print!("{:06} ", i*chunk_size);
-            for b in bytes {
-                print!("{:07} ", b);
-            }
-            print!("\n");
-        }
-    }
-
-    /// Lift the instruction at the given address. Panics if unknown opcode is found.
-    pub fn lift(&mut self, addr: Pos) -> Option<Opcode> {
-        let mut opcode = self.memory[addr];
-        debug!("[{}] Lifting {:05} ", addr, opcode);
-        let mode3 = opcode / 10000;
-        opcode = opcode % 10000;
-        let mode2 = opcode / 1000;
-        opcode = opcode % 1000;
-        let mode1 = opcode / 100;
-        opcode = opcode % 100;
-        debug!("{} ({} {} {})\n", opcode, mode3, mode2, mode1);
-
-        match opcode {
-            1|2|7|8 => {
-                // Lifting an Add, Mul, LessThan, Equals
-                let param1 = self.read(addr+1);
-                let param2 = self.read(addr+2);
-                let param3 = self.read(addr+3);
-
-                let param1 = match mode1 {
-                    0 => Positional(param1 as usize),
-                    1 => Immediate(param1),
-                    2 => Relative(param1),
-                    _ => unreachable!()
-                };
-
-                let param2 = match mode2 {
-                    0 => Positional(param2 as usize),
-                    1 => Immediate(param2),
-                    2 => Relative(param2),
-                    _ => unreachable!()
-                };
-
-                let param3 = match mode3 {
-                    0 => Positional(param3 as usize),
-                    1 => Immediate(param3),
-                    2 => Relative(param3),
-                    _ => unreachable!()
-                };
-
-                let op = match opcode {
-                    1 => Opcode::Add(param1, param2, param3),
-                    2 => Opcode::Mul(param1, param2, param3),
-                    7 => Opcode::LessThan(param1, param2, param3),
-                    8 => Opcode::Equals(param1, param2, param3),
-                    _ => unreachable!()
-                };
-
-                debug!("Lifted [{:4}] {} {:?}\n", addr, opcode, op);
-
-                self.instructions.insert(addr, op);
-                Some(op)
-            }
-            3|4|9 => {
-                // Lifting an In, Out, AdjustRelativeBase
-                let param1 = self.read(addr+1);
-                let value = match mode1 {
-                    0 => Positional(param1 as usize),
-                    1 => Immediate(param1),
-                    2 => Relative(param1),
-                    _ => unreachable!()
-                };
-
-                let op = match opcode {
-                    3 => Opcode::In(value),
-                    4 => Opcode::Out(value),
-                    9 => Opcode::AdjustRelativeBase(value),
-                    _ => unreachable!()
-                };
-
-                self.instructions.insert(addr, op);
-                Some(op)
-            }
-
-            5|6 => {
-                // Lifting an JumpNonZero, JumpZero
-                let param1 = self.read(addr+1);
-                let param2 = self.read(addr+2);
-
-                let param1 = match mode1 {
-                    0 => Positional(param1 as usize),
-                    1 => Immediate(param1),
-                    2 => Relative(param1),
-                    _ => unreachable!()
-                };
-
-                let param2 = match mode2 {
-                    0 => Positional(param2 as usize),
-                    1 => Immediate(param2),
-                    2 => Relative(param2),
-                    _ => unreachable!()
-                };
-
-                let op = match opcode {
-                    5 => Opcode::JumpNonZero(param1, param2),
-                    6 => Opcode::JumpZero(param1, param2),
-                    _ => unreachable!()
-                };
-
-                self.instructions.insert(addr, op);
-                Some(op)
-            }
-            99 => {
-                // Lifting an Halt opcode
-                self.instructions.insert(addr, Opcode::Halt);
-                Some(Opcode::Halt)
-            }
-            _ => { 
-                // Hit an unknown opcode, break out of the loop
-                info!("Unknown opcode @ {}: {}\n", addr, opcode);
-                None
-            }
-        }
-    }
-
-    /// Execute the current program loaded into the emulator.
-    ///
-    /// The emulator will see if the current instruction has been lifted already. If not, attempt
-    /// to lift the instruction. If so, use the previously lifted instruction.
-    pub fn run(&mut self) {
-        loop {
-            let opcode = self.instructions.get(&self.ip);
-            let opcode = match opcode {
-                // Haven't seen this opcode yet, attempt to lift it from memory
-                None => {
-                    match self.lift(self.ip) {
-                        Some(op) => op,
-                        None => panic!("Failed to lift addr at {}", self.ip)
-                    }
-                }
-
-                // Seen this opcode already, attempt to emulate it
-                Some(op) => { *op }
-            };
-            info!("Executing: {:?}\n", opcode);
-            match opcode {
-                Opcode::Add(param1, param2, dest) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let dest = match dest {
-                        Positional(addr) => addr as usize,
-                        Immediate(_imm) => panic!("Cannot execute Add with an immediate dest"),
-                        Relative(rel_offset) => (self.relative_base + rel_offset) as usize
-                    };
-
-                    let result = value1 + value2;
-                    debug!("Add: {} = {} + {} ({})\n", dest, value1, value2, result);
-                    self.write(dest, result);
-                    self.ip += 4;
-                }
-                Opcode::Mul(param1, param2, dest) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let dest = match dest {
-                        Positional(addr) => addr as usize,
-                        Immediate(_imm) => panic!("Cannot execute Mul with an immediate dest"),
-                        Relative(rel_offset) => (self.relative_base + rel_offset) as usize
-                    };
-
-                    let result = value1 * value2;
-                    debug!("Mul: [{}] = {} * {} ({})\n", dest, value1, value2, result);
-                    self.write(dest, result);
-                    self.ip += 4;
-                }
-                
-                Opcode::In(dest) => {
-                    let input_val = self.read_input();
-                    if input_val.is_none() {
-                        // print!("InP without any input.. breaking\n");
-                        break;
-                    }
-
-                    let dest = match dest {
-                        Positional(addr) => addr as usize,
-                        Immediate(_imm) => panic!("Cannot execute In with an immediate dest"),
-                        Relative(rel_offset) => (self.relative_base + rel_offset) as usize
-                    };
-
-                    let input_val = input_val.unwrap();
-                    info!("In: [{}] = {}\n", dest, input_val);
-                    self.write(dest, input_val);
-                    self.ip += 2;
-                }
-
-                Opcode::Out(value) => {
-                    let value = match value {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-
-                    debug!("Out: output.push({})\n", value);
-                    self.write_output(value);
-                    self.ip += 2;
-                }
-
-                Opcode::JumpNonZero(param1, param2) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    debug!("JumpNonZero: if {} is nonzero, jmp to {}\n", value1, value2);
-                    if value1 != 0 {
-                        debug!("   ip = {}\n", value2);
-                        self.ip = value2 as usize;
-                    } else {
-                        debug!("   ip += 3\n");
-                        self.ip += 3;
-                    }
-                }
-
-                Opcode::JumpZero(param1, param2) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    debug!("JumpZero: if {} is nonzero, jmp to {}\n", value1, value2);
-                    if value1 == 0 {
-                        debug!("   ip = {}\n", value2);
-                        self.ip = value2 as usize;
-                    } else {
-                        debug!("   ip += 3\n");
-                        self.ip += 3;
-                    }
-                }
-
-                Opcode::LessThan(param1, param2, dest) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let dest = match dest {
-                        Positional(addr) => addr as usize,
-                        Immediate(_imm) => panic!("Cannot execute LessThan with an immediate dest"),
-                        Relative(rel_offset) => (self.relative_base + rel_offset) as usize
-                    };
-
-                    debug!("LessThan: if {} < {}, [{}] = 1 else [{}] = 0\n", value1, value2, dest, dest);
-                    let value = if value1 < value2 { 1 } else { 0 };
-                    self.write(dest, value);
-                    self.ip += 4;
-                }
-
-                Opcode::Equals(param1, param2, dest) => {
-                    let value1 = match param1 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let value2 = match param2 {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-                    let dest = match dest {
-                        Positional(addr) => addr as usize,
-                        Immediate(_imm) => panic!("Cannot execute Equals with an immediate dest"),
-                        Relative(rel_offset) => (self.relative_base + rel_offset) as usize
-                    };
-
-                    debug!("Equals: if {} == {}, [{}] = 1 else [{}] = 0\n", value1, value2, dest, dest);
-                    let value = if value1 == value2 { 1 } else { 0 };
-                    self.write(dest, value);
-                    self.ip += 4;
-                }
-                Opcode::AdjustRelativeBase(offset) => {
-                    let offset = match offset {
-                        Positional(addr) => self.read(addr),
-                        Immediate(imm) => imm,
-                        Relative(rel_offset) => self.read((self.relative_base + rel_offset) as usize)
-                    };
-
-                    info!("New relative base: {} = {} + {}\n", self.relative_base + offset, 
-                        self.relative_base, offset);
-                    self.relative_base += offset; 
-                    self.ip += 2;
-                }
-                Opcode::Halt => { 
-                    self.halted = true;
-                    break; 
-                }
-            }
-        }
-    }
-
-    /// Write a value to the given address.
-    ///
-    /// Since data and code reside in the same memory, a write could corrupt a cached instruction.
-    /// On each write, there is a check to see if the write corrupts a cached instruction and if
-    /// so, the cached instruction is updated. 
-    pub fn write(&mut self, address: Pos, value: Imm) {
-        if address > self.memory.len() {
-            debug!("Resizing to {}\n", address + 1000);
-            self.memory.resize(address + 1000, 0);
-        }
-        self.memory[address] = value;
-
-        // A write could overwrite a cached instruction. Check if this write corrupts a previously
-        // lifted instruction.
-        let mut modified = None;
-        for (start, op) in self.instructions.iter() {
-            let end = start + op.len();
-            if (start..&end).contains(&&address) {
-                // Found the instruction that was modified. Mark the instruction address to check.
-                modified = Some(start.clone());
-                break;
-            }
-        }
-
-        // If this write, modified an instruction, attempt to lift the new instruction at this address:
-        // * If the modified instruction is still a valid instruction, update the cache.
-        // * If the modified instruction results in an invalid instruction, invalidate the cache.
-        if let Some(start) = modified {
-            let new_instr = self.lift(start);
-            let old_op = self.instructions.get(&start);
-            match new_instr {
-                Some(new_op) => {
-                    info!("[{}] {:?} -> {:?} -- New instruction\n", start, old_op, new_op);
-                    self.instructions.insert(start, new_op);
-                }
-                None => {
-                    info!("[{}] {:?} -> None -- New instruction is invalid\n", start, old_op);
-                    self.instructions.remove(&start);
-                }
-            }
-        }
-    }
-
-    /// Read a value from the given address
-    pub fn read(&mut self, address: Pos) -> Imm {
-        if address > self.memory.len() {
-            debug!("Resizing to {}\n", address + 1000);
-            self.memory.resize(address + 1000, 0);
-        }
-        self.memory[address as usize]
-    }
-
-    /// Returns the next item in the input buffer
-    pub fn read_input(&mut self) -> Option<isize> {
-        if self.input.len() == 0 { return None; }
-        Some(self.input.remove(0))
-    }
-
-    /// Write a value to the output buffer
-    pub fn write_output(&mut self, value: isize) {
-        self.output.push(value);
-    }
-
-    pub fn _print_output(&self) {
-        for o in self.output.iter() {
-            print!("{}\n", o);
-        }
-    }
-}
+use intcode::{Event, Program};
 
 enum Direction {
     Up,
@@ -560,6 +24,7 @@ impl From<isize> for Turn {
     }
 }
 
+#[allow(dead_code)]
 enum TileId {
     Empty,
     Wall,
@@ -602,10 +67,7 @@ impl Board {
     /// Given a width and height, create a new board of (width * 2 + 1)
     pub fn new(width: usize, height: usize) -> Board {
         // Allocate the print buffer with '.' as blank spaces
-        let mut buffer = Vec::new();
-        for _ in 0..((width * 2 + 1) * (height * 2 + 1)) {
-            buffer.push(' ');
-        }
+        let buffer = vec![' '; (width * 2 + 1) * (height * 2 + 1)];
 
         Board {
             width: width * 2 + 1,
@@ -617,7 +79,7 @@ impl Board {
     }
 
     pub fn mark(&mut self, x: isize, y: isize) {
-        let curr_y = self.center_y + (-1 * y) as usize;
+        let curr_y = (self.center_y as isize - y) as usize;
         let curr_x = (self.center_x as isize + x) as usize;
         self.buffer[curr_y * self.width + curr_x] = '%';
     }
@@ -628,7 +90,7 @@ impl Board {
             for c in line {
                 print!("{}", c);
             }
-            print!("\n");
+            println!();
         }
     }
 }
@@ -646,14 +108,20 @@ fn main() {
         // If we have already visited a location, use the color printed. Otherwise,
         // the default board is always black
         let curr_location = (location_x, location_y);
-        let input_val = visited.get(&curr_location).or(Some(&0)).unwrap();
+        let input_val = visited.get(&curr_location).unwrap_or(&0);
 
-        program.input.push(*input_val);
-        program.run();
-        if program.halted { break; }
- 
-        let color = program.output.remove(0);
-        let new_dir: Turn = program.output.remove(0).into();
+        program.input.push_back(*input_val);
+
+        // The robot outputs the color to paint followed by the direction to turn
+        let color = match program.run_until_output().unwrap() {
+            Event::Output(color) => color,
+            Event::Halted => break,
+            event => panic!("Expected a color, found {:?}", event)
+        };
+        let new_dir: Turn = match program.run_until_output().unwrap() {
+            Event::Output(turn) => turn.into(),
+            event => panic!("Expected a turn, found {:?}", event)
+        };
 
         visited.insert(curr_location, color);
 
@@ -679,8 +147,8 @@ fn main() {
     }
 
     // Calculate the width of the board needed
-    let max_x = visited.keys().map(|x| (x.0 as isize).abs() as usize).max().unwrap();
-    let max_y = visited.keys().map(|x| (x.1 as isize).abs() as usize).max().unwrap();
+    let max_x = visited.keys().map(|x| x.0.unsigned_abs()).max().unwrap();
+    let max_y = visited.keys().map(|x| x.1.unsigned_abs()).max().unwrap();
 
     let mut board = Board::new(max_x, max_y);
 
@@ -0,0 +1,54 @@
+//! Compares executing the day09 BOOST program one instruction per dispatch with `Program::step`
+//! against whole basic blocks per dispatch with `Program::run`, with and without the JIT, and
+//! against the ahead-of-time translation checked in for the tests.
+//!
+//! Run with `cargo bench -p intcode --bench dispatch`
+
+use std::time::{Duration, Instant};
+
+use intcode::{Event, Jit, Program};
+
+mod day09 {
+    include!("../tests/aot/day09.rs");
+}
+
+/// Best time out of `rounds` runs of BOOST stage 2 using the given driver
+fn best_of<F: FnMut(&mut Program)>(rounds: usize, mut driver: F) -> Duration {
+    let input = include_str!("../../day09/input");
+    (0..rounds).map(|_| {
+        let mut program = Program::from_input(input);
+        program.input.push_back(2);
+        let start = Instant::now();
+        driver(&mut program);
+        let elapsed = start.elapsed();
+        assert_eq!(program.output, vec![77944]);
+        elapsed
+    }).min().unwrap()
+}
+
+fn main() {
+    let rounds = 20;
+
+    let step = best_of(rounds, |program| {
+        while program.step().unwrap() != Event::Halted {}
+    });
+    println!("step per instruction: {:>10.3?}", step);
+
+    let blocks = best_of(rounds, |program| {
+        program.run().unwrap();
+    });
+    println!("run per block:        {:>10.3?}", blocks);
+
+    let jit = best_of(rounds, |program| {
+        program.jit = Some(Jit::default());
+        program.run().unwrap();
+    });
+    println!("run with JIT:         {:>10.3?}", jit);
+
+    let translated = best_of(rounds, |program| {
+        let mut translated = day09::Translated::from_program(program.clone());
+        translated.run().unwrap();
+        *program = translated.program;
+    });
+    println!("translated:           {:>10.3?}", translated);
+}
@@ -0,0 +1,92 @@
+//! Compares the old linear scan of the lifted instruction cache on every write against the
+//! constant time lookup used by `Program::write`, replaying the writes of the day09 BOOST program.
+//!
+//! Run with `cargo bench -p intcode --bench invalidation`
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+use intcode::{Imm, Memory, Opcode, PagedMemory, Pos, Program};
+
+/// Memory that remembers every address written
+#[derive(Default)]
+struct Recording {
+    memory: PagedMemory,
+    writes: Vec<Pos>
+}
+
+impl Memory for Recording {
+    fn read(&self, address: Pos) -> Imm {
+        self.memory.read(address)
+    }
+
+    fn write(&mut self, address: Pos, value: Imm) {
+        self.writes.push(address);
+        self.memory.write(address, value);
+    }
+
+    fn regions(&self) -> Vec<(Pos, &[Imm])> {
+        self.memory.regions()
+    }
+}
+
+/// Cache check performed on every write before the constant time lookup
+fn linear_scan(instructions: &HashMap<Pos, Opcode>, address: Pos) -> Option<Pos> {
+    for (&start, op) in instructions.iter() {
+        if (start..start + op.len()).contains(&address) {
+            return Some(start);
+        }
+    }
+    None
+}
+
+fn main() {
+    let input = include_str!("../../day09/input");
+    let rounds = 20;
+
+    // End to end time of BOOST stage 2 with the constant time check
+    let mut best = None;
+    let mut program = Program::from_input(input).with_memory::<Recording>();
+    for _ in 0..rounds {
+        program = Program::from_input(input).with_memory::<Recording>();
+        program.memory.writes.clear();
+        program.input.push_back(2);
+        let start = Instant::now();
+        program.run().unwrap();
+        let elapsed = start.elapsed();
+        best = Some(best.map_or(elapsed, |best: std::time::Duration| best.min(elapsed)));
+    }
+    let writes = std::mem::take(&mut program.memory.writes);
+    println!("BOOST stage 2:   {:>10.3?} ({} writes, {} lifted instructions)",
+        best.unwrap(), writes.len(), program.instructions.len());
+
+    // Replay every write against the final instruction cache with both checks
+    let lifted: HashMap<Pos, Opcode> = program.instructions.iter().collect();
+    let start = Instant::now();
+    let mut linear_hits = 0;
+    for _ in 0..rounds {
+        for &address in &writes {
+            if black_box(linear_scan(&lifted, address)).is_some() {
+                linear_hits += 1;
+            }
+        }
+    }
+    let linear = start.elapsed() / rounds;
+
+    let start = Instant::now();
+    let mut constant_hits = 0;
+    for _ in 0..rounds {
+        for &address in &writes {
+            if black_box(program.instructions.containing(address).next()).is_some() {
+                constant_hits += 1;
+            }
+        }
+    }
+    let constant = start.elapsed() / rounds;
+
+    assert_eq!(linear_hits, constant_hits);
+    let per_write = |total: std::time::Duration| total.as_nanos() as f64 / writes.len() as f64;
+    println!("linear scan:     {:>10.3?} ({:.1} ns/write)", linear, per_write(linear));
+    println!("constant lookup: {:>10.3?} ({:.1} ns/write)", constant, per_write(constant));
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Fixed part of every translated module between the image and the translated code. Translated
+/// code falls back to the interpreter through `interpret!` for anything it does not handle
+/// itself, which re-executes the instruction so that faults are reported exactly as usual.
+const PRELUDE: &str = r#"
+/// Execute the instruction at the IP, or at `$addr`, with the interpreter
+#[allow(unused_macros)]
+macro_rules! interpret {
+    ($p:ident) => {
+        match $p.step()? {
+            Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+            Event::Halted => return Ok(ExitReason::Halted),
+            _ => continue
+        }
+    };
+    ($p:ident, $addr:expr) => {{
+        $p.ip = $addr;
+        interpret!($p)
+    }};
+}
+
+/// Address of a relative parameter of the instruction at `$addr`. Negative addresses and
+/// overflow are left to the interpreter.
+#[allow(unused_macros)]
+macro_rules! rel {
+    ($p:ident, $offset:expr, $addr:expr) => {
+        match $p.relative_base.checked_add($offset) {
+            Some(address) if address >= 0 => address as Pos,
+            _ => interpret!($p, $addr)
+        }
+    };
+}
+
+/// Execute the instruction at the IP with the interpreter, noting writes into translated code
+#[allow(unused_macros)]
+macro_rules! fallback {
+    ($p:ident, $modified:expr) => {{
+        if let Some(block) = written_block($p) {
+            $modified.insert(block);
+        }
+        interpret!($p)
+    }};
+}
+
+/// Start address of the translated block containing `address`
+fn block_at(address: Pos) -> Option<Pos> {
+    let index = BLOCKS.partition_point(|&(start, _)| start <= address).checked_sub(1)?;
+    let (start, end) = BLOCKS[index];
+    if address < end { Some(start) } else { None }
+}
+
+/// Start address of the translated block the instruction at the IP writes into
+fn written_block<I: InputSource, O: OutputSink, M: Memory>(p: &Program<I, O, M>) -> Option<Pos> {
+    let dest = match p.decode(p.ip) {
+        Ok(Opcode::Add(_, _, dest)) | Ok(Opcode::Mul(_, _, dest)) | Ok(Opcode::LessThan(_, _, dest))
+        | Ok(Opcode::Equals(_, _, dest)) | Ok(Opcode::In(dest)) => dest,
+        _ => return None
+    };
+    match dest {
+        Mode::Positional(address) => block_at(address),
+        Mode::Relative(offset) => match p.relative_base.checked_add(offset) {
+            Some(address) if address >= 0 => block_at(address as Pos),
+            _ => None
+        },
+        Mode::Immediate(_) => None
+    }
+}
+
+/// Translated program with the emulator state it runs on
+pub struct Translated<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Emulator state, shared with the interpreter fallback
+    pub program: Program<I, O, M>,
+
+    /// Start addresses of the blocks whose code was overwritten. These only run on the
+    /// interpreter from then on.
+    pub modified: BTreeSet<Pos>
+}
+
+impl Translated {
+    /// Translated program with the image loaded at address 0
+    pub fn new() -> Self {
+        Translated::from_program(Program::from_image(IMAGE))
+    }
+}
+
+impl Default for Translated {
+    fn default() -> Self {
+        Translated::new()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Translated<I, O, M> {
+    /// Run translated code on an existing emulator state. Blocks whose code differs from the
+    /// image, for example after patching the program, are interpreted.
+    pub fn from_program(program: Program<I, O, M>) -> Self {
+        let modified = BLOCKS.iter()
+            .filter(|&&(start, end)| {
+                (start..end).any(|address| program.read(address) != IMAGE.get(address).copied().unwrap_or(0))
+            })
+            .map(|&(start, _)| start)
+            .collect();
+        Translated { program, modified }
+    }
+
+    /// Execute until the program halts or needs input, like `Program::run`
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        loop {
+            let p = &mut self.program;
+            match p.ip {
+                ip if self.modified.contains(&ip) => fallback!(p, self.modified),
+"#;
+
+/// Closes the dispatch loop opened by [`PRELUDE`]
+const EPILOGUE: &str = "                _ => fallback!(p, self.modified)
+            }
+        }
+    }
+}
+";
+
+/// Emits the Rust code executing a single instruction inside a dispatch arm
+struct Emitter<'a> {
+    /// Code being generated
+    code: &'a mut String,
+
+    /// Address of the instruction
+    addr: Pos,
+
+    /// Start address of the translated block containing an address
+    block_at: &'a dyn Fn(Pos) -> Option<Pos>
+}
+
+impl Emitter<'_> {
+    fn line(&mut self, line: &str) {
+        let _ = writeln!(self.code, "                    {}", line);
+    }
+
+    /// Fall back to the interpreter for the whole instruction
+    fn interpret(&mut self) {
+        let line = format!("interpret!(p, {})", self.addr);
+        self.line(&line);
+    }
+
+    /// Expression for the value of a parameter. Memory reads are bound to `name` first.
+    fn value(&mut self, param: Mode, name: &str) -> String {
+        let read = match param {
+            Mode::Immediate(imm) => return imm.to_string(),
+            Mode::Positional(address) => format!("p.read({})", address),
+            Mode::Relative(offset) => format!("p.read(rel!(p, {}, {}))", offset, self.addr)
+        };
+        self.line(&format!("let {} = {};", name, read));
+        name.to_string()
+    }
+
+    /// Bind a relative destination address to `d`. Positional destinations are used as is.
+    fn dest(&mut self, param: Mode) {
+        if let Mode::Relative(offset) = param {
+            self.line(&format!("let d = rel!(p, {}, {});", offset, self.addr));
+        }
+    }
+
+    /// Store `value` to `dest`, leaving for the interpreter if that overwrote translated code.
+    /// Returns false if the write always lands in translated code.
+    fn store(&mut self, dest: Mode, value: &str, next: Pos) -> bool {
+        match dest {
+            Mode::Positional(address) => {
+                self.line(&format!("p.write({}, {});", address, value));
+                if let Some(block) = (self.block_at)(address) {
+                    self.line(&format!("self.modified.insert({});", block));
+                    self.line(&format!("p.ip = {};", next));
+                    self.line("continue;");
+                    return false;
+                }
+            }
+            _ => {
+                self.line(&format!("p.write(d, {});", value));
+                self.line("if let Some(block) = block_at(d) {");
+                self.line("    self.modified.insert(block);");
+                self.line(&format!("    p.ip = {};", next));
+                self.line("    continue;");
+                self.line("}");
+            }
+        }
+        true
+    }
+
+    /// Emit the instruction. Returns false if execution never reaches the next instruction.
+    fn insn(&mut self, op: Opcode) -> bool {
+        let _ = writeln!(self.code, "                    // {:04}  {}", self.addr, op);
+        let next = self.addr + op.len();
+        match op {
+            Opcode::Add(a, b, dest) | Opcode::Mul(a, b, dest) | Opcode::LessThan(a, b, dest)
+            | Opcode::Equals(a, b, dest) => {
+                if let Mode::Immediate(_) = dest {
+                    self.interpret();
+                    return false;
+                }
+                let a = self.value(a, "a");
+                let b = self.value(b, "b");
+                self.dest(dest);
+                match op {
+                    Opcode::Add(..) | Opcode::Mul(..) => {
+                        let method = if matches!(op, Opcode::Add(..)) { "checked_add" } else { "checked_mul" };
+                        self.line(&format!("let v = match isize::{}({}, {}) {{", method, a, b));
+                        self.line("    Some(v) => v,");
+                        self.line(&format!("    None => interpret!(p, {})", self.addr));
+                        self.line("};");
+                    }
+                    Opcode::LessThan(..) => self.line(&format!("let v = isize::from({} < {});", a, b)),
+                    _ => self.line(&format!("let v = isize::from({} == {});", a, b))
+                }
+                self.store(dest, "v", next)
+            }
+            Opcode::In(dest) => {
+                if let Mode::Immediate(_) = dest {
+                    self.interpret();
+                    return false;
+                }
+                self.dest(dest);
+                self.line("let v = match p.read_input() {");
+                self.line("    Some(v) => v,");
+                self.line("    None => return Ok(ExitReason::NeedsInput)");
+                self.line("};");
+                self.store(dest, "v", next)
+            }
+            Opcode::Out(a) => {
+                let a = self.value(a, "a");
+                self.line(&format!("p.write_output({});", a));
+                true
+            }
+            Opcode::AdjustRelativeBase(a) => {
+                let a = self.value(a, "a");
+                self.line(&format!("match p.relative_base.checked_add({}) {{", a));
+                self.line("    Some(rb) => p.relative_base = rb,");
+                self.line(&format!("    None => interpret!(p, {})", self.addr));
+                self.line("}");
+                true
+            }
+            Opcode::JumpNonZero(cond, target) | Opcode::JumpZero(cond, target) => {
+                let nonzero = matches!(op, Opcode::JumpNonZero(..));
+                if let Mode::Immediate(value) = cond {
+                    if (value != 0) != nonzero {
+                        // Never taken, but reading a relative target may still fault
+                        if let Mode::Relative(offset) = target {
+                            self.line(&format!("let _ = rel!(p, {}, {});", offset, self.addr));
+                        }
+                        return true;
+                    }
+                }
+
+                // Condition of a conditional jump, `None` if the jump is always taken
+                let taken = match cond {
+                    Mode::Immediate(_) => None,
+                    _ => {
+                        let cond = self.value(cond, "a");
+                        Some(format!("{} {} 0", cond, if nonzero { "!=" } else { "==" }))
+                    }
+                };
+                match (target, &taken) {
+                    (Mode::Immediate(target), _) if target < 0 => {
+                        // Either a fault or the next instruction
+                        match &taken {
+                            Some(taken) => self.line(&format!("if {} {{ interpret!(p, {}) }}", taken, self.addr)),
+                            None => self.interpret()
+                        }
+                        taken.is_some()
+                    }
+                    (Mode::Immediate(target), Some(taken)) => {
+                        self.line(&format!("p.ip = if {} {{ {} }} else {{ {} }};", taken, target, next));
+                        self.line("continue;");
+                        false
+                    }
+                    (Mode::Immediate(target), None) => {
+                        self.line(&format!("p.ip = {};", target));
+                        self.line("continue;");
+                        false
+                    }
+                    (target, _) => {
+                        let target = self.value(target, "b");
+                        let indent = if taken.is_some() { "    " } else { "" };
+                        if let Some(taken) = &taken {
+                            self.line(&format!("if {} {{", taken));
+                        }
+                        self.line(&format!("{}if {} < 0 {{ interpret!(p, {}) }}", indent, target, self.addr));
+                        self.line(&format!("{}p.ip = {} as Pos;", indent, target));
+                        self.line(&format!("{}continue;", indent));
+                        if taken.is_some() {
+                            self.line("}");
+                        }
+                        taken.is_some()
+                    }
+                }
+            }
+            Opcode::Halt => {
+                self.line(&format!("p.ip = {};", self.addr));
+                self.line("p.halted = true;");
+                self.line("return Ok(ExitReason::Halted);");
+                false
+            }
+        }
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Translate the code reachable from address 0 ahead of time into a standalone Rust module
+    pub fn translate(&self) -> String {
+        self.translate_from(&[0])
+    }
+
+    /// Translate the code reachable from any of `entries` ahead of time into a standalone Rust
+    /// module. Extra entries are useful for code only reached through jump tables.
+    ///
+    /// The module embeds the current memory image as `IMAGE` and defines a `Translated` wrapper
+    /// around a [`Program`] whose `run` dispatches on the IP to one match arm per basic block.
+    /// Return addresses found by [`Program::recover_functions`] are entries too, and blocks are
+    /// split before every `In` so that execution resumes in translated code after
+    /// [`ExitReason::NeedsInput`](crate::ExitReason).
+    ///
+    /// Anything else runs on the interpreter, one instruction at a time: code that was not found,
+    /// faults, and every block the program has written into. Translated code does not record the
+    /// undo journal or the shadow call stack.
+    ///
+    /// The module refers to the crate as `intcode` and can be compiled with `include!`.
+    pub fn translate_from(&self, entries: &[Pos]) -> String {
+        let mut entries = entries.to_vec();
+        entries.extend(self.recover_functions().cfg.blocks.keys());
+        let cfg = self.control_flow_graph_from(&entries);
+
+        // Split blocks before every `In`
+        let mut blocks: BTreeMap<Pos, Vec<(Pos, Opcode)>> = BTreeMap::new();
+        for block in cfg.blocks.values() {
+            let mut start = block.start;
+            for &(addr, op) in &block.insns {
+                if let Opcode::In(_) = op {
+                    start = addr;
+                }
+                blocks.entry(start).or_default().push((addr, op));
+            }
+        }
+        let bounds: Vec<(Pos, Pos)> = blocks.iter().map(|(&start, insns)| {
+            let &(addr, op) = insns.last().expect("blocks are never empty");
+            (start, addr + op.len())
+        }).collect();
+        let block_at = |address: Pos| {
+            bounds.iter().find(|&&(start, end)| (start..end).contains(&address)).map(|&(start, _)| start)
+        };
+
+        let image: Vec<String> = (0..self.image_end()).map(|address| self.read(address).to_string()).collect();
+
+        let mut code = String::new();
+        let _ = writeln!(code, "// Generated by `intcode::Program::translate`. Do not edit.");
+        let _ = writeln!(code, "//");
+        let _ = writeln!(code, "// {} words of code in {} block{}, translated from a {} word image.",
+                         bounds.iter().map(|(start, end)| end - start).sum::<usize>(), blocks.len(),
+                         if blocks.len() == 1 { "" } else { "s" }, image.len());
+        code.push_str("\nuse std::collections::{BTreeSet, VecDeque};\n\n");
+        code.push_str("use intcode::{Event, ExitReason, InputSource, Memory, Mode, Opcode, OutputSink, PagedMemory, \
+                       Pos, Program, VmError};\n\n");
+
+        code.push_str("/// Memory image the module was translated from\npub const IMAGE: &[isize] = &[\n");
+        for chunk in image.chunks(16) {
+            let _ = writeln!(code, "    {},", chunk.join(", "));
+        }
+        code.push_str("];\n\n");
+
+        code.push_str("/// Start and end address of every translated block\nconst BLOCKS: &[(Pos, Pos)] = &[\n");
+        let bounds_text: Vec<String> = bounds.iter().map(|(start, end)| format!("({}, {})", start, end)).collect();
+        for chunk in bounds_text.chunks(8) {
+            let _ = writeln!(code, "    {},", chunk.join(", "));
+        }
+        code.push_str("];\n");
+        code.push_str(PRELUDE);
+
+        for (&start, insns) in &blocks {
+            let _ = writeln!(code, "                {} => {{", start);
+            let mut falls_through = true;
+            for &(addr, op) in insns {
+                let mut emitter = Emitter { code: &mut code, addr, block_at: &block_at };
+                if !emitter.insn(op) {
+                    falls_through = false;
+                    break;
+                }
+            }
+            if falls_through {
+                let &(addr, op) = insns.last().expect("blocks are never empty");
+                let _ = writeln!(code, "                    p.ip = {};\n                    continue;", addr + op.len());
+            }
+            code.push_str("                }\n");
+        }
+        code.push_str(EPILOGUE);
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        // in -> [9]; jz [9], #7; out [9]; halt
+        let program = Program::from_input("3,9,1006,9,7,4,9,99");
+        let code = program.translate();
+        assert!(code.contains("pub const IMAGE: &[isize] = &[\n    3, 9, 1006, 9, 7, 4, 9, 99,\n];"));
+        assert!(code.contains("const BLOCKS: &[(Pos, Pos)] = &[\n    (0, 5), (5, 7), (7, 8),\n];"));
+        assert!(code.contains("\
+                0 => {
+                    // 0000  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0002  jz [9], #7
+                    let a = p.read(9);
+                    p.ip = if a == 0 { 7 } else { 5 };
+                    continue;
+                }
+"));
+    }
+}
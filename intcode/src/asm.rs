@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Precise reason a line of assembly was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    /// The mnemonic or directive is not known
+    UnknownMnemonic(String),
+
+    /// An instruction or directive was given the wrong number of operands
+    OperandCount { expected: usize, found: usize },
+
+    /// An operand or expression could not be parsed
+    Syntax(String),
+
+    /// A destination operand is immediate
+    ImmediateDestination,
+
+    /// A positional operand evaluated to a negative address
+    NegativeAddress(Imm),
+
+    /// An expression refers to a symbol that is never defined
+    UndefinedSymbol(String),
+
+    /// A label or constant is defined twice
+    DuplicateSymbol(String),
+
+    /// A constant is defined in terms of itself
+    RecursiveSymbol(String),
+
+    /// An expression overflowed or divided by zero
+    Arithmetic
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(name) => write!(f, "unknown mnemonic `{}`", name),
+            AsmErrorKind::OperandCount { expected, found } =>
+                write!(f, "expected {} operand(s), found {}", expected, found),
+            AsmErrorKind::Syntax(text) => write!(f, "syntax error: {}", text),
+            AsmErrorKind::ImmediateDestination => write!(f, "immediate mode destination"),
+            AsmErrorKind::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            AsmErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol `{}`", name),
+            AsmErrorKind::DuplicateSymbol(name) => write!(f, "symbol `{}` defined twice", name),
+            AsmErrorKind::RecursiveSymbol(name) => write!(f, "symbol `{}` is defined in terms of itself", name),
+            AsmErrorKind::Arithmetic => write!(f, "arithmetic overflow or division by zero")
+        }
+    }
+}
+
+/// Error assembling a program, with the 1-based line it occurred on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Constant expression over integers and symbols
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(Imm),
+    Symbol(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>)
+}
+
+/// Recursive descent parser for expressions:
+///
+/// ```text
+/// expr   := term (('+' | '-') term)*
+/// term   := unary (('*' | '/' | '%') unary)*
+/// unary  := ('-' | '+') unary | atom
+/// atom   := number | symbol | '(' expr ')'
+/// ```
+struct ExprParser<'a> {
+    text: &'a str,
+    pos: usize
+}
+
+impl<'a> ExprParser<'a> {
+    fn parse(text: &str) -> Result<Expr, AsmErrorKind> {
+        let mut parser = ExprParser { text, pos: 0 };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if parser.pos != text.len() {
+            return Err(AsmErrorKind::Syntax(format!("unexpected `{}`", &text[parser.pos..])));
+        }
+        Ok(expr)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.text[self.pos..].chars().next()
+    }
+
+    fn expr(&mut self) -> Result<Expr, AsmErrorKind> {
+        let mut left = self.term()?;
+        while let Some(op) = self.peek().filter(|c| matches!(c, '+' | '-')) {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, AsmErrorKind> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.peek().filter(|c| matches!(c, '*' | '/' | '%')) {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, AsmErrorKind> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            Some('+') => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, AsmErrorKind> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                if self.peek() != Some(')') {
+                    return Err(AsmErrorKind::Syntax("missing `)`".to_string()));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let token = self.token();
+                let value = match token.strip_prefix("0x") {
+                    Some(hex) => Imm::from_str_radix(hex, 16),
+                    None => token.parse()
+                };
+                value.map(Expr::Number).map_err(|_| AsmErrorKind::Syntax(format!("invalid number `{}`", token)))
+            }
+            Some(c) if is_symbol_start(c) => Ok(Expr::Symbol(self.token().to_string())),
+            Some(c) => Err(AsmErrorKind::Syntax(format!("unexpected `{}`", c))),
+            None => Err(AsmErrorKind::Syntax("missing operand".to_string()))
+        }
+    }
+
+    /// Consume a run of symbol characters
+    fn token(&mut self) -> &'a str {
+        let rest = &self.text[self.pos..];
+        let len = rest.find(|c: char| !is_symbol_char(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+}
+
+fn is_symbol_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '.'
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn is_symbol(name: &str) -> bool {
+    name.starts_with(is_symbol_start) && name.chars().all(is_symbol_char)
+}
+
+/// Operand before symbols are resolved
+#[derive(Debug, Clone)]
+enum Operand {
+    Positional(Expr),
+    Immediate(Expr),
+    Relative(Expr)
+}
+
+impl Operand {
+    /// Parse `[expr]`, `#expr`, `[rb]`, `[rb+expr]`, `[rb-expr]` or a bare immediate `expr`
+    fn parse(text: &str) -> Result<Operand, AsmErrorKind> {
+        let text = text.trim();
+        if let Some(imm) = text.strip_prefix('#') {
+            return Ok(Operand::Immediate(ExprParser::parse(imm)?));
+        }
+
+        let inner = match text.strip_prefix('[') {
+            Some(rest) => rest.strip_suffix(']')
+                              .ok_or_else(|| AsmErrorKind::Syntax(format!("missing `]` in `{}`", text)))?
+                              .trim(),
+            None => return Ok(Operand::Immediate(ExprParser::parse(text)?))
+        };
+
+        match inner.strip_prefix("rb") {
+            Some("") => Ok(Operand::Relative(Expr::Number(0))),
+            Some(offset) if offset.trim_start().starts_with(['+', '-']) => {
+                Ok(Operand::Relative(ExprParser::parse(offset)?))
+            }
+            _ => Ok(Operand::Positional(ExprParser::parse(inner)?))
+        }
+    }
+}
+
+/// Parsed source line that emits words
+#[derive(Debug, Clone)]
+enum Item {
+    Insn(&'static str, Vec<Operand>),
+    Data(Vec<Expr>)
+}
+
+/// Number of operands and the index of the destination operand, if any
+fn signature(mnemonic: &str) -> Option<(&'static str, usize, Option<usize>)> {
+    let signature = match mnemonic {
+        "add" => ("add", 3, Some(2)),
+        "mul" => ("mul", 3, Some(2)),
+        "lt" => ("lt", 3, Some(2)),
+        "eq" => ("eq", 3, Some(2)),
+        "in" => ("in", 1, Some(0)),
+        "out" => ("out", 1, None),
+        "jnz" => ("jnz", 2, None),
+        "jz" => ("jz", 2, None),
+        "arb" => ("arb", 1, None),
+        "halt" => ("halt", 0, None),
+        _ => return None
+    };
+    Some(signature)
+}
+
+/// Split a comma separated operand list, returning nothing for an empty list
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').map(str::trim).collect()
+    }
+}
+
+/// Symbol table built by the first pass
+#[derive(Default)]
+struct Symbols {
+    labels: HashMap<String, Imm>,
+    constants: HashMap<String, (usize, Expr)>
+}
+
+impl Symbols {
+    fn define_label(&mut self, name: &str, addr: Pos) -> Result<(), AsmErrorKind> {
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(AsmErrorKind::DuplicateSymbol(name.to_string()));
+        }
+        self.labels.insert(name.to_string(), addr as Imm);
+        Ok(())
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<Imm, AsmErrorKind> {
+        self.eval_depth(expr, &mut Vec::new())
+    }
+
+    /// Evaluate `expr`, keeping the constants being expanded in `stack` to catch cycles
+    fn eval_depth<'a>(&'a self, expr: &'a Expr, stack: &mut Vec<&'a str>) -> Result<Imm, AsmErrorKind> {
+        match expr {
+            Expr::Number(value) => Ok(*value),
+            Expr::Symbol(name) => {
+                if let Some(&addr) = self.labels.get(name) {
+                    return Ok(addr);
+                }
+                let (_, value) = self.constants.get(name)
+                                     .ok_or_else(|| AsmErrorKind::UndefinedSymbol(name.clone()))?;
+                if stack.contains(&name.as_str()) {
+                    return Err(AsmErrorKind::RecursiveSymbol(name.clone()));
+                }
+                stack.push(name);
+                let value = self.eval_depth(value, stack);
+                stack.pop();
+                value
+            }
+            Expr::Neg(expr) => self.eval_depth(expr, stack)?.checked_neg().ok_or(AsmErrorKind::Arithmetic),
+            Expr::Binary(op, left, right) => {
+                let left = self.eval_depth(left, stack)?;
+                let right = self.eval_depth(right, stack)?;
+                let value = match op {
+                    '+' => left.checked_add(right),
+                    '-' => left.checked_sub(right),
+                    '*' => left.checked_mul(right),
+                    '/' => left.checked_div(right),
+                    '%' => left.checked_rem(right),
+                    _ => unreachable!()
+                };
+                value.ok_or(AsmErrorKind::Arithmetic)
+            }
+        }
+    }
+
+    fn mode(&self, operand: &Operand) -> Result<Mode, AsmErrorKind> {
+        match operand {
+            Operand::Positional(expr) => match self.eval(expr)? {
+                addr if addr < 0 => Err(AsmErrorKind::NegativeAddress(addr)),
+                addr => Ok(Mode::Positional(addr as usize))
+            },
+            Operand::Immediate(expr) => Ok(Mode::Immediate(self.eval(expr)?)),
+            Operand::Relative(expr) => Ok(Mode::Relative(self.eval(expr)?))
+        }
+    }
+}
+
+/// Build the opcode for a mnemonic from resolved operands
+fn opcode(mnemonic: &str, modes: &[Mode]) -> Opcode {
+    match (mnemonic, modes) {
+        ("add", &[a, b, c]) => Opcode::Add(a, b, c),
+        ("mul", &[a, b, c]) => Opcode::Mul(a, b, c),
+        ("lt", &[a, b, c]) => Opcode::LessThan(a, b, c),
+        ("eq", &[a, b, c]) => Opcode::Equals(a, b, c),
+        ("in", &[a]) => Opcode::In(a),
+        ("out", &[a]) => Opcode::Out(a),
+        ("jnz", &[a, b]) => Opcode::JumpNonZero(a, b),
+        ("jz", &[a, b]) => Opcode::JumpZero(a, b),
+        ("arb", &[a]) => Opcode::AdjustRelativeBase(a),
+        ("halt", &[]) => Opcode::Halt,
+        _ => unreachable!()
+    }
+}
+
+/// Assemble a program into Intcode words.
+///
+/// Each line holds optional labels, then an instruction or directive, then an optional `;`
+/// comment:
+///
+/// ```text
+/// limit = 10               ; constant, also written `.equ limit, 10`
+/// loop:                    ; label, the address of the next word
+///     add [count], #1 -> [count]
+///     lt [count], #limit -> [rb+2]
+///     jnz [rb+2], loop     ; a bare expression is immediate
+///     out [count]
+///     halt
+/// count: .data 0
+/// ```
+///
+/// Operands are `[expr]` (positional), `#expr` (immediate) or `[rb+expr]` (relative). The
+/// destination of `add`, `mul`, `lt`, `eq` and `in` may follow `->` or be the last operand.
+/// Expressions combine numbers, labels and constants with `+ - * / %` and parentheses. The
+/// listing printed by the disassembler is valid input.
+pub fn assemble(source: &str) -> Result<Vec<Imm>, AsmError> {
+    let mut symbols = Symbols::default();
+    let mut items = Vec::new();
+    let mut addr: Pos = 0;
+
+    // First pass: assign addresses to labels and parse every line
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let error = |kind| AsmError { line: number, kind };
+        let mut text = line.split(';').next().unwrap_or("").trim();
+
+        // Leading labels
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_symbol(label) {
+                break;
+            }
+            symbols.define_label(label, addr).map_err(error)?;
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        // Constants
+        let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let constant = match text.split_once('=') {
+            Some((name, value)) if is_symbol(name.trim()) => Some((name.trim(), value)),
+            _ if name == ".equ" => match split_operands(rest)[..] {
+                [name, value] if is_symbol(name) => Some((name, value)),
+                _ => return Err(error(AsmErrorKind::Syntax("expected `.equ name, value`".to_string())))
+            },
+            _ => None
+        };
+        if let Some((name, value)) = constant {
+            if symbols.labels.contains_key(name) || symbols.constants.contains_key(name) {
+                return Err(error(AsmErrorKind::DuplicateSymbol(name.to_string())));
+            }
+            let value = ExprParser::parse(value).map_err(error)?;
+            symbols.constants.insert(name.to_string(), (number, value));
+            continue;
+        }
+
+        if name == ".data" {
+            let values = split_operands(rest).into_iter().map(ExprParser::parse)
+                                             .collect::<Result<Vec<Expr>, _>>().map_err(error)?;
+            addr += values.len();
+            items.push((number, Item::Data(values)));
+            continue;
+        }
+
+        let (mnemonic, count, dest) = signature(name)
+            .ok_or_else(|| error(AsmErrorKind::UnknownMnemonic(name.to_string())))?;
+        let mut operands = match rest.split_once("->") {
+            Some((sources, dest)) => {
+                let mut operands = split_operands(sources);
+                operands.push(dest.trim());
+                operands
+            }
+            None => split_operands(rest)
+        };
+        if operands.len() != count {
+            return Err(error(AsmErrorKind::OperandCount { expected: count, found: operands.len() }));
+        }
+
+        let operands = operands.drain(..).map(Operand::parse).collect::<Result<Vec<_>, _>>().map_err(error)?;
+        if let Some(Operand::Immediate(_)) = dest.map(|index| &operands[index]) {
+            return Err(error(AsmErrorKind::ImmediateDestination));
+        }
+
+        addr += 1 + count;
+        items.push((number, Item::Insn(mnemonic, operands)));
+    }
+
+    // Report broken constants even if they are never used
+    let mut constants: Vec<&(usize, Expr)> = symbols.constants.values().collect();
+    constants.sort_by_key(|&&(number, _)| number);
+    for (number, value) in constants {
+        symbols.eval(value).map_err(|kind| AsmError { line: *number, kind })?;
+    }
+
+    // Second pass: resolve expressions and encode
+    let mut words = Vec::with_capacity(addr);
+    for (number, item) in items {
+        let error = |kind| AsmError { line: number, kind };
+        match item {
+            Item::Data(values) => {
+                for value in values {
+                    words.push(symbols.eval(&value).map_err(error)?);
+                }
+            }
+            Item::Insn(mnemonic, operands) => {
+                let modes = operands.iter().map(|operand| symbols.mode(operand))
+                                    .collect::<Result<Vec<Mode>, _>>().map_err(error)?;
+                words.extend(opcode(mnemonic, &modes).encode());
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+/// Format words in the comma separated format read by [`Program::from_input`]
+pub fn to_intcode(words: &[Imm]) -> String {
+    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    words.join(",")
+}
+
+impl Program {
+    /// Assemble `source` and load it, see [`assemble`] for the syntax
+    pub fn from_assembly(source: &str) -> Result<Program, AsmError> {
+        Ok(Program::from_input(&to_intcode(&assemble(source)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::Strategy;
+
+    #[test]
+    fn test_day5_example() {
+        // Output 1 if the input equals 8, 0 otherwise
+        let source = "
+                in -> [input]
+                eq [input], [eight] -> [input]
+                out [input]
+                halt
+            input: .data -1
+            eight: .data 8";
+        assert_eq!(to_intcode(&assemble(source).unwrap()), "3,9,8,9,10,9,4,9,99,-1,8");
+    }
+
+    #[test]
+    fn test_operand_syntax() {
+        let source = "
+            base = 100
+            arb #base * 2
+            add [rb+3], #5 -> [base]
+            mul [rb - 1], 2, [rb]
+            jz #0, end
+            end: halt";
+        let words = assemble(source).unwrap();
+        assert_eq!(to_intcode(&words), "109,200,1201,3,5,100,21202,-1,2,0,1106,0,13,99");
+
+        let mut program = Program::from_assembly(source).unwrap();
+        program.run().unwrap();
+        assert_eq!(program.read(100), 5);
+    }
+
+    #[test]
+    fn test_labels_and_constants() {
+        let source = "
+            .equ limit, count - start + 2
+            start: loop: add [count], #1 -> [count]
+                lt [count], #limit -> [flag]
+                jnz [flag], loop
+                out [count]
+                halt
+            count: .data 0
+            flag: .data 0, (3 + 4) * 2 % 5, 0x10";
+        let mut program = Program::from_assembly(source).unwrap();
+        program.run().unwrap();
+        assert_eq!(program.output, vec![16]);
+        assert_eq!(program.read(16), 4);
+        assert_eq!(program.read(17), 16);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(error("nop"), AsmError { line: 1, kind: AsmErrorKind::UnknownMnemonic("nop".to_string()) });
+        assert_eq!(error("halt\nadd #1, #2").kind, AsmErrorKind::OperandCount { expected: 3, found: 2 });
+        assert_eq!(error("add #1, #2 -> #3").kind, AsmErrorKind::ImmediateDestination);
+        assert_eq!(error("out [0 - 1]").kind, AsmErrorKind::NegativeAddress(-1));
+        assert_eq!(error("out [x]").kind, AsmErrorKind::UndefinedSymbol("x".to_string()));
+        assert_eq!(error("x: halt\nx: halt").kind, AsmErrorKind::DuplicateSymbol("x".to_string()));
+        assert_eq!(error("a = b\nb = a + 1"), AsmError { line: 1, kind: AsmErrorKind::RecursiveSymbol("b".to_string()) });
+        assert_eq!(error("out #1 / 0").kind, AsmErrorKind::Arithmetic);
+        assert!(matches!(error("out [5").kind, AsmErrorKind::Syntax(_)));
+        assert!(matches!(error("out #(1 + 2").kind, AsmErrorKind::Syntax(_)));
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let input = include_str!("../../day09/input");
+        let original = Program::from_input(input);
+        for &strategy in &[Strategy::RecursiveDescent, Strategy::LinearSweep] {
+            let listing = original.disassemble(strategy).to_string();
+            let words = assemble(&listing).unwrap();
+            let image: Vec<Imm> = input.trim().split(',').map(|x| x.parse().unwrap()).collect();
+            assert_eq!(words[..], image[..words.len()]);
+            assert!(image[words.len()..].iter().all(|&word| word == 0));
+        }
+    }
+}
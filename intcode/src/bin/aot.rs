@@ -0,0 +1,40 @@
+//! Translate an Intcode program ahead of time into a Rust module
+//!
+//! Usage: `aot [--entries=<addr>,...] <input file>`. The module is printed to stdout and can be
+//! compiled with `include!` in any crate depending on `intcode`. `--entries` adds entry points
+//! besides address 0, for code only reached through jump tables.
+
+use intcode::{Pos, Program};
+
+fn main() {
+    let mut entries: Vec<Pos> = vec![0];
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.strip_prefix("--entries=") {
+            Some(list) => {
+                for entry in list.split(',') {
+                    entries.push(entry.parse().unwrap_or_else(|_| {
+                        eprintln!("Invalid entry point: {}", entry);
+                        std::process::exit(1);
+                    }));
+                }
+            }
+            None => path = Some(arg)
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: aot [--entries=<addr>,...] <input file>");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    print!("{}", program.translate_from(&entries));
+}
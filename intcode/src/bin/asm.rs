@@ -0,0 +1,22 @@
+//! Assemble an Intcode program into the comma separated format read by the puzzle days
+//!
+//! Usage: `asm <source file>`. See `intcode::assemble` for the syntax.
+
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("Usage: asm <source file>");
+        std::process::exit(1);
+    });
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    match intcode::assemble(&source) {
+        Ok(words) => println!("{}", intcode::to_intcode(&words)),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
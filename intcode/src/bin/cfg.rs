@@ -0,0 +1,37 @@
+//! Print the control-flow graph of an Intcode program
+//!
+//! Usage: `cfg [--json] <input file>`. Writes Graphviz DOT by default, render it with
+//! `cfg input | dot -Tsvg > cfg.svg`. `--json` writes JSON instead.
+
+use intcode::Program;
+
+fn main() {
+    let mut json = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ => path = Some(arg)
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: cfg [--json] <input file>");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    let cfg = program.control_flow_graph();
+    if json {
+        print!("{}", cfg.to_json());
+    } else {
+        print!("{}", cfg.to_dot());
+    }
+}
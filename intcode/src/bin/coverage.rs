@@ -0,0 +1,65 @@
+//! Measure which parts of an Intcode program a set of inputs exercises
+//!
+//! Usage: `coverage [--lcov] [--linear] <input file> [<inputs>...]`. Each `<inputs>` argument is
+//! one run given as comma separated input values, for example `coverage day05/input 1 5`. Without
+//! any, the program runs once with no input. The counts of all runs are merged and printed over
+//! the disassembly, `--lcov` writes an lcov tracefile keyed by address instead. `--linear`
+//! disassembles every address like `disasm --linear`, which finds more of a program that patches
+//! its own code.
+
+use intcode::{Coverage, Program, Strategy};
+
+fn main() {
+    let mut lcov = false;
+    let mut strategy = Strategy::RecursiveDescent;
+    let mut path = None;
+    let mut runs = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--lcov" => lcov = true,
+            "--linear" => strategy = Strategy::LinearSweep,
+            _ if path.is_none() => path = Some(arg),
+            _ => runs.push(arg)
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: coverage [--lcov] [--linear] <input file> [<inputs>...]");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+    if runs.is_empty() {
+        runs.push(String::new());
+    }
+
+    let mut coverage = Coverage::default();
+    for run in &runs {
+        let inputs: Vec<isize> = run.split(',').filter(|value| !value.is_empty())
+            .map(|value| value.trim().parse().unwrap_or_else(|_| {
+                eprintln!("Invalid input value: {}", value);
+                std::process::exit(1);
+            }))
+            .collect();
+
+        let mut program = program.clone().with_coverage();
+        program.input.extend(inputs);
+        if let Err(err) = program.run() {
+            eprintln!("Run with inputs [{}]: {}", run, err);
+        }
+        coverage.merge(program.coverage.as_ref().unwrap());
+    }
+
+    let listing = program.disassemble(strategy);
+    if lcov {
+        print!("{}", coverage.to_lcov(&path, &listing));
+    } else {
+        print!("{}", coverage.report(&listing));
+    }
+}
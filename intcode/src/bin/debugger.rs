@@ -0,0 +1,68 @@
+//! Interactive debugger for Intcode programs
+//!
+//! Usage: `debugger <input file>`. Type `help` at the prompt for the list of commands. An empty
+//! line repeats the previous command.
+
+use std::io::{self, BufRead, Write};
+
+use intcode::{Command, Debugger, Program};
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: debugger <input file>");
+            std::process::exit(1);
+        }
+    };
+
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    let mut debugger = Debugger::new(program.with_call_stack());
+    println!("{}", debugger.execute(&Command::List(None, 1)));
+
+    let stdin = io::stdin();
+    let mut last = None;
+    loop {
+        print!("(intcode) ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        let command = if line.trim().is_empty() {
+            match last.take() {
+                Some(command) => command,
+                None => continue
+            }
+        } else {
+            match Command::parse(&line) {
+                Ok(command) => command,
+                Err(err) => {
+                    println!("{}", err);
+                    continue;
+                }
+            }
+        };
+
+        if command == Command::Quit {
+            break;
+        }
+
+        let output = debugger.execute(&command);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        last = Some(command);
+    }
+}
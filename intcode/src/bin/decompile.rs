@@ -0,0 +1,23 @@
+//! Print structured pseudo-code of an Intcode program
+//!
+//! Usage: `decompile <input file>`. Functions are recovered from the calling convention and
+//! named after their entry point, the program entry point is `F0`.
+
+use intcode::Program;
+
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("Usage: decompile <input file>");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    print!("{}", program.decompile());
+}
@@ -0,0 +1,32 @@
+//! Print an annotated assembly listing of an Intcode program
+//!
+//! Usage: `disasm [--linear] <input file>`. By default only code reachable from address 0 is
+//! disassembled, `--linear` decodes every address in order instead.
+
+use intcode::{Program, Strategy};
+
+fn main() {
+    let mut strategy = Strategy::RecursiveDescent;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--linear" => strategy = Strategy::LinearSweep,
+            _ => path = Some(arg)
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: disasm [--linear] <input file>");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    print!("{}", program.disassemble(strategy));
+}
@@ -0,0 +1,80 @@
+//! Fuzz the input of an Intcode program
+//!
+//! Usage: `fuzz [--iterations=N] [--budget=N] [--seed=N] <input file> <corpus dir> [<seed input>...]`.
+//! Each seed input is a comma separated list of input values. Inputs that reach new coverage are
+//! kept in `<corpus dir>/queue`, inputs that crash the program (minimised), exhaust the
+//! instruction budget or produce a new output sequence are saved to `crashes`, `hangs` and
+//! `outputs`. A corpus saved by an earlier run is picked up again.
+
+use intcode::{FindingKind, Fuzzer, Program};
+
+/// Parse the value of a `--name=N` option
+fn number(arg: &str, name: &str) -> u64 {
+    arg[name.len() + 3..].parse().unwrap_or_else(|_| {
+        eprintln!("Invalid value for --{}: {}", name, arg);
+        std::process::exit(1);
+    })
+}
+
+fn main() {
+    let mut iterations = 100_000;
+    let mut budget = None;
+    let mut seed = None;
+    let mut paths = Vec::new();
+    let mut seeds = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            _ if arg.starts_with("--iterations=") => iterations = number(&arg, "iterations"),
+            _ if arg.starts_with("--budget=") => budget = Some(number(&arg, "budget")),
+            _ if arg.starts_with("--seed=") => seed = Some(number(&arg, "seed")),
+            _ if paths.len() < 2 => paths.push(arg),
+            _ => seeds.push(arg.split(',').map(|value| value.trim().parse::<isize>()).collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|_| {
+                    eprintln!("Invalid seed input: {}", arg);
+                    std::process::exit(1);
+                }))
+        }
+    }
+
+    if paths.len() < 2 {
+        eprintln!("Usage: fuzz [--iterations=N] [--budget=N] [--seed=N] <input file> <corpus dir> [<seed input>...]");
+        std::process::exit(1);
+    }
+    let input = std::fs::read_to_string(&paths[0]).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", paths[0], err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", paths[0], err);
+        std::process::exit(1);
+    });
+
+    let mut fuzzer = Fuzzer::new(program);
+    if let Some(seed) = seed {
+        fuzzer = fuzzer.seed(seed);
+    }
+    if let Some(budget) = budget {
+        fuzzer.budget = budget;
+    }
+    let result = fuzzer.corpus_dir(&paths[1]).and_then(|mut fuzzer| {
+        for input in seeds {
+            fuzzer.add_input(input)?;
+        }
+        fuzzer.run(iterations)?;
+        Ok(fuzzer)
+    });
+    let fuzzer = result.unwrap_or_else(|err| {
+        eprintln!("Failed to write the corpus to {}: {}", paths[1], err);
+        std::process::exit(1);
+    });
+
+    for finding in fuzzer.findings() {
+        match finding.kind {
+            FindingKind::Crash(err) => println!("crash   {}: input {:?}", err, finding.input),
+            FindingKind::Budget => println!("hang    input {:?}", finding.input),
+            FindingKind::NovelOutput => println!("output  {:?}: input {:?}", finding.output, finding.input)
+        }
+    }
+    eprintln!("{} inputs in the corpus, {} coverage points, {} findings", fuzzer.corpus().len(), fuzzer.coverage(),
+              fuzzer.findings().len());
+}
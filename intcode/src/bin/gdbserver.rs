@@ -0,0 +1,70 @@
+//! GDB remote serial protocol server for Intcode programs
+//!
+//! Usage: `gdbserver <input file> <host:port | unix:path>`. Clients are served one after another
+//! against the same program, so a front-end can disconnect and attach again. See
+//! `intcode::GdbStub` for how Intcode state is mapped onto the protocol.
+
+use std::net::TcpListener;
+
+use intcode::{GdbStub, Program};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: gdbserver <input file> <host:port | unix:path>");
+        std::process::exit(1);
+    }
+
+    let input = std::fs::read_to_string(&args[1]).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", args[1], err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", args[1], err);
+        std::process::exit(1);
+    });
+    let mut stub = GdbStub::new(program);
+
+    if let Some(path) = args[2].strip_prefix("unix:") {
+        serve_unix(&mut stub, path);
+        return;
+    }
+
+    let listener = TcpListener::bind(&args[2]).unwrap_or_else(|err| {
+        eprintln!("Failed to listen on {}: {}", args[2], err);
+        std::process::exit(1);
+    });
+    println!("Listening on {}", args[2]);
+    for stream in listener.incoming() {
+        // Every packet is acknowledged before the reply, so avoid waiting on delayed ACKs
+        let served = stream.and_then(|stream| {
+            stream.set_nodelay(true)?;
+            stub.serve(stream)
+        });
+        match served {
+            Ok(()) => println!("Client detached"),
+            Err(err) => println!("Connection error: {}", err)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_unix(stub: &mut GdbStub, path: &str) {
+    let listener = std::os::unix::net::UnixListener::bind(path).unwrap_or_else(|err| {
+        eprintln!("Failed to listen on {}: {}", path, err);
+        std::process::exit(1);
+    });
+    println!("Listening on {}", path);
+    for stream in listener.incoming() {
+        match stream.and_then(|stream| stub.serve(stream)) {
+            Ok(()) => println!("Client detached"),
+            Err(err) => println!("Connection error: {}", err)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_stub: &mut GdbStub, _path: &str) {
+    eprintln!("Unix sockets are not supported on this platform");
+    std::process::exit(1);
+}
@@ -0,0 +1,47 @@
+//! Check an Intcode program for suspicious code without running it
+//!
+//! Usage: `lint [--json] [--deny-warnings] <input file>`. Prints one finding per line, or a JSON
+//! document with `--json`. Exits with status 1 if there are errors, or any findings at all with
+//! `--deny-warnings`, so it can gate CI.
+
+use intcode::{Program, Severity};
+
+fn main() {
+    let mut json = false;
+    let mut deny = Severity::Error;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--deny-warnings" => deny = Severity::Warning,
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: lint [--json] [--deny-warnings] <input file>");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    let report = program.lint();
+    if json {
+        print!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+    if report.severity().is_some_and(|severity| severity >= deny) {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,49 @@
+//! Count where an Intcode program spends its instructions
+//!
+//! Usage: `profile [--top=N] <input file> [<input value>...]`. Runs the program with the given
+//! input values until it halts or needs more input, then prints the `N` (10 by default) hottest
+//! instructions, basic blocks and loops and the instruction mix.
+
+use intcode::Program;
+
+fn main() {
+    let mut top = 10;
+    let mut path = None;
+    let mut inputs = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            _ if arg.starts_with("--top=") => {
+                top = arg["--top=".len()..].parse().unwrap_or_else(|_| {
+                    eprintln!("Invalid value for --top: {}", arg);
+                    std::process::exit(1);
+                });
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => inputs.push(arg.parse::<isize>().unwrap_or_else(|_| {
+                eprintln!("Invalid input value: {}", arg);
+                std::process::exit(1);
+            }))
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Usage: profile [--top=N] <input file> [<input value>...]");
+        std::process::exit(1);
+    });
+    let input = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    let mut run = program.clone().with_profile();
+    run.input.extend(inputs);
+    match run.run() {
+        Ok(reason) => eprintln!("{:?}, output {:?}", reason, run.output),
+        Err(err) => eprintln!("{}, output {:?}", err, run.output)
+    }
+    print!("{}", run.profile.as_ref().unwrap().report(run.coverage.as_ref().unwrap(), &program, top));
+}
@@ -0,0 +1,133 @@
+//! Record and inspect execution traces of Intcode programs
+//!
+//! Usage:
+//!
+//! * `trace record <input file> <trace file> [<input value>...]` runs the program with the given
+//!   input values and writes a binary trace of every executed instruction.
+//! * `trace view <trace file> [--addr=N] [--value=V] [--op=MNEMONIC] [--around=STEP] [--window=N]`
+//!   prints the trace. `--addr` keeps instructions at or writing to an address, `--value` keeps
+//!   instructions that read, wrote, input or output a value and `--op` keeps one kind of
+//!   instruction, such as `add` or `jnz`. `--around` only prints the `--window` steps (5 by
+//!   default) either side of a step.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use intcode::{Program, TraceReader};
+
+const USAGE: &str = "Usage: trace record <input file> <trace file> [<input value>...]\n\
+       trace view <trace file> [--addr=N] [--value=V] [--op=MNEMONIC] [--around=STEP] [--window=N]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+/// Parse the value of `--name=value`
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid value for {}: {}", name, value);
+        std::process::exit(1);
+    })
+}
+
+fn record(args: &[String]) {
+    let (path, trace_path) = match args {
+        [path, trace_path, ..] => (path, trace_path),
+        _ => usage()
+    };
+    let inputs: Vec<isize> = args[2..].iter().map(|arg| parse("input value", arg)).collect();
+
+    let input = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let program = Program::parse(&input).unwrap_or_else(|err| {
+        eprintln!("Failed to parse {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let file = File::create(trace_path).unwrap_or_else(|err| {
+        eprintln!("Failed to create {}: {}", trace_path, err);
+        std::process::exit(1);
+    });
+
+    let mut program = program.with_trace(file);
+    program.input.extend(inputs);
+    let exit = program.run();
+    let steps = program.finish_trace().unwrap_or_else(|err| {
+        eprintln!("Failed to write {}: {}", trace_path, err);
+        std::process::exit(1);
+    });
+
+    println!("Output: {:?}", program.output);
+    match exit {
+        Ok(reason) => println!("{:?} after {} instructions", reason, steps),
+        Err(err) => println!("{} after {} instructions", err, steps)
+    }
+}
+
+fn view(args: &[String]) {
+    let mut path = None;
+    let mut addr = None;
+    let mut value = None;
+    let mut op = None;
+    let mut around = None;
+    let mut window = 5;
+    for arg in args {
+        let (name, arg_value) = match arg.find('=') {
+            Some(index) => (&arg[..index], &arg[index + 1..]),
+            None => (arg.as_str(), "")
+        };
+        match name {
+            "--addr" => addr = Some(parse(name, arg_value)),
+            "--value" => value = Some(parse(name, arg_value)),
+            "--op" => op = Some(arg_value.to_string()),
+            "--around" => around = Some(parse::<u64>(name, arg_value)),
+            "--window" => window = parse(name, arg_value),
+            _ if name.starts_with("--") => usage(),
+            _ => path = Some(arg)
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+    let file = File::open(path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let reader = TraceReader::new(BufReader::new(file)).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        std::process::exit(1);
+    });
+
+    let first = around.map_or(0, |step| step.saturating_sub(window));
+    let last = around.map_or(u64::MAX, |step| step.saturating_add(window));
+    for entry in reader {
+        let entry = entry.unwrap_or_else(|err| {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        });
+        if entry.step < first {
+            continue;
+        }
+        if entry.step > last {
+            break;
+        }
+        if addr.is_some_and(|addr| !entry.touches(addr))
+                || value.is_some_and(|value| !entry.contains_value(value))
+                || op.as_ref().is_some_and(|op| op != entry.op.mnemonic()) {
+            continue;
+        }
+
+        let marker = if around == Some(entry.step) { "=>" } else { "  " };
+        println!("{}{}", marker, entry);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("record") => record(&args[1..]),
+        Some("view") => view(&args[1..]),
+        _ => usage()
+    }
+}
@@ -0,0 +1,353 @@
+use std::sync::Arc;
+
+use crate::error::{ErrorKind, VmError};
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Source operand with its parameter mode already resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Src {
+    Imm(Imm),
+    Pos(Pos),
+    Rel(isize)
+}
+
+/// Destination operand. Immediate destinations are never compiled into a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dst {
+    Pos(Pos),
+    Rel(isize)
+}
+
+impl From<Mode> for Src {
+    fn from(mode: Mode) -> Src {
+        match mode {
+            Mode::Immediate(imm) => Src::Imm(imm),
+            Mode::Positional(addr) => Src::Pos(addr),
+            Mode::Relative(rel) => Src::Rel(rel)
+        }
+    }
+}
+
+impl Dst {
+    fn new(mode: Mode) -> Option<Dst> {
+        match mode {
+            Mode::Immediate(_) => None,
+            Mode::Positional(addr) => Some(Dst::Pos(addr)),
+            Mode::Relative(rel) => Some(Dst::Rel(rel))
+        }
+    }
+}
+
+/// Straight-line instruction specialised from an [`Opcode`] when its block is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Insn {
+    /// [dest] = imm. Folded from an `Add`, `Mul`, `LessThan` or `Equals` of two immediates.
+    Store(Dst, Imm),
+
+    /// [dest] = src. Folded from an `Add` of 0 or a `Mul` by 1.
+    Copy(Src, Dst),
+
+    /// [dest] = [src] + imm with positional operands, the usual counter update
+    AddPosImm(Pos, Imm, Pos),
+
+    Add(Src, Src, Dst),
+    Mul(Src, Src, Dst),
+    LessThan(Src, Src, Dst),
+    Equals(Src, Src, Dst),
+    AdjustRelativeBase(Src)
+}
+
+impl Insn {
+    /// Specialise a lifted instruction. Returns `None` for instructions that end a block: jumps,
+    /// I/O, halt, and instructions that will fault on an immediate destination.
+    pub fn compile(op: Opcode) -> Option<Insn> {
+        use Src::*;
+
+        let insn = match op {
+            Opcode::Add(param1, param2, dest) => {
+                let dest = Dst::new(dest)?;
+                match (Src::from(param1), Src::from(param2)) {
+                    (Imm(a), Imm(b)) if a.checked_add(b).is_some() => Insn::Store(dest, a + b),
+                    (src, Imm(0)) | (Imm(0), src) => Insn::Copy(src, dest),
+                    (Pos(a), Imm(b)) | (Imm(b), Pos(a)) => match dest {
+                        Dst::Pos(dest) => Insn::AddPosImm(a, b, dest),
+                        _ => Insn::Add(Pos(a), Imm(b), dest)
+                    },
+                    (a, b) => Insn::Add(a, b, dest)
+                }
+            }
+            Opcode::Mul(param1, param2, dest) => {
+                let dest = Dst::new(dest)?;
+                match (Src::from(param1), Src::from(param2)) {
+                    (Imm(a), Imm(b)) if a.checked_mul(b).is_some() => Insn::Store(dest, a * b),
+                    (src, Imm(1)) | (Imm(1), src) => Insn::Copy(src, dest),
+                    (a, b) => Insn::Mul(a, b, dest)
+                }
+            }
+            Opcode::LessThan(param1, param2, dest) => {
+                let dest = Dst::new(dest)?;
+                match (Src::from(param1), Src::from(param2)) {
+                    (Imm(a), Imm(b)) => Insn::Store(dest, (a < b) as isize),
+                    (a, b) => Insn::LessThan(a, b, dest)
+                }
+            }
+            Opcode::Equals(param1, param2, dest) => {
+                let dest = Dst::new(dest)?;
+                match (Src::from(param1), Src::from(param2)) {
+                    (Imm(a), Imm(b)) => Insn::Store(dest, (a == b) as isize),
+                    (a, b) => Insn::Equals(a, b, dest)
+                }
+            }
+            Opcode::AdjustRelativeBase(offset) => Insn::AdjustRelativeBase(offset.into()),
+            Opcode::In(_) | Opcode::Out(_) | Opcode::JumpNonZero(_, _) | Opcode::JumpZero(_, _)
+                | Opcode::Halt => return None
+        };
+
+        Some(insn)
+    }
+
+    /// Length of the instruction this was compiled from
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self {
+            Insn::AdjustRelativeBase(_) => 2,
+            _ => 4
+        }
+    }
+}
+
+/// Straight-line run of instructions starting at `start`.
+///
+/// A block covers every instruction up to, but not including, the first jump, I/O, halt or
+/// undecodable instruction. That terminating instruction is executed by [`Program::step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// Address of the first instruction
+    pub start: Pos,
+
+    /// Specialised instructions in execution order
+    pub insns: Vec<Insn>
+}
+
+impl Block {
+    /// Address of the instruction that ends the block
+    pub fn end(&self) -> Pos {
+        self.start + self.insns.iter().map(Insn::len).sum::<usize>()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Build the block starting at `start`, lifting instructions as needed, and cache it
+    fn compile_block(&mut self, start: Pos) -> Arc<Block> {
+        let mut insns = Vec::new();
+        let mut addr = start;
+        loop {
+            let op = match self.instructions.get(addr) {
+                Some(&op) => op,
+                None => match self.lift(addr) {
+                    Ok(op) => op,
+
+                    // Let `step` report the fault once execution reaches it
+                    Err(_) => break
+                }
+            };
+
+            match Insn::compile(op) {
+                Some(insn) => insns.push(insn),
+                None => break
+            }
+            addr += op.len();
+        }
+
+        debug!("Compiled block [{}..{}] {:?}\n", start, addr, insns);
+        let block = Arc::new(Block { start, insns });
+        self.instructions.insert_block(block.clone());
+        block
+    }
+
+    /// Get the block starting at the current IP, compiling it if needed
+    pub(crate) fn block_at_ip(&mut self) -> Arc<Block> {
+        match self.instructions.block(self.ip) {
+            Some(block) => block.clone(),
+            None => self.compile_block(self.ip)
+        }
+    }
+
+    /// Execute up to `limit` straight-line instructions of the block at the current IP, leaving
+    /// the IP on the instruction that ends the block.
+    ///
+    /// If a write modifies lifted code, the rest of the block is abandoned so the modified code
+    /// is lifted again before it executes. Returns the number of instructions executed.
+    pub(crate) fn execute_block(&mut self, limit: u64) -> Result<u64, VmError> {
+        let block = self.block_at_ip();
+
+        let generation = self.instructions.generation();
+        let mut executed = 0;
+        for insn in block.insns.iter() {
+            if executed == limit {
+                break;
+            }
+
+            let ip = self.ip;
+            self.begin_record();
+            self.begin_trace();
+            self.begin_profile();
+            let result = self.execute_insn(*insn);
+            self.end_profile(result.is_ok());
+            self.end_trace(result.is_ok());
+            self.end_record(result.is_ok());
+            result?;
+            self.track_coverage(ip);
+            executed += 1;
+            if self.instructions.generation() != generation {
+                info!("Code modified in block {}, leaving block\n", block.start);
+                break;
+            }
+        }
+
+        Ok(executed)
+    }
+
+    fn src(&self, src: Src) -> Result<Imm, VmError> {
+        match src {
+            Src::Imm(imm) => Ok(imm),
+            Src::Pos(addr) => Ok(self.read(addr)),
+            Src::Rel(rel_offset) => Ok(self.read(self.relative(rel_offset)?))
+        }
+    }
+
+    fn dst(&self, dst: Dst) -> Result<Pos, VmError> {
+        match dst {
+            Dst::Pos(addr) => Ok(addr),
+            Dst::Rel(rel_offset) => self.relative(rel_offset)
+        }
+    }
+
+    fn execute_insn(&mut self, insn: Insn) -> Result<(), VmError> {
+        match insn {
+            Insn::Store(dest, value) => {
+                let dest = self.dst(dest)?;
+                self.write(dest, value);
+            }
+            Insn::Copy(src, dest) => {
+                let value = self.src(src)?;
+                let dest = self.dst(dest)?;
+                self.write(dest, value);
+            }
+            Insn::AddPosImm(src, imm, dest) => {
+                let value = self.read(src).checked_add(imm)
+                                          .ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                self.write(dest, value);
+            }
+            Insn::Add(a, b, dest) => {
+                // Resolve the destination before the overflow check, as `Program::execute` does
+                let (a, b) = (self.src(a)?, self.src(b)?);
+                let dest = self.dst(dest)?;
+                let value = a.checked_add(b).ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                self.write(dest, value);
+            }
+            Insn::Mul(a, b, dest) => {
+                // Resolve the destination before the overflow check, as `Program::execute` does
+                let (a, b) = (self.src(a)?, self.src(b)?);
+                let dest = self.dst(dest)?;
+                let value = a.checked_mul(b).ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                self.write(dest, value);
+            }
+            Insn::LessThan(a, b, dest) => {
+                let value = (self.src(a)? < self.src(b)?) as Imm;
+                let dest = self.dst(dest)?;
+                self.write(dest, value);
+            }
+            Insn::Equals(a, b, dest) => {
+                let value = (self.src(a)? == self.src(b)?) as Imm;
+                let dest = self.dst(dest)?;
+                self.write(dest, value);
+            }
+            Insn::AdjustRelativeBase(offset) => {
+                let offset = self.src(offset)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                                                       .ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+            }
+        }
+
+        self.ip += insn.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::Mode::*;
+
+    #[test]
+    fn test_superinstructions() {
+        let add = |a, b, c| Insn::compile(Opcode::Add(a, b, c));
+        assert_eq!(add(Immediate(2), Immediate(3), Positional(9)), Some(Insn::Store(Dst::Pos(9), 5)));
+        assert_eq!(add(Relative(1), Immediate(0), Positional(9)),
+                   Some(Insn::Copy(Src::Rel(1), Dst::Pos(9))));
+        assert_eq!(add(Immediate(-1), Positional(4), Positional(4)), Some(Insn::AddPosImm(4, -1, 4)));
+        assert_eq!(add(Immediate(isize::MAX), Immediate(1), Positional(0)),
+                   Some(Insn::Add(Src::Imm(isize::MAX), Src::Imm(1), Dst::Pos(0))));
+        assert_eq!(add(Immediate(1), Immediate(1), Immediate(0)), None);
+
+        assert_eq!(Insn::compile(Opcode::Mul(Immediate(1), Positional(3), Relative(2))),
+                   Some(Insn::Copy(Src::Pos(3), Dst::Rel(2))));
+        assert_eq!(Insn::compile(Opcode::Equals(Immediate(7), Immediate(7), Positional(1))),
+                   Some(Insn::Store(Dst::Pos(1), 1)));
+        assert_eq!(Insn::compile(Opcode::JumpZero(Immediate(0), Immediate(0))), None);
+    }
+
+    #[test]
+    fn test_block_boundaries() {
+        // Add, Mul, AdjustRelativeBase, Out, Halt
+        let mut program = Program::from_input("1101,1,2,20,1002,20,3,20,109,5,4,20,99");
+        assert_eq!(program.execute_block(u64::MAX), Ok(3));
+        assert_eq!(program.ip, 10);
+        assert_eq!(program.read(20), 9);
+        assert_eq!(program.relative_base, 5);
+
+        let block = program.instructions.block(0).unwrap();
+        assert_eq!(block.end(), 10);
+        assert_eq!(block.insns[0], Insn::Store(Dst::Pos(20), 3));
+    }
+
+    #[test]
+    fn test_write_into_own_block() {
+        // The first Add overwrites the second Add with a Halt before it executes
+        // Add(Imm(99), Imm(0), Pos(4)), Add(Imm(7), Imm(0), Pos(20)), Out(Pos(20)), Halt
+        let mut program = Program::from_input("1101,99,0,4,1101,7,0,20,4,20,99");
+        program.run().unwrap();
+        assert!(program.output.is_empty());
+        assert_eq!(program.ip, 4);
+    }
+
+    #[test]
+    fn test_fault_inside_block() {
+        // Add(Imm(1), Imm(1), Pos(10)), AdjustRelativeBase(Imm(-5)), Add(Rel(0), Imm(1), Pos(10))
+        let mut program = Program::from_input("1101,1,1,10,109,-5,1201,0,1,10,99");
+        let err = program.run().unwrap_err();
+        assert_eq!(err, VmError { ip: 6, opcode: 1201, kind: ErrorKind::NegativeAddress(-5) });
+        assert_eq!(program.read(10), 2);
+    }
+
+    #[test]
+    fn test_fault_matches_step() {
+        // An overflowing Add and Mul with a negative relative destination fault on the address
+        // whether they run in a block or one step at a time
+        for input in &["20001,5,6,-1,99,9223372036854775807,1", "20002,5,6,-1,99,9223372036854775807,2"] {
+            let mut blocks = Program::from_input(input);
+            let mut steps = Program::from_input(input);
+            let err = loop {
+                if let Err(err) = steps.step() {
+                    break err;
+                }
+            };
+            assert_eq!(blocks.run().unwrap_err(), err);
+            assert_eq!(err.kind, ErrorKind::NegativeAddress(-1));
+        }
+    }
+}
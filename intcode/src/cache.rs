@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::block::Block;
+use crate::opcode::{Opcode, Pos};
+
+/// Addresses below this limit track their coverage in a flat table
+const DENSE_LIMIT: usize = 1 << 20;
+
+/// Cache of lifted instructions keyed by the address they were lifted from.
+///
+/// Alongside the instructions, the cache keeps a per-address count of how many lifted
+/// instructions cover each address. Checking whether a write lands inside lifted code is a single
+/// table lookup, so the common case of writing data costs the same no matter how many
+/// instructions have been lifted.
+///
+/// Lifted instructions are grouped into straight-line [`Block`]s for execution. Invalidating any
+/// instruction drops every block and bumps the cache generation so a running block can notice
+/// that its code changed underneath it.
+#[derive(Debug, Clone, Default)]
+pub struct InstructionCache {
+    /// Lifted instructions keyed by IP of the instruction
+    instructions: HashMap<Pos, Opcode>,
+
+    /// Number of lifted instructions covering each address below `DENSE_LIMIT`. Addresses past
+    /// the end of the table are not covered.
+    coverage: Vec<u8>,
+
+    /// Number of lifted instructions that extend past `DENSE_LIMIT`
+    far: usize,
+
+    /// Compiled blocks keyed by the address of their first instruction
+    blocks: HashMap<Pos, Arc<Block>>,
+
+    /// Bumped every time a lifted instruction is invalidated
+    generation: u64
+}
+
+impl InstructionCache {
+    /// Get the lifted instruction starting at `addr`
+    pub fn get(&self, addr: Pos) -> Option<&Opcode> {
+        self.instructions.get(&addr)
+    }
+
+    /// Number of lifted instructions
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Returns true if no instructions have been lifted
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Iterate over (address, instruction) pairs in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, Opcode)> + '_ {
+        self.instructions.iter().map(|(&addr, &op)| (addr, op))
+    }
+
+    /// Returns true if `address` might be covered by a lifted instruction
+    fn maybe_covered(&self, address: Pos) -> bool {
+        let dense = self.coverage.get(address).is_some_and(|&count| count > 0);
+
+        // Instructions that extend past `DENSE_LIMIT` are only counted in `far`
+        dense || (self.far > 0 && address.saturating_add(Opcode::MAX_LEN) > DENSE_LIMIT)
+    }
+
+    /// Returns the start addresses of every lifted instruction that covers `address`
+    pub fn containing(&self, address: Pos) -> impl Iterator<Item = Pos> + '_ {
+        // Instructions are at most `Opcode::MAX_LEN` long, so only instructions starting just
+        // before the address can contain it
+        let starts = if self.maybe_covered(address) {
+            address.saturating_sub(Opcode::MAX_LEN - 1)..address + 1
+        } else {
+            0..0
+        };
+
+        starts.filter(move |&start| {
+            self.instructions.get(&start).is_some_and(|op| address < start + op.len())
+        })
+    }
+
+    /// Cache a lifted instruction, replacing any instruction previously lifted from `addr`
+    pub(crate) fn insert(&mut self, addr: Pos, op: Opcode) {
+        self.remove(addr);
+        self.instructions.insert(addr, op);
+        self.cover(addr, op.len(), true);
+    }
+
+    /// Remove the lifted instruction at `addr` from the cache, along with every block
+    pub(crate) fn remove(&mut self, addr: Pos) -> Option<Opcode> {
+        let op = self.instructions.remove(&addr)?;
+        self.cover(addr, op.len(), false);
+        self.blocks.clear();
+        self.generation += 1;
+        Some(op)
+    }
+
+    /// Get the compiled block starting at `addr`
+    pub fn block(&self, addr: Pos) -> Option<&Arc<Block>> {
+        self.blocks.get(&addr)
+    }
+
+    /// Cache a compiled block. Every instruction in the block must already be cached.
+    pub(crate) fn insert_block(&mut self, block: Arc<Block>) {
+        self.blocks.insert(block.start, block);
+    }
+
+    /// Counter that changes whenever a lifted instruction is invalidated
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Update the coverage counts for an instruction of `len` words at `addr`
+    fn cover(&mut self, addr: Pos, len: usize, add: bool) {
+        let end = addr.saturating_add(len);
+        if end > DENSE_LIMIT {
+            if add { self.far += 1 } else { self.far -= 1 }
+            return;
+        }
+
+        if end > self.coverage.len() {
+            self.coverage.resize(end, 0);
+        }
+
+        for count in &mut self.coverage[addr..end] {
+            if add { *count += 1 } else { *count -= 1 }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::Mode::*;
+
+    #[test]
+    fn test_coverage_tracking() {
+        let mut cache = InstructionCache::default();
+        let add = Opcode::Add(Immediate(1), Immediate(2), Positional(0));
+        cache.insert(10, add);
+        cache.insert(12, Opcode::Halt);
+        assert_eq!(cache.containing(9).count(), 0);
+        assert_eq!(cache.containing(12).collect::<Vec<_>>(), vec![10, 12]);
+        assert_eq!(cache.containing(13).collect::<Vec<_>>(), vec![10]);
+        assert_eq!(cache.containing(14).count(), 0);
+
+        // Replacing and removing instructions releases their coverage
+        cache.insert(10, Opcode::Halt);
+        assert_eq!(cache.containing(13).count(), 0);
+        cache.remove(12);
+        assert_eq!(cache.containing(12).count(), 0);
+        assert_eq!(cache.coverage.iter().map(|&c| c as usize).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_coverage_past_dense_limit() {
+        let mut cache = InstructionCache::default();
+        let add = Opcode::Add(Immediate(1), Immediate(2), Positional(0));
+        cache.insert(DENSE_LIMIT - 2, add);
+        cache.insert(1 << 40, Opcode::Halt);
+        assert_eq!(cache.containing(DENSE_LIMIT - 1).collect::<Vec<_>>(), vec![DENSE_LIMIT - 2]);
+        assert_eq!(cache.containing(DENSE_LIMIT + 1).collect::<Vec<_>>(), vec![DENSE_LIMIT - 2]);
+        assert_eq!(cache.containing(1 << 40).collect::<Vec<_>>(), vec![1 << 40]);
+        assert!(cache.coverage.is_empty());
+
+        cache.remove(DENSE_LIMIT - 2);
+        cache.remove(1 << 40);
+        assert_eq!(cache.far, 0);
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::block::{Dst, Insn};
+use crate::cfg::{natural_loops, Cfg, EdgeKind, Terminator};
+use crate::disasm::{ends_flow, jump_target};
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// A call recognised from the calling convention: the return address is stored to `[rb]`
+/// immediately before an unconditional jump to the callee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSite {
+    /// Address of the jump into the callee
+    pub addr: Pos,
+
+    /// Entry point of the callee
+    pub target: Pos,
+
+    /// Address execution continues at once the callee returns
+    pub return_addr: Pos
+}
+
+/// A function recovered from its call sites
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// Entry point. The program entry point at address 0 is treated as a function too.
+    pub entry: Pos,
+
+    /// Stack frame size, from an `arb #n` at the entry point
+    pub frame_size: Option<Imm>,
+
+    /// Start addresses of the basic blocks in the function body
+    pub blocks: BTreeSet<Pos>,
+
+    /// Calls made by the function
+    pub calls: Vec<CallSite>,
+
+    /// Addresses of the jumps through `[rb+n]` that return to the caller
+    pub returns: Vec<Pos>
+}
+
+/// Functions of a program and the calls between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+    /// Control-flow graph including the code at every return address
+    pub cfg: Cfg,
+
+    /// Functions keyed by entry point
+    pub functions: BTreeMap<Pos, Function>
+}
+
+/// Returns the call made by a basic block, if it ends in one
+fn call_site(insns: &[(Pos, Opcode)]) -> Option<CallSite> {
+    let (&(addr, jump), rest) = insns.split_last()?;
+    let &(_, store) = rest.last()?;
+
+    let target = jump_target(&jump)?;
+    if !ends_flow(&jump) {
+        return None;
+    }
+    match Insn::compile(store) {
+        Some(Insn::Store(Dst::Rel(0), value)) if value == (addr + jump.len()) as Imm => {
+            Some(CallSite { addr, target, return_addr: addr + jump.len() })
+        }
+        _ => None
+    }
+}
+
+/// Returns true if the instruction returns to the caller through an address in the frame
+fn is_return(op: &Opcode) -> bool {
+    match *op {
+        Opcode::JumpNonZero(Mode::Immediate(cond), Mode::Relative(_)) => cond != 0,
+        Opcode::JumpZero(Mode::Immediate(cond), Mode::Relative(_)) => cond == 0,
+        _ => false
+    }
+}
+
+impl CallGraph {
+    /// Function whose body contains `addr`
+    pub fn function_at(&self, addr: Pos) -> Option<&Function> {
+        let block = self.cfg.blocks.range(..=addr).next_back().filter(|(_, block)| addr < block.end)?.0;
+        self.functions.values().find(|function| function.blocks.contains(block))
+    }
+
+    /// Entry points of the functions called by the function at `entry`
+    pub fn callees(&self, entry: Pos) -> BTreeSet<Pos> {
+        self.functions.get(&entry).map_or_else(BTreeSet::new, |function| {
+            function.calls.iter().map(|call| call.target).collect()
+        })
+    }
+
+    /// Call sites that call the function at `entry`
+    pub fn callers(&self, entry: Pos) -> Vec<CallSite> {
+        self.functions.values().flat_map(|function| function.calls.iter())
+            .filter(|call| call.target == entry)
+            .copied()
+            .collect()
+    }
+
+    /// Successors of the block starting at `start` inside `function`. A call continues at its
+    /// return address.
+    pub(crate) fn local_successors(&self, function: &Function, start: Pos) -> Vec<Pos> {
+        let block = &self.cfg.blocks[&start];
+        let last = block.insns.last().unwrap().0;
+        if let Some(call) = function.calls.iter().find(|call| call.addr == last) {
+            return vec![call.return_addr];
+        }
+        self.cfg.successors(start).map(|edge| edge.to)
+            .filter(|to| function.blocks.contains(to))
+            .collect()
+    }
+
+    /// Natural loops of the function at `entry`, as the start addresses of the blocks of each
+    /// loop body keyed by loop header
+    pub fn loops(&self, entry: Pos) -> BTreeMap<Pos, BTreeSet<Pos>> {
+        let function = match self.functions.get(&entry) {
+            Some(function) => function,
+            None => return BTreeMap::new()
+        };
+        let succs = function.blocks.iter().map(|&start| (start, self.local_successors(function, start))).collect();
+        natural_loops(entry, &succs)
+    }
+
+    /// Render the call graph in Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n    node [shape=box fontname=monospace];\n");
+        for function in self.functions.values() {
+            let frame = function.frame_size.map_or_else(String::new, |size| format!("\\nframe {}", size));
+            let _ = writeln!(dot, "    f{} [label=\"F{}{}\"];", function.entry, function.entry, frame);
+        }
+        for function in self.functions.values() {
+            for callee in self.callees(function.entry) {
+                let _ = writeln!(dot, "    f{} -> f{};", function.entry, callee);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Recover functions and the call graph from the code reachable from address 0.
+    ///
+    /// Return addresses are only ever reached through an indirect jump, so the code after each
+    /// call is added as another entry point until no new calls are found.
+    pub fn recover_functions(&self) -> CallGraph {
+        let mut entries = vec![0];
+        let (cfg, calls) = loop {
+            let cfg = self.control_flow_graph_from(&entries);
+            let calls: Vec<CallSite> = cfg.blocks.values().filter_map(|block| call_site(&block.insns)).collect();
+            let before = entries.len();
+            for call in &calls {
+                if !entries.contains(&call.return_addr) {
+                    entries.push(call.return_addr);
+                }
+            }
+            if entries.len() == before {
+                break (cfg, calls);
+            }
+        };
+        let calls: BTreeMap<Pos, CallSite> = calls.into_iter().map(|call| (call.addr, call)).collect();
+
+        let mut entry_points: BTreeSet<Pos> = calls.values().map(|call| call.target).collect();
+        entry_points.insert(0);
+
+        let mut functions = BTreeMap::new();
+        for &entry in &entry_points {
+            if !cfg.blocks.contains_key(&entry) {
+                continue;
+            }
+
+            // Walk the body, stepping over calls to their return address
+            let mut function = Function {
+                entry,
+                frame_size: match self.decode(entry) {
+                    Ok(Opcode::AdjustRelativeBase(Mode::Immediate(size))) if size > 0 => Some(size),
+                    _ => None
+                },
+                blocks: BTreeSet::new(),
+                calls: Vec::new(),
+                returns: Vec::new()
+            };
+            let mut worklist = vec![entry];
+            while let Some(start) = worklist.pop() {
+                // Jumps may target words that overlap other code and never start a block
+                let block = match cfg.blocks.get(&start) {
+                    Some(block) => block,
+                    None => continue
+                };
+                if !function.blocks.insert(start) {
+                    continue;
+                }
+                let &(last, op) = block.insns.last().expect("blocks are never empty");
+
+                if let Some(call) = calls.get(&last) {
+                    function.calls.push(*call);
+                    worklist.push(call.return_addr);
+                    continue;
+                }
+                if block.terminator == Terminator::Indirect && is_return(&op) {
+                    function.returns.push(last);
+                }
+                for edge in cfg.successors(start) {
+                    // Jumping to another function's entry without a call is a tail call
+                    if edge.kind == EdgeKind::Taken && edge.to != entry && entry_points.contains(&edge.to) {
+                        continue;
+                    }
+                    worklist.push(edge.to);
+                }
+            }
+
+            function.calls.sort_by_key(|call| call.addr);
+            function.returns.sort_unstable();
+            functions.insert(entry, function);
+        }
+
+        CallGraph { cfg, functions }
+    }
+}
+
+/// Frame of the shadow call stack, pushed when a call is executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// Call the frame was entered through
+    pub call: CallSite,
+
+    /// Relative base at the time of the call
+    pub relative_base: isize
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Maintain a shadow call stack while executing, so that backtraces can be shown.
+    ///
+    /// A taken jump is a call when `[rb]` holds the address just past the jump, and a return when
+    /// it lands on the return address of a frame on the stack. Frames are not rewound by
+    /// [`Program::step_back`].
+    pub fn with_call_stack(mut self) -> Self {
+        self.call_stack = Some(Vec::new());
+        self
+    }
+
+    /// Update the shadow call stack after the instruction at `from` was executed
+    pub(crate) fn track_call(&mut self, from: Pos) {
+        let stack = match &mut self.call_stack {
+            Some(stack) => stack,
+            None => return
+        };
+        let return_addr = match self.instructions.get(from) {
+            Some(op @ Opcode::JumpNonZero(_, _)) | Some(op @ Opcode::JumpZero(_, _)) => from + op.len(),
+            _ => return
+        };
+        let ip = self.ip;
+        if ip == return_addr {
+            return;
+        }
+
+        // Returning may unwind several frames when a callee never returned normally
+        if let Some(depth) = stack.iter().rposition(|frame| frame.call.return_addr == ip) {
+            stack.truncate(depth);
+            return;
+        }
+
+        if self.relative_base >= 0 && self.memory.read(self.relative_base as usize) == return_addr as Imm {
+            let call = CallSite { addr: from, target: ip, return_addr };
+            stack.push(Frame { call, relative_base: self.relative_base });
+        }
+    }
+
+    /// Current call stack as `(address, function entry)` pairs, innermost first. The innermost
+    /// address is the IP, the others are the call sites of each frame. Empty if the shadow call
+    /// stack is disabled.
+    pub fn backtrace(&self) -> Vec<(Pos, Pos)> {
+        let stack = match &self.call_stack {
+            Some(stack) => stack,
+            None => return Vec::new()
+        };
+
+        let mut trace = Vec::with_capacity(stack.len() + 1);
+        let mut addr = self.ip;
+        for frame in stack.iter().rev() {
+            trace.push((addr, frame.call.target));
+            addr = frame.call.addr;
+        }
+        trace.push((addr, 0));
+        trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ExitReason;
+
+    /// Computes factorial of its input recursively:
+    ///
+    /// ```text
+    ///     arb #100
+    ///     in -> [rb+1]
+    ///     add #11, #0 -> [rb]
+    ///     jz #0, fact
+    ///     out [rb+1]
+    ///     halt
+    /// fact:
+    ///     arb #3
+    ///     lt [rb-2], #2 -> [rb+2]
+    ///     jnz [rb+2], done
+    ///     add [rb-2], #-1 -> [rb+1]
+    ///     mul #1, #34 -> [rb]
+    ///     jz #0, fact
+    ///     mul [rb-2], [rb+1] -> [rb-2]
+    /// done:
+    ///     arb #-3
+    ///     jz #0, [rb]
+    /// ```
+    const FACT: &str = "109,100,203,1,21101,11,0,0,1106,0,14,204,1,99,109,3,21207,-2,2,2,1205,2,38,\
+                        21201,-2,-1,1,21102,1,34,0,1106,0,14,22202,-2,1,-2,109,-3,2106,0,0";
+
+    #[test]
+    fn test_recover_functions() {
+        let program = Program::from_input(FACT);
+        let graph = program.recover_functions();
+        let entries: Vec<Pos> = graph.functions.keys().copied().collect();
+        assert_eq!(entries, vec![0, 14]);
+
+        let main = &graph.functions[&0];
+        assert_eq!(main.frame_size, Some(100));
+        assert_eq!(main.calls, vec![CallSite { addr: 8, target: 14, return_addr: 11 }]);
+        assert!(main.returns.is_empty());
+
+        let fact = &graph.functions[&14];
+        assert_eq!(fact.frame_size, Some(3));
+        assert_eq!(fact.blocks.iter().copied().collect::<Vec<_>>(), vec![14, 23, 34, 38]);
+        assert_eq!(fact.returns, vec![40]);
+        assert_eq!(graph.callees(14).into_iter().collect::<Vec<_>>(), vec![14]);
+        assert_eq!(graph.callers(14).len(), 2);
+        assert_eq!(graph.function_at(35).map(|function| function.entry), Some(14));
+        assert_eq!(graph.function_at(12).map(|function| function.entry), Some(0));
+        assert_eq!(graph.to_dot(), "\
+digraph calls {
+    node [shape=box fontname=monospace];
+    f0 [label=\"F0\\nframe 100\"];
+    f14 [label=\"F14\\nframe 3\"];
+    f0 -> f14;
+    f14 -> f14;
+}
+");
+    }
+
+    #[test]
+    fn test_shadow_call_stack() {
+        let mut program = Program::from_input(FACT).with_call_stack();
+        program.input.push_back(4);
+
+        // Run until the innermost call reaches its base case
+        while program.ip != 38 {
+            program.step().unwrap();
+        }
+        assert_eq!(program.backtrace(), vec![(38, 14), (31, 14), (31, 14), (31, 14), (8, 0)]);
+
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert_eq!(program.output, vec![24]);
+        assert_eq!(program.backtrace(), vec![(13, 0)]);
+    }
+}
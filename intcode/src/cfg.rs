@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::disasm::jump_target;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// How control leaves a basic block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    /// Execution runs into the next block, which starts at a jump target
+    Fallthrough,
+
+    /// Jump with an immediate target
+    Jump,
+
+    /// Jump whose target is read from memory. Its successors are unknown.
+    Indirect,
+
+    /// `Halt` instruction
+    Halt,
+
+    /// The word after the block does not decode, or overlaps other code
+    Invalid
+}
+
+impl Terminator {
+    fn name(self) -> &'static str {
+        match self {
+            Terminator::Fallthrough => "fallthrough",
+            Terminator::Jump => "jump",
+            Terminator::Indirect => "indirect",
+            Terminator::Halt => "halt",
+            Terminator::Invalid => "invalid"
+        }
+    }
+}
+
+/// Kind of control-flow edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Straight-line execution into the next block
+    Fallthrough,
+
+    /// A jump whose condition held
+    Taken,
+
+    /// A jump whose condition failed
+    NotTaken,
+
+    /// A jump to a word that does not start an instruction, so `to` is not a block
+    Invalid
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::Taken => "taken",
+            EdgeKind::NotTaken => "not-taken",
+            EdgeKind::Invalid => "invalid"
+        }
+    }
+}
+
+/// Control-flow edge between the blocks starting at `from` and `to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: Pos,
+    pub to: Pos,
+    pub kind: EdgeKind
+}
+
+/// Maximal straight-line run of instructions with a single entry at `start`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Address of the first instruction
+    pub start: Pos,
+
+    /// Address just past the last instruction
+    pub end: Pos,
+
+    /// Instructions with their addresses, in order
+    pub insns: Vec<(Pos, Opcode)>,
+
+    /// How control leaves the block
+    pub terminator: Terminator
+}
+
+/// Control-flow graph of the code reachable from the entry point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    /// Basic blocks keyed by start address
+    pub blocks: BTreeMap<Pos, BasicBlock>,
+
+    /// Every edge, ordered by source block
+    pub edges: Vec<Edge>
+}
+
+/// Escape a string for a double quoted DOT or JSON string
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Cfg {
+    /// Successors of the block starting at `start`
+    pub fn successors(&self, start: Pos) -> impl Iterator<Item = &Edge> + '_ {
+        self.edges.iter().filter(move |edge| edge.from == start)
+    }
+
+    /// Predecessors of the block starting at `start`
+    pub fn predecessors(&self, start: Pos) -> impl Iterator<Item = &Edge> + '_ {
+        self.edges.iter().filter(move |edge| edge.to == start)
+    }
+
+    /// Blocks ending in a jump whose target is read from memory
+    pub fn indirect_jumps(&self) -> impl Iterator<Item = &BasicBlock> + '_ {
+        self.blocks.values().filter(|block| block.terminator == Terminator::Indirect)
+    }
+
+    /// Render the graph in Graphviz DOT format. Indirect jumps are drawn in red and jumps to
+    /// invalid targets lead to a dashed red node.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n    node [shape=box fontname=monospace];\n");
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (addr, op) in &block.insns {
+                let _ = write!(label, "{:04}  {}\\l", addr, escape(&op.to_string()));
+            }
+            let style = match block.terminator {
+                Terminator::Indirect => " color=red xlabel=\"indirect\"",
+                Terminator::Invalid => " style=dashed",
+                _ => ""
+            };
+            let _ = writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, style);
+        }
+        let invalid: BTreeSet<Pos> = self.edges.iter().filter(|edge| edge.kind == EdgeKind::Invalid)
+            .map(|edge| edge.to)
+            .collect();
+        for target in invalid {
+            let _ = writeln!(dot, "    b{} [label=\"{:04}  invalid target\\l\" style=dashed color=red];", target, target);
+        }
+        for edge in &self.edges {
+            let color = match edge.kind {
+                EdgeKind::Fallthrough => "black",
+                EdgeKind::Taken => "green",
+                EdgeKind::NotTaken | EdgeKind::Invalid => "red"
+            };
+            let _ = writeln!(dot, "    b{} -> b{} [label=\"{}\" color={}];", edge.from, edge.to, edge.kind.name(),
+                             color);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Serialise the graph as JSON with stable ordering so that graphs can be diffed
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n  \"blocks\": [");
+        for (i, block) in self.blocks.values().enumerate() {
+            let insns: Vec<String> = block.insns.iter().map(|(addr, op)| {
+                format!("{{\"addr\": {}, \"text\": \"{}\"}}", addr, escape(&op.to_string()))
+            }).collect();
+            let _ = write!(json, "{}\n    {{\"start\": {}, \"end\": {}, \"terminator\": \"{}\", \"insns\": [{}]}}",
+                           if i == 0 { "" } else { "," }, block.start, block.end, block.terminator.name(),
+                           insns.join(", "));
+        }
+        json.push_str("\n  ],\n  \"edges\": [");
+        for (i, edge) in self.edges.iter().enumerate() {
+            let _ = write!(json, "{}\n    {{\"from\": {}, \"to\": {}, \"kind\": \"{}\"}}",
+                           if i == 0 { "" } else { "," }, edge.from, edge.to, edge.kind.name());
+        }
+        json.push_str("\n  ]\n}\n");
+        json
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Recover the control-flow graph of the code reachable from address 0
+    pub fn control_flow_graph(&self) -> Cfg {
+        self.control_flow_graph_from(&[0])
+    }
+
+    /// Recover the control-flow graph of the code reachable from any of `entries`
+    pub fn control_flow_graph_from(&self, entries: &[Pos]) -> Cfg {
+        let code = self.reachable_code(entries);
+
+        // Blocks start at entries, jump targets and after every jump
+        let mut leaders: BTreeSet<Pos> = entries.iter().copied().collect();
+        for (&addr, op) in &code {
+            if let Some(target) = jump_target(op) {
+                leaders.insert(target);
+            }
+            if matches!(op, Opcode::JumpNonZero(_, _) | Opcode::JumpZero(_, _)) {
+                leaders.insert(addr + op.len());
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut edges = Vec::new();
+        let mut current: Option<BasicBlock> = None;
+        for (&addr, &op) in &code {
+            let mut block = match current.take() {
+                Some(block) if block.end == addr && !leaders.contains(&addr) => block,
+                other => {
+                    // The previous block ended without a jump or halt
+                    if let Some(block) = other {
+                        blocks.insert(block.start, block);
+                    }
+                    BasicBlock { start: addr, end: addr, insns: Vec::new(), terminator: Terminator::Invalid }
+                }
+            };
+
+            block.insns.push((addr, op));
+            block.end = addr + op.len();
+
+            let next = block.end;
+            let terminator = match op {
+                Opcode::Halt => Some(Terminator::Halt),
+                Opcode::JumpNonZero(cond, target) | Opcode::JumpZero(cond, target) => {
+                    let taken_when_nonzero = matches!(op, Opcode::JumpNonZero(_, _));
+                    let (may_take, may_skip) = match cond {
+                        Mode::Immediate(value) => ((value != 0) == taken_when_nonzero,
+                                                   (value != 0) != taken_when_nonzero),
+                        _ => (true, true)
+                    };
+
+                    if may_take {
+                        if let Some(target) = jump_target(&op) {
+                            let kind = if code.contains_key(&target) { EdgeKind::Taken } else { EdgeKind::Invalid };
+                            edges.push(Edge { from: block.start, to: target, kind });
+                        }
+                    }
+                    if may_skip && code.contains_key(&next) {
+                        edges.push(Edge { from: block.start, to: next, kind: EdgeKind::NotTaken });
+                    }
+
+                    match target {
+                        Mode::Immediate(_) => Some(Terminator::Jump),
+                        _ if may_take => Some(Terminator::Indirect),
+                        _ => Some(Terminator::Jump)
+                    }
+                }
+                _ if leaders.contains(&next) && code.contains_key(&next) => {
+                    edges.push(Edge { from: block.start, to: next, kind: EdgeKind::Fallthrough });
+                    Some(Terminator::Fallthrough)
+                }
+                _ => None
+            };
+
+            match terminator {
+                Some(terminator) => {
+                    block.terminator = terminator;
+                    blocks.insert(block.start, block);
+                }
+                None => current = Some(block)
+            }
+        }
+        if let Some(block) = current {
+            blocks.insert(block.start, block);
+        }
+
+        edges.sort_by_key(|edge| (edge.from, !matches!(edge.kind, EdgeKind::Taken | EdgeKind::Invalid)));
+        Cfg { blocks, edges }
+    }
+}
+
+/// Natural loops over `succs` from back edges to a dominating header. Returns the blocks of each
+/// loop body, including the header, keyed by header.
+pub(crate) fn natural_loops(entry: Pos, succs: &BTreeMap<Pos, Vec<Pos>>) -> BTreeMap<Pos, BTreeSet<Pos>> {
+    let dom = dominators(entry, succs);
+    let mut bodies: BTreeMap<Pos, BTreeSet<Pos>> = BTreeMap::new();
+    for (&from, tos) in succs {
+        for &header in tos.iter().filter(|header| dom.get(&from).is_some_and(|dom| dom.contains(header))) {
+            let body = bodies.entry(header).or_insert_with(|| std::iter::once(header).collect());
+            let mut worklist = vec![from];
+            while let Some(node) = worklist.pop() {
+                if body.insert(node) {
+                    worklist.extend(succs.iter().filter(|(_, tos)| tos.contains(&node)).map(|(&pred, _)| pred));
+                }
+            }
+        }
+    }
+    bodies
+}
+
+/// Dominator sets over `succs`, reachable from `entry`
+fn dominators(entry: Pos, succs: &BTreeMap<Pos, Vec<Pos>>) -> BTreeMap<Pos, BTreeSet<Pos>> {
+    let nodes: BTreeSet<Pos> = succs.keys().copied().collect();
+    let mut preds: BTreeMap<Pos, Vec<Pos>> = BTreeMap::new();
+    for (&from, tos) in succs {
+        for &to in tos {
+            preds.entry(to).or_default().push(from);
+        }
+    }
+
+    let mut dom: BTreeMap<Pos, BTreeSet<Pos>> = nodes.iter().map(|&node| (node, nodes.clone())).collect();
+    dom.insert(entry, std::iter::once(entry).collect());
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in nodes.iter().filter(|&&node| node != entry) {
+            let mut new = preds.get(&node).into_iter().flatten()
+                .map(|pred| dom[pred].clone())
+                .reduce(|a, b| a.intersection(&b).copied().collect())
+                .unwrap_or_default();
+            new.insert(node);
+            if new != dom[&node] {
+                dom.insert(node, new);
+                changed = true;
+            }
+        }
+    }
+    dom
+}
+
+/// Address standing in for the single exit of a function when computing post-dominators
+const EXIT: Pos = Pos::MAX;
+
+/// Immediate post-dominator of every block that has one other than the function exit
+pub(crate) fn immediate_post_dominators(succs: &BTreeMap<Pos, Vec<Pos>>) -> BTreeMap<Pos, Pos> {
+    // Post-dominators are dominators of the reversed graph
+    let mut reversed: BTreeMap<Pos, Vec<Pos>> = BTreeMap::new();
+    reversed.insert(EXIT, Vec::new());
+    for (&from, tos) in succs {
+        reversed.entry(from).or_default();
+        if tos.is_empty() {
+            reversed.get_mut(&EXIT).unwrap().push(from);
+        }
+        for &to in tos {
+            reversed.entry(to).or_default().push(from);
+        }
+    }
+
+    let pdom = dominators(EXIT, &reversed);
+    let mut ipdom = BTreeMap::new();
+    for (&node, post) in &pdom {
+        if node == EXIT || !post.contains(&EXIT) {
+            // Blocks that never reach the exit have no useful post-dominator
+            continue;
+        }
+        let closest = post.iter().filter(|&&p| p != node).find(|p| pdom[p].len() == post.len() - 1);
+        if let Some(&closest) = closest.filter(|&&p| p != EXIT) {
+            ipdom.insert(node, closest);
+        }
+    }
+    ipdom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+
+    #[test]
+    fn test_loop() {
+        let program = Program::from_input(SUMS);
+        let cfg = program.control_flow_graph();
+        let starts: Vec<Pos> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, vec![0, 11]);
+        assert_eq!(cfg.blocks[&0].insns.len(), 4);
+        assert_eq!(cfg.blocks[&0].terminator, Terminator::Jump);
+        assert_eq!(cfg.blocks[&11].terminator, Terminator::Halt);
+        assert_eq!(cfg.edges, vec![
+            Edge { from: 0, to: 0, kind: EdgeKind::Taken },
+            Edge { from: 0, to: 11, kind: EdgeKind::NotTaken }
+        ]);
+    }
+
+    #[test]
+    fn test_fallthrough_and_indirect() {
+        // in -> [20]; L2: out [20]; jz [20], L2; jnz #1, [20]
+        let program = Program::from_input("3,20,4,20,1006,20,2,105,1,20,99");
+        let cfg = program.control_flow_graph();
+        let starts: Vec<Pos> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, vec![0, 2, 7]);
+        assert_eq!(cfg.blocks[&0].terminator, Terminator::Fallthrough);
+        assert_eq!(cfg.blocks[&7].terminator, Terminator::Indirect);
+        assert_eq!(cfg.indirect_jumps().count(), 1);
+        assert_eq!(cfg.successors(0).collect::<Vec<_>>(), vec![&Edge { from: 0, to: 2, kind: EdgeKind::Fallthrough }]);
+        assert_eq!(cfg.predecessors(2).count(), 2);
+        assert_eq!(cfg.successors(7).count(), 0);
+    }
+
+    #[test]
+    fn test_invalid_jump_target() {
+        // jnz #1, #4 jumps into the zero after the halt
+        let cfg = Program::from_input("1105,1,4,99,0").control_flow_graph();
+        assert_eq!(cfg.edges, vec![Edge { from: 0, to: 4, kind: EdgeKind::Invalid }]);
+        assert!(cfg.to_json().contains("{\"from\": 0, \"to\": 4, \"kind\": \"invalid\"}"));
+        assert_eq!(cfg.to_dot(), "\
+digraph cfg {
+    node [shape=box fontname=monospace];
+    b0 [label=\"0000  jnz #1, #4\\l\"];
+    b4 [label=\"0004  invalid target\\l\" style=dashed color=red];
+    b0 -> b4 [label=\"invalid\" color=red];
+}
+");
+    }
+
+    #[test]
+    fn test_exports() {
+        let program = Program::from_input("1105,1,3,99");
+        let cfg = program.control_flow_graph();
+        assert_eq!(cfg.to_dot(), "\
+digraph cfg {
+    node [shape=box fontname=monospace];
+    b0 [label=\"0000  jnz #1, #3\\l\"];
+    b3 [label=\"0003  halt\\l\"];
+    b0 -> b3 [label=\"taken\" color=green];
+}
+");
+        assert_eq!(cfg.to_json(), "\
+{
+  \"blocks\": [
+    {\"start\": 0, \"end\": 3, \"terminator\": \"jump\", \"insns\": [{\"addr\": 0, \"text\": \"jnz #1, #3\"}]},
+    {\"start\": 3, \"end\": 4, \"terminator\": \"halt\", \"insns\": [{\"addr\": 3, \"text\": \"halt\"}]}
+  ],
+  \"edges\": [
+    {\"from\": 0, \"to\": 3, \"kind\": \"taken\"}
+  ]
+}
+");
+    }
+}
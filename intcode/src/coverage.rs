@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::disasm::Disassembly;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Opcode, Pos};
+use crate::program::Program;
+
+/// How often a conditional jump went each way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Branch {
+    /// Times the jump was taken
+    pub taken: u64,
+
+    /// Times execution fell through to the next instruction
+    pub not_taken: u64
+}
+
+/// Code coverage collected over one or more runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Times the instruction at each address was executed
+    pub hits: BTreeMap<Pos, u64>,
+
+    /// Outcomes of each `JumpZero` and `JumpNonZero`, keyed by address of the jump
+    pub branches: BTreeMap<Pos, Branch>
+}
+
+impl Coverage {
+    /// Add the counts of another run
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&addr, &hits) in &other.hits {
+            *self.hits.entry(addr).or_insert(0) += hits;
+        }
+        for (&addr, branch) in &other.branches {
+            let entry = self.branches.entry(addr).or_default();
+            entry.taken += branch.taken;
+            entry.not_taken += branch.not_taken;
+        }
+    }
+
+    /// Addresses to report: every instruction of the listing and every executed address, which
+    /// can differ from the listing in self-modifying code
+    fn addresses(&self, disassembly: &Disassembly) -> BTreeSet<Pos> {
+        disassembly.code().map(|(addr, _)| addr).chain(self.hits.keys().copied()).collect()
+    }
+
+    /// Conditional jumps to report: those of the listing and those executed outside it
+    fn jumps(&self, disassembly: &Disassembly) -> BTreeSet<Pos> {
+        disassembly.code()
+            .filter(|(_, op)| matches!(op, Opcode::JumpNonZero(_, _) | Opcode::JumpZero(_, _)))
+            .map(|(addr, _)| addr)
+            .chain(self.branches.keys().copied())
+            .collect()
+    }
+
+    /// Listing with the hit count of each instruction in front of it, in the style of `gcov`.
+    /// Instructions never executed are marked `#####` and data lines `-`. Conditional jumps are
+    /// followed by how often they were taken.
+    pub fn report(&self, disassembly: &Disassembly) -> String {
+        let addresses = self.addresses(disassembly);
+        let executed = addresses.iter().filter(|addr| self.hits.contains_key(addr)).count();
+        let branches = self.jumps(disassembly).len();
+        let directions: usize = self.branches.values()
+            .map(|branch| (branch.taken > 0) as usize + (branch.not_taken > 0) as usize)
+            .sum();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "Instructions executed: {}/{}", executed, addresses.len());
+        let _ = writeln!(out, "Branch directions taken: {}/{}", directions, branches * 2);
+        for line in &disassembly.lines {
+            if let Some(label) = disassembly.labels.get(&line.addr) {
+                let _ = writeln!(out, "{:>9}:{}:", "", label);
+            }
+            let count = match (line.op, self.hits.get(&line.addr)) {
+                (None, _) => "-".to_string(),
+                (Some(_), None) => "#####".to_string(),
+                (Some(_), Some(hits)) => hits.to_string()
+            };
+            let _ = write!(out, "{:>9}:{:04}:    {}", count, line.addr, disassembly.text(line));
+            if line.op.is_some() {
+                self.write_branch(&mut out, line.addr);
+            }
+            let _ = writeln!(out);
+        }
+
+        // Code written at runtime is not in the listing of the original image
+        let code: BTreeSet<Pos> = disassembly.code().map(|(addr, _)| addr).collect();
+        let outside: Vec<(&Pos, &u64)> = self.hits.iter().filter(|(addr, _)| !code.contains(addr)).collect();
+        if !outside.is_empty() {
+            let _ = writeln!(out, "Executed outside the listing:");
+        }
+        for (&addr, hits) in outside {
+            let _ = write!(out, "{:>9}:{:04}:", hits, addr);
+            self.write_branch(&mut out, addr);
+            let _ = writeln!(out);
+        }
+        out
+    }
+
+    /// Append the outcomes of the jump at `addr`, if it is one
+    fn write_branch(&self, out: &mut String, addr: Pos) {
+        if let Some(branch) = self.branches.get(&addr) {
+            let _ = write!(out, " ; taken {}, not taken {}", branch.taken, branch.not_taken);
+        }
+    }
+
+    /// Coverage in the lcov tracefile format for the source file `name`. Line numbers are
+    /// Intcode addresses and each conditional jump is a block with a taken and a not taken
+    /// branch.
+    pub fn to_lcov(&self, name: &str, disassembly: &Disassembly) -> String {
+        let addresses = self.addresses(disassembly);
+        let jumps = self.jumps(disassembly);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", name);
+
+        let mut hit = 0;
+        for &addr in &jumps {
+            // Branches of an instruction that never executed are reported as `-`
+            let (taken, not_taken) = match (self.hits.contains_key(&addr), self.branches.get(&addr)) {
+                (true, Some(branch)) => (branch.taken.to_string(), branch.not_taken.to_string()),
+                (true, None) => ("0".to_string(), "0".to_string()),
+                (false, _) => ("-".to_string(), "-".to_string())
+            };
+            for (index, count) in [taken, not_taken].iter().enumerate() {
+                let _ = writeln!(out, "BRDA:{},0,{},{}", addr, index, count);
+                if count != "-" && count != "0" {
+                    hit += 1;
+                }
+            }
+        }
+        let _ = writeln!(out, "BRF:{}", jumps.len() * 2);
+        let _ = writeln!(out, "BRH:{}", hit);
+
+        for &addr in &addresses {
+            let _ = writeln!(out, "DA:{},{}", addr, self.hits.get(&addr).copied().unwrap_or(0));
+        }
+        let _ = writeln!(out, "LF:{}", addresses.len());
+        let _ = writeln!(out, "LH:{}", addresses.iter().filter(|addr| self.hits.contains_key(addr)).count());
+        let _ = writeln!(out, "end_of_record");
+        out
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Count how often each instruction executes and which way each conditional jump goes. The
+    /// counts are collected in [`Program::coverage`].
+    ///
+    /// A jump to the instruction right after it counts as not taken.
+    pub fn with_coverage(mut self) -> Self {
+        self.coverage = Some(Coverage::default());
+        self
+    }
+
+    /// Update the coverage after the instruction at `from` was executed
+    pub(crate) fn track_coverage(&mut self, from: Pos) {
+        let coverage = match &mut self.coverage {
+            Some(coverage) => coverage,
+            None => return
+        };
+        *coverage.hits.entry(from).or_insert(0) += 1;
+
+        let len = match self.instructions.get(from) {
+            Some(op @ Opcode::JumpNonZero(_, _)) | Some(op @ Opcode::JumpZero(_, _)) => op.len(),
+            _ => return
+        };
+        let branch = coverage.branches.entry(from).or_default();
+        if self.ip == from + len {
+            branch.not_taken += 1;
+        } else {
+            branch.taken += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::Strategy;
+    use crate::fixtures::sums_with_data;
+
+    fn run(input: &str, inputs: &[isize]) -> Coverage {
+        let mut program = Program::from_input(input).with_coverage();
+        program.input.extend(inputs);
+        program.run().unwrap();
+        program.coverage.unwrap()
+    }
+
+    #[test]
+    fn test_counts() {
+        let coverage = run(&sums_with_data(), &[4, 5, 0]);
+        let hits: Vec<(Pos, u64)> = coverage.hits.into_iter().collect();
+        assert_eq!(hits, vec![(0, 3), (2, 3), (6, 3), (8, 3), (11, 1)]);
+        assert_eq!(coverage.branches.get(&8), Some(&Branch { taken: 2, not_taken: 1 }));
+
+        // The same counts with the JIT requested, which coverage turns off
+        let mut program = Program::from_input(&sums_with_data()).with_jit().with_coverage();
+        program.input.extend(&[4, 5, 0]);
+        program.run().unwrap();
+        assert_eq!(program.coverage.unwrap().hits.get(&2), Some(&3));
+    }
+
+    #[test]
+    fn test_merge_and_report() {
+        let listing = Program::from_input(&sums_with_data()).disassemble(Strategy::RecursiveDescent);
+        let mut coverage = run(&sums_with_data(), &[0]);
+        assert!(coverage.report(&listing).starts_with("Instructions executed: 5/5\n\
+                                                       Branch directions taken: 1/2\n"));
+
+        coverage.merge(&run(&sums_with_data(), &[1, 0]));
+        assert_eq!(coverage.report(&listing), "\
+Instructions executed: 5/5
+Branch directions taken: 2/2
+         :L0:
+        3:0000:    in -> [100]
+        3:0002:    add [101], [100] -> [101]
+        3:0006:    out [101]
+        3:0008:    jnz [100], L0 ; taken 1, not taken 2
+        2:0011:    halt
+        -:0012:    .data 7, -3
+");
+    }
+
+    #[test]
+    fn test_self_modified_code() {
+        // Patches the invalid word at 4 into `jnz #1, #8` before executing it
+        let input = "1101,1105,0,4,0,1,8,0,99";
+        let listing = Program::from_input(input).disassemble(Strategy::RecursiveDescent);
+        let report = run(input, &[]).report(&listing);
+        assert!(report.starts_with("Instructions executed: 3/3\n"));
+        let tail: Vec<&str> = report.lines().skip_while(|line| !line.starts_with("Executed")).collect();
+        assert_eq!(tail, vec!["Executed outside the listing:",
+                              "        1:0004: ; taken 1, not taken 0",
+                              "        1:0008:"]);
+    }
+
+    #[test]
+    fn test_branch_total() {
+        // A listed jump that always falls through, then a jump patched in at 7 that is taken. Both
+        // count towards the total, as they do in the lcov output.
+        let input = "1106,1,3,1101,1105,0,7,0,1,11,0,99";
+        let listing = Program::from_input(input).disassemble(Strategy::RecursiveDescent);
+        let coverage = run(input, &[]);
+        assert!(coverage.report(&listing).starts_with("Instructions executed: 4/4\n\
+                                                       Branch directions taken: 2/4\n"));
+        assert!(coverage.to_lcov("patched.ic", &listing).contains("BRF:4\nBRH:2\n"));
+    }
+
+    #[test]
+    fn test_lcov() {
+        // The first jump is always taken, the second never
+        let input = "1105,1,4,0,1105,0,0,99";
+        let listing = Program::from_input(input).disassemble(Strategy::RecursiveDescent);
+        assert_eq!(run(input, &[]).to_lcov("jumps.ic", &listing), "\
+TN:
+SF:jumps.ic
+BRDA:0,0,0,1
+BRDA:0,0,1,0
+BRDA:4,0,0,0
+BRDA:4,0,1,1
+BRF:4
+BRH:2
+DA:0,1
+DA:4,1
+DA:7,1
+LF:3
+LH:3
+end_of_record
+");
+    }
+}
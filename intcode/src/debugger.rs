@@ -0,0 +1,395 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::error::VmError;
+use crate::event::Event;
+use crate::opcode::{Imm, Pos};
+use crate::program::Program;
+
+/// Number of instructions shown by `list` when no count is given
+const LIST_LEN: usize = 8;
+
+/// Number of words shown by `x` when no count is given
+const EXAMINE_LEN: usize = 8;
+
+const HELP: &str = "\
+break <addr>       b   set a breakpoint
+delete <addr>      d   remove a breakpoint
+breakpoints        bl  list breakpoints
+step [n]           s   execute n instructions (default 1)
+continue           c   run until a breakpoint, halt, fault or missing input
+regs               r   show IP, relative base and halted flag
+x <addr> [n]           show n words of memory
+set <addr> <val>       write a word of memory
+input <val>...     i   queue values for `In` instructions
+output             o   show every value output so far
+list [addr] [n]    l   disassemble n instructions (default around the IP)
+backtrace          bt  show the shadow call stack
+help               h   show this help
+quit               q   exit the debugger";
+
+/// A parsed debugger command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Break(Pos),
+    Delete(Pos),
+    Breakpoints,
+    Step(usize),
+    Continue,
+    Registers,
+    Examine(Pos, usize),
+    Set(Pos, Imm),
+    Input(Vec<Imm>),
+    Output,
+    List(Option<Pos>, usize),
+    Backtrace,
+    Help,
+    Quit
+}
+
+fn number<T: std::str::FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("missing {}", what))?;
+    arg.parse().map_err(|_| format!("invalid {}: {}", what, arg))
+}
+
+fn number_or<T: std::str::FromStr>(arg: Option<&str>, what: &str, default: T) -> Result<T, String> {
+    match arg {
+        Some(_) => number(arg, what),
+        None => Ok(default)
+    }
+}
+
+impl Command {
+    /// Parse a line of user input
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let mut args = line.split_whitespace();
+        let name = args.next().ok_or("empty command")?;
+
+        let command = match name {
+            "break" | "b" => Command::Break(number(args.next(), "address")?),
+            "delete" | "d" => Command::Delete(number(args.next(), "address")?),
+            "breakpoints" | "bl" => Command::Breakpoints,
+            "step" | "s" => Command::Step(number_or(args.next(), "count", 1)?),
+            "continue" | "c" => Command::Continue,
+            "regs" | "r" => Command::Registers,
+            "x" => {
+                let address = number(args.next(), "address")?;
+                Command::Examine(address, number_or(args.next(), "count", EXAMINE_LEN)?)
+            }
+            "set" => Command::Set(number(args.next(), "address")?, number(args.next(), "value")?),
+            "input" | "i" => {
+                let values = args.by_ref().map(|arg| number(Some(arg), "value"))
+                                 .collect::<Result<Vec<Imm>, String>>()?;
+                if values.is_empty() {
+                    return Err("missing value".to_string());
+                }
+                Command::Input(values)
+            }
+            "output" | "o" => Command::Output,
+            "list" | "l" => {
+                let address = args.next().map(|arg| number(Some(arg), "address")).transpose()?;
+                Command::List(address, number_or(args.next(), "count", LIST_LEN)?)
+            }
+            "backtrace" | "bt" => Command::Backtrace,
+            "help" | "h" => Command::Help,
+            "quit" | "q" => Command::Quit,
+            _ => return Err(format!("unknown command: {} (try `help`)", name))
+        };
+
+        if let Some(arg) = args.next() {
+            return Err(format!("unexpected argument: {}", arg));
+        }
+
+        Ok(command)
+    }
+}
+
+/// Reason execution stopped after a `step` or `continue`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Every requested step was executed
+    Stepped,
+
+    /// Execution reached a breakpoint, which has not executed yet
+    Breakpoint(Pos),
+
+    /// The program needs input that has not been queued
+    NeedsInput,
+
+    /// The program halted
+    Halted,
+
+    /// An instruction faulted
+    Fault(VmError)
+}
+
+/// Interactive debugger wrapping a [`Program`]
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    /// Program being debugged
+    pub program: Program,
+
+    /// Addresses execution stops at before executing the instruction there
+    pub breakpoints: BTreeSet<Pos>,
+
+    /// Number of outputs already shown to the user
+    outputs_shown: usize
+}
+
+impl Debugger {
+    pub fn new(program: Program) -> Debugger {
+        Debugger {
+            program,
+            breakpoints: BTreeSet::new(),
+            outputs_shown: 0
+        }
+    }
+
+    /// Execute up to `count` instructions, stopping early at a breakpoint other than the one the
+    /// IP starts on. A `count` of `None` runs until something else stops execution.
+    pub fn run(&mut self, count: Option<usize>) -> Stop {
+        let mut executed = 0;
+        loop {
+            if count == Some(executed) {
+                return Stop::Stepped;
+            }
+            if executed > 0 && self.breakpoints.contains(&self.program.ip) {
+                return Stop::Breakpoint(self.program.ip);
+            }
+
+            match self.program.step() {
+                Ok(Event::Executed) | Ok(Event::Output(_)) => executed += 1,
+                Ok(Event::NeedsInput) => return Stop::NeedsInput,
+                Ok(Event::Halted) => return Stop::Halted,
+                Err(err) => return Stop::Fault(err)
+            }
+        }
+    }
+
+    /// Execute a command, returning the text to show the user
+    pub fn execute(&mut self, command: &Command) -> String {
+        let mut out = String::new();
+        match *command {
+            Command::Break(address) => {
+                self.breakpoints.insert(address);
+                let _ = write!(out, "Breakpoint at {}", address);
+            }
+            Command::Delete(address) => {
+                if self.breakpoints.remove(&address) {
+                    let _ = write!(out, "Deleted breakpoint at {}", address);
+                } else {
+                    let _ = write!(out, "No breakpoint at {}", address);
+                }
+            }
+            Command::Breakpoints => {
+                if self.breakpoints.is_empty() {
+                    out.push_str("No breakpoints");
+                }
+                for address in &self.breakpoints {
+                    let _ = writeln!(out, "{}", address);
+                }
+            }
+            Command::Step(count) => {
+                let stop = self.run(Some(count));
+                self.describe_stop(&mut out, stop);
+            }
+            Command::Continue => {
+                let stop = self.run(None);
+                self.describe_stop(&mut out, stop);
+            }
+            Command::Registers => {
+                let _ = write!(out, "ip {}  rb {}  halted {}", self.program.ip, self.program.relative_base,
+                               self.program.halted);
+            }
+            Command::Examine(address, count) => {
+                for (row, start) in (address..address.saturating_add(count)).step_by(EXAMINE_LEN).enumerate() {
+                    if row > 0 {
+                        out.push('\n');
+                    }
+                    let _ = write!(out, "{:06}:", start);
+                    let end = address.saturating_add(count).min(start.saturating_add(EXAMINE_LEN));
+                    for addr in start..end {
+                        let _ = write!(out, " {}", self.program.read(addr));
+                    }
+                }
+            }
+            Command::Set(address, value) => {
+                self.program.write(address, value);
+                let _ = write!(out, "[{}] = {}", address, value);
+            }
+            Command::Input(ref values) => {
+                self.program.input.extend(values);
+                let _ = write!(out, "{} value(s) queued", self.program.input.len());
+            }
+            Command::Output => {
+                let values: Vec<String> = self.program.output.iter().map(|v| v.to_string()).collect();
+                out.push_str(&values.join(","));
+                self.outputs_shown = self.program.output.len();
+            }
+            Command::List(address, count) => self.list(&mut out, address, count),
+            Command::Backtrace => {
+                if self.program.call_stack.is_none() {
+                    out.push_str("Call stack tracking is disabled");
+                }
+                for (depth, (addr, function)) in self.program.backtrace().into_iter().enumerate() {
+                    let _ = writeln!(out, "#{:<3}{:06} in F{}", depth, addr, function);
+                }
+            }
+            Command::Help => out.push_str(HELP),
+            Command::Quit => {}
+        }
+
+        out.trim_end().to_string()
+    }
+
+    /// Describe why execution stopped, any new output, and the next instruction
+    fn describe_stop(&mut self, out: &mut String, stop: Stop) {
+        for value in &self.program.output[self.outputs_shown..] {
+            let _ = writeln!(out, "output: {}", value);
+        }
+        self.outputs_shown = self.program.output.len();
+
+        match stop {
+            Stop::Stepped => {}
+            Stop::Breakpoint(address) => { let _ = writeln!(out, "Breakpoint at {}", address); }
+            Stop::NeedsInput => out.push_str("Waiting for input, queue some with `input`\n"),
+            Stop::Halted => out.push_str("Program halted\n"),
+            Stop::Fault(err) => { let _ = writeln!(out, "Fault: {}", err); }
+        }
+        self.list(out, None, 1);
+    }
+
+    /// Disassemble `count` instructions starting at `address`. Without an address, the listing
+    /// starts a few instructions before the IP when earlier instructions have been lifted.
+    fn list(&self, out: &mut String, address: Option<Pos>, count: usize) {
+        let mut addr = match address {
+            Some(address) => address,
+            None => self.listing_start(count / 2)
+        };
+
+        for _ in 0..count {
+            let marker = if addr == self.program.ip { "=>" } else { "  " };
+            let bp = if self.breakpoints.contains(&addr) { "*" } else { " " };
+            let next = match self.program.decode(addr) {
+                Ok(op) => {
+                    let _ = writeln!(out, "{}{}{:06}  {}", marker, bp, addr, op);
+                    addr.checked_add(op.len())
+                }
+                Err(_) => {
+                    let _ = writeln!(out, "{}{}{:06}  .data {}", marker, bp, addr, self.program.read(addr));
+                    addr.checked_add(1)
+                }
+            };
+
+            // Stop at the end of the address space
+            match next {
+                Some(next) => addr = next,
+                None => break
+            }
+        }
+    }
+
+    /// Walk back from the IP over up to `before` previously lifted instructions that end exactly
+    /// where the next one starts
+    fn listing_start(&self, before: usize) -> Pos {
+        let mut start = self.program.ip;
+        for _ in 0..before {
+            let previous = (1..=crate::opcode::Opcode::MAX_LEN).filter_map(|len| start.checked_sub(len))
+                .find(|&addr| self.program.instructions.get(addr).is_some_and(|op| addr + op.len() == start));
+            match previous {
+                Some(addr) => start = addr,
+                None => break
+            }
+        }
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+
+    fn run(debugger: &mut Debugger, line: &str) -> String {
+        debugger.execute(&Command::parse(line).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse("b 10"), Ok(Command::Break(10)));
+        assert_eq!(Command::parse("step"), Ok(Command::Step(1)));
+        assert_eq!(Command::parse("s 5"), Ok(Command::Step(5)));
+        assert_eq!(Command::parse("x 3"), Ok(Command::Examine(3, EXAMINE_LEN)));
+        assert_eq!(Command::parse("set 3 -4"), Ok(Command::Set(3, -4)));
+        assert_eq!(Command::parse("i 1 2 3"), Ok(Command::Input(vec![1, 2, 3])));
+        assert_eq!(Command::parse("l"), Ok(Command::List(None, LIST_LEN)));
+        assert!(Command::parse("b").is_err());
+        assert!(Command::parse("b -1").is_err());
+        assert!(Command::parse("r 1").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_breakpoints_and_input() {
+        let mut debugger = Debugger::new(Program::from_input(SUMS));
+        assert_eq!(run(&mut debugger, "c"), "Waiting for input, queue some with `input`\n\
+                                             => 000000  in -> [100]");
+
+        run(&mut debugger, "i 4 5 0");
+        run(&mut debugger, "b 6");
+        assert_eq!(run(&mut debugger, "c"), "Breakpoint at 6\n=>*000006  out [101]");
+        assert_eq!(run(&mut debugger, "s"), "output: 4\n=> 000008  jnz [100], #0");
+
+        // Continuing from a breakpoint executes it before checking breakpoints again
+        assert_eq!(run(&mut debugger, "c"), "Breakpoint at 6\n=>*000006  out [101]");
+        run(&mut debugger, "d 6");
+        assert_eq!(run(&mut debugger, "c"), "output: 9\noutput: 9\nProgram halted\n=> 000011  halt");
+        assert_eq!(run(&mut debugger, "o"), "4,9,9");
+        assert_eq!(run(&mut debugger, "r"), "ip 11  rb 0  halted true");
+    }
+
+    #[test]
+    fn test_memory_and_listing() {
+        let mut debugger = Debugger::new(Program::from_input(SUMS));
+        assert_eq!(run(&mut debugger, "x 0 10"), "000000: 3 100 1 101 100 101 4 101\n000008: 1005 100");
+        assert_eq!(run(&mut debugger, "set 101 7"), "[101] = 7");
+        assert_eq!(run(&mut debugger, "x 101 1"), "000101: 7");
+
+        assert_eq!(run(&mut debugger, "l 6 3"),
+                   "   000006  out [101]\n   000008  jnz [100], #0\n   000011  halt");
+        assert_eq!(run(&mut debugger, "l 12 1"), "   000012  .data 0");
+
+        // The listing stops at the end of the address space
+        assert_eq!(run(&mut debugger, "l 18446744073709551615 2"), "   18446744073709551615  .data 0");
+        run(&mut debugger, "set 18446744073709551614 1101");
+        assert_eq!(run(&mut debugger, "l 18446744073709551614 3"),
+                   "   18446744073709551614  .data 1101\n   18446744073709551615  .data 0");
+
+        // Once executed, earlier instructions are shown before the IP
+        run(&mut debugger, "i 1");
+        run(&mut debugger, "s 2");
+        assert_eq!(run(&mut debugger, "l 0 2"),
+                   "   000000  in -> [100]\n   000002  add [101], [100] -> [101]");
+        assert!(run(&mut debugger, "l").starts_with("   000000  in -> [100]\n   000002  add"));
+    }
+
+    #[test]
+    fn test_backtrace() {
+        // arb #100, add #9, #0 -> [rb], jz #0, #10, halt, out #1, jz #0, [rb]
+        let program = Program::from_input("109,100,21101,9,0,0,1106,0,10,99,104,1,2106,0,0");
+        let mut debugger = Debugger::new(program.clone());
+        assert_eq!(run(&mut debugger, "bt"), "Call stack tracking is disabled");
+
+        let mut debugger = Debugger::new(program.with_call_stack());
+        run(&mut debugger, "s 3");
+        assert_eq!(run(&mut debugger, "bt"), "#0  000010 in F10\n#1  000006 in F0");
+        run(&mut debugger, "s 2");
+        assert_eq!(run(&mut debugger, "bt"), "#0  000009 in F0");
+    }
+
+    #[test]
+    fn test_fault() {
+        let mut debugger = Debugger::new(Program::from_input("1,-1,0,0"));
+        assert_eq!(run(&mut debugger, "s"), "Fault: [0] negative address -1 (opcode 1)\n=> 000000  .data 1");
+    }
+}
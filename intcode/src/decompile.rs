@@ -0,0 +1,1100 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::calls::{CallGraph, Function};
+use crate::cfg::{immediate_post_dominators, natural_loops, EdgeKind, Terminator};
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Memory location read or written by pseudo-code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Var {
+    /// Absolute address, shown as `m<addr>`
+    Global(Pos),
+
+    /// Slot in the caller's frame, by offset from the relative base at function entry. Arguments
+    /// and results are passed here. Shown as `arg<n>`.
+    Arg(isize),
+
+    /// Slot in the function's own frame, by offset from the relative base after the frame is
+    /// set up. Shown as `local<n>`.
+    Local(isize),
+
+    /// Offset from a relative base that could not be tracked statically, shown as `rb[n]`
+    Rel(isize)
+}
+
+/// Binary operator of an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Lt,
+    Ge,
+    Eq,
+    Ne
+}
+
+/// Pseudo-code expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Const(Imm),
+    Var(Var),
+
+    /// Next value read from the input
+    Input,
+
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>)
+}
+
+/// Pseudo-code statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    Assign(Var, Expr),
+    Output(Expr),
+
+    /// Call of the function at the given entry point
+    Call(Pos),
+
+    /// Return to the caller
+    Return,
+
+    /// Jump to an address computed at runtime
+    JumpIndirect(Expr),
+
+    /// Jump to a label, for control flow that does not fit `if` and loops
+    Goto(Pos),
+    Label(Pos),
+
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Loop(Vec<Stmt>),
+    Break,
+    Continue,
+    Halt,
+
+    /// An instruction that always faults, such as one writing to an immediate parameter
+    Trap
+}
+
+/// Pseudo-code of one recovered function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCode {
+    /// Entry point of the function
+    pub entry: Pos,
+
+    /// Stack frame size, if the function sets one up
+    pub frame_size: Option<Imm>,
+
+    /// Structured body
+    pub body: Vec<Stmt>
+}
+
+/// Pseudo-code of every recovered function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decompiled {
+    /// Functions in address order
+    pub functions: Vec<FunctionCode>
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Lt => "<",
+            BinOp::Ge => ">=",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!="
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Mul => 3,
+            BinOp::Add | BinOp::Sub => 2,
+            BinOp::Lt | BinOp::Ge | BinOp::Eq | BinOp::Ne => 1
+        }
+    }
+
+    /// Comparison with the opposite result
+    fn negate(self) -> Option<BinOp> {
+        match self {
+            BinOp::Lt => Some(BinOp::Ge),
+            BinOp::Ge => Some(BinOp::Lt),
+            BinOp::Eq => Some(BinOp::Ne),
+            BinOp::Ne => Some(BinOp::Eq),
+            _ => None
+        }
+    }
+}
+
+impl Expr {
+    /// Build a binary expression, folding constants and tidying up the idioms compilers emit
+    fn binary(op: BinOp, a: Expr, b: Expr) -> Expr {
+        use Expr::Const;
+
+        match (op, a, b) {
+            (BinOp::Add, Const(a), Const(b)) if a.checked_add(b).is_some() => Const(a + b),
+            (BinOp::Mul, Const(a), Const(b)) if a.checked_mul(b).is_some() => Const(a * b),
+            (BinOp::Lt, Const(a), Const(b)) => Const((a < b) as Imm),
+            (BinOp::Ge, Const(a), Const(b)) => Const((a >= b) as Imm),
+            (BinOp::Eq, Const(a), Const(b)) => Const((a == b) as Imm),
+            (BinOp::Ne, Const(a), Const(b)) => Const((a != b) as Imm),
+
+            // Keep constants on the right
+            (BinOp::Add, Const(a), b) => Expr::binary(BinOp::Add, b, Const(a)),
+            (BinOp::Mul, Const(a), b) => Expr::binary(BinOp::Mul, b, Const(a)),
+            (BinOp::Eq, Const(a), b) => Expr::binary(BinOp::Eq, b, Const(a)),
+            (BinOp::Ne, Const(a), b) => Expr::binary(BinOp::Ne, b, Const(a)),
+
+            (BinOp::Add, a, Const(0)) | (BinOp::Mul, a, Const(1)) => a,
+            (BinOp::Add, a, Const(b)) if b < 0 && b != Imm::MIN => Expr::binary(BinOp::Sub, a, Const(-b)),
+            (BinOp::Mul, a, Const(-1)) => Expr::Neg(Box::new(a)),
+
+            // Comparisons already produce 0 or 1
+            (BinOp::Ne, a, Const(0)) if a.is_comparison() => a,
+            (BinOp::Eq, a, Const(0)) if a.is_comparison() => a.not(),
+
+            (op, a, b) => Expr::Binary(op, Box::new(a), Box::new(b))
+        }
+    }
+
+    fn is_comparison(&self) -> bool {
+        matches!(self, Expr::Binary(op, _, _) if op.negate().is_some())
+    }
+
+    /// Condition that holds when the value is non-zero
+    fn truth(self) -> Expr {
+        Expr::binary(BinOp::Ne, self, Expr::Const(0))
+    }
+
+    /// Condition that holds when the value is zero
+    fn not(self) -> Expr {
+        match self {
+            Expr::Binary(op, a, b) if op.negate().is_some() => Expr::Binary(op.negate().unwrap(), a, b),
+            Expr::Const(value) => Expr::Const((value == 0) as Imm),
+            other => Expr::Binary(BinOp::Eq, Box::new(other), Box::new(Expr::Const(0)))
+        }
+    }
+
+    fn reads(&self, vars: &mut Vec<Var>) {
+        match self {
+            Expr::Var(var) => vars.push(*var),
+            Expr::Neg(a) => a.reads(vars),
+            Expr::Binary(_, a, b) => {
+                a.reads(vars);
+                b.reads(vars);
+            }
+            Expr::Const(_) | Expr::Input => {}
+        }
+    }
+
+    fn has_input(&self) -> bool {
+        match self {
+            Expr::Input => true,
+            Expr::Neg(a) => a.has_input(),
+            Expr::Binary(_, a, b) => a.has_input() || b.has_input(),
+            Expr::Const(_) | Expr::Var(_) => false
+        }
+    }
+
+    /// Replace every read of `var` with `value` and simplify the result
+    fn substitute(self, var: Var, value: &Expr) -> Expr {
+        match self {
+            Expr::Var(v) if v == var => value.clone(),
+            Expr::Neg(a) => match a.substitute(var, value) {
+                Expr::Const(a) if a != Imm::MIN => Expr::Const(-a),
+                a => Expr::Neg(Box::new(a))
+            },
+            Expr::Binary(op, a, b) => Expr::binary(op, a.substitute(var, value), b.substitute(var, value)),
+            other => other
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter, parent: u8) -> fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Var(var) => write!(f, "{}", var),
+            Expr::Input => write!(f, "input()"),
+            Expr::Neg(a) => {
+                write!(f, "-")?;
+                a.fmt_prec(f, 4)
+            }
+            Expr::Binary(op, a, b) => {
+                let prec = op.precedence();
+                if prec < parent {
+                    write!(f, "(")?;
+                }
+                // Arithmetic is left associative, comparisons do not chain
+                let left = if op.negate().is_some() { prec + 1 } else { prec };
+                a.fmt_prec(f, left)?;
+                write!(f, " {} ", op.symbol())?;
+                b.fmt_prec(f, prec + 1)?;
+                if prec < parent {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Var::Global(addr) => write!(f, "m{}", addr),
+            Var::Arg(n) if n < 0 => write!(f, "arg_m{}", n.unsigned_abs()),
+            Var::Arg(n) => write!(f, "arg{}", n),
+            Var::Local(n) if n < 0 => write!(f, "local_m{}", n.unsigned_abs()),
+            Var::Local(n) => write!(f, "local{}", n),
+            Var::Rel(n) => write!(f, "rb[{}]", n)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+impl Stmt {
+    /// Location written by the statement
+    fn writes(&self) -> Option<Var> {
+        match self {
+            Stmt::Assign(var, _) => Some(*var),
+            _ => None
+        }
+    }
+
+    /// Expressions evaluated by the statement itself
+    fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Stmt::Assign(_, expr) | Stmt::Output(expr) | Stmt::JumpIndirect(expr) | Stmt::If(expr, _, _)
+                | Stmt::While(expr, _) => vec![expr],
+            _ => Vec::new()
+        }
+    }
+
+    /// Returns true if the statement may observe any location, such as a call or return
+    fn reads_everything(&self) -> bool {
+        match self {
+            Stmt::Call(_) | Stmt::Return | Stmt::JumpIndirect(_) => true,
+            Stmt::If(_, then, other) => then.iter().chain(other).any(Stmt::reads_everything),
+            _ => false
+        }
+    }
+
+    fn has_io(&self) -> bool {
+        match self {
+            Stmt::Output(_) => true,
+            Stmt::Assign(_, expr) | Stmt::JumpIndirect(expr) | Stmt::If(expr, _, _) | Stmt::While(expr, _) => {
+                expr.has_input()
+            }
+            _ => false
+        }
+    }
+
+    /// Locations read by the statement's own expressions
+    fn reads(&self, vars: &mut Vec<Var>) {
+        match self {
+            Stmt::Assign(_, expr) | Stmt::Output(expr) | Stmt::JumpIndirect(expr) | Stmt::If(expr, _, _)
+                | Stmt::While(expr, _) => expr.reads(vars),
+            _ => {}
+        }
+    }
+
+    fn count_reads(&self, var: Var) -> usize {
+        let mut vars = Vec::new();
+        self.reads(&mut vars);
+        vars.iter().filter(|&&v| v == var).count()
+    }
+
+    fn substitute(&mut self, var: Var, value: &Expr) {
+        for expr in self.exprs_mut() {
+            *expr = std::mem::replace(expr, Expr::Const(0)).substitute(var, value);
+        }
+    }
+}
+
+fn write_stmts(f: &mut fmt::Formatter, stmts: &[Stmt], indent: usize) -> fmt::Result {
+    let pad = "    ".repeat(indent);
+    for stmt in stmts {
+        match stmt {
+            Stmt::Assign(var, expr) => writeln!(f, "{}{} = {};", pad, var, expr)?,
+            Stmt::Output(expr) => writeln!(f, "{}output({});", pad, expr)?,
+            Stmt::Call(entry) => writeln!(f, "{}F{}();", pad, entry)?,
+            Stmt::Return => writeln!(f, "{}return;", pad)?,
+            Stmt::JumpIndirect(expr) => writeln!(f, "{}goto *{};", pad, expr)?,
+            Stmt::Goto(addr) => writeln!(f, "{}goto L{};", pad, addr)?,
+            Stmt::Label(addr) => writeln!(f, "{}L{}:", "    ".repeat(indent - 1), addr)?,
+            Stmt::If(cond, then, other) => {
+                writeln!(f, "{}if {} {{", pad, cond)?;
+                write_stmts(f, then, indent + 1)?;
+                if !other.is_empty() {
+                    writeln!(f, "{}}} else {{", pad)?;
+                    write_stmts(f, other, indent + 1)?;
+                }
+                writeln!(f, "{}}}", pad)?;
+            }
+            Stmt::While(cond, body) => {
+                writeln!(f, "{}while {} {{", pad, cond)?;
+                write_stmts(f, body, indent + 1)?;
+                writeln!(f, "{}}}", pad)?;
+            }
+            Stmt::Loop(body) => {
+                writeln!(f, "{}loop {{", pad)?;
+                write_stmts(f, body, indent + 1)?;
+                writeln!(f, "{}}}", pad)?;
+            }
+            Stmt::Break => writeln!(f, "{}break;", pad)?,
+            Stmt::Continue => writeln!(f, "{}continue;", pad)?,
+            Stmt::Halt => writeln!(f, "{}halt;", pad)?,
+            Stmt::Trap => writeln!(f, "{}trap;", pad)?
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for FunctionCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.frame_size {
+            Some(size) => writeln!(f, "fn F{}() {{  // frame {}", self.entry, size)?,
+            None => writeln!(f, "fn F{}() {{", self.entry)?
+        }
+        write_stmts(f, &self.body, 1)?;
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Decompiled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+/// How control leaves a lowered block
+#[derive(Debug, Clone)]
+enum Exit {
+    Goto(Pos),
+
+    /// Condition for the first target, otherwise the second target
+    Branch(Expr, Pos, Pos),
+
+    /// Return, halt or fault
+    End
+}
+
+/// Lowers the instructions of one function to statements
+struct Lowering<'a> {
+    graph: &'a CallGraph,
+    function: &'a Function
+}
+
+impl Lowering<'_> {
+    /// Variable for a parameter, given the relative base offset from function entry
+    fn var(&self, mode: Mode, delta: Option<isize>) -> Option<Var> {
+        match mode {
+            Mode::Immediate(_) => None,
+            Mode::Positional(addr) => Some(Var::Global(addr)),
+            // Offsets that overflow are left relative to the current base, as for an unknown delta
+            Mode::Relative(offset) => Some(match (delta.and_then(|delta| delta.checked_add(offset)),
+                                                  self.function.frame_size) {
+                (Some(slot), Some(frame)) if slot < frame => Var::Arg(slot),
+                (Some(slot), Some(frame)) => slot.checked_sub(frame).map_or(Var::Rel(offset), Var::Local),
+                (Some(slot), None) => Var::Local(slot),
+                (None, _) => Var::Rel(offset)
+            })
+        }
+    }
+
+    fn operand(&self, mode: Mode, delta: Option<isize>) -> Expr {
+        match mode {
+            Mode::Immediate(value) => Expr::Const(value),
+            _ => Expr::Var(self.var(mode, delta).unwrap())
+        }
+    }
+
+    /// Intra-procedural successors. A call continues at its return address.
+    fn successors(&self, start: Pos) -> Vec<Pos> {
+        self.graph.local_successors(self.function, start)
+    }
+
+    /// Relative base offset from function entry at the start of every block, where known
+    fn deltas(&self) -> BTreeMap<Pos, Option<isize>> {
+        let mut deltas = BTreeMap::new();
+        deltas.insert(self.function.entry, Some(0));
+        let mut worklist = vec![self.function.entry];
+        while let Some(start) = worklist.pop() {
+            let mut delta = deltas[&start];
+            for (_, op) in &self.graph.cfg.blocks[&start].insns {
+                if let Opcode::AdjustRelativeBase(offset) = *op {
+                    delta = match offset {
+                        Mode::Immediate(offset) => delta.and_then(|delta: isize| delta.checked_add(offset)),
+                        _ => None
+                    };
+                }
+            }
+            for next in self.successors(start) {
+                let merged = match deltas.get(&next) {
+                    None => delta,
+                    Some(&old) if old == delta => continue,
+                    Some(_) => None
+                };
+                if deltas.insert(next, merged) != Some(merged) {
+                    worklist.push(next);
+                }
+            }
+        }
+        deltas
+    }
+
+    /// Lower a basic block to statements and its exit
+    fn block(&self, start: Pos, mut delta: Option<isize>) -> (Vec<Stmt>, Exit) {
+        let block = &self.graph.cfg.blocks[&start];
+        let mut stmts = Vec::new();
+        for &(addr, op) in &block.insns {
+            let binary = |op, a, b, dest| -> Option<Stmt> {
+                let var = self.var(dest, delta)?;
+                Some(Stmt::Assign(var, Expr::binary(op, self.operand(a, delta), self.operand(b, delta))))
+            };
+            let stmt = match op {
+                Opcode::Add(a, b, dest) => binary(BinOp::Add, a, b, dest),
+                Opcode::Mul(a, b, dest) => binary(BinOp::Mul, a, b, dest),
+                Opcode::LessThan(a, b, dest) => binary(BinOp::Lt, a, b, dest),
+                Opcode::Equals(a, b, dest) => binary(BinOp::Eq, a, b, dest),
+                Opcode::In(dest) => self.var(dest, delta).map(|var| Stmt::Assign(var, Expr::Input)),
+                Opcode::Out(a) => Some(Stmt::Output(self.operand(a, delta))),
+                Opcode::AdjustRelativeBase(offset) => {
+                    delta = match offset {
+                        Mode::Immediate(offset) => delta.and_then(|delta: isize| delta.checked_add(offset)),
+                        _ => None
+                    };
+                    continue;
+                }
+                Opcode::Halt => {
+                    stmts.push(Stmt::Halt);
+                    return (stmts, Exit::End);
+                }
+                Opcode::JumpNonZero(cond, target) | Opcode::JumpZero(cond, target) => {
+                    return self.jump(start, addr, op, cond, target, delta, stmts);
+                }
+            };
+
+            match stmt {
+                // Copies of a location to itself are how some programs spell `nop`
+                Some(Stmt::Assign(var, Expr::Var(src))) if var == src => {}
+                Some(stmt) => stmts.push(stmt),
+                None => {
+                    stmts.push(Stmt::Trap);
+                    return (stmts, Exit::End);
+                }
+            }
+        }
+
+        match block.terminator {
+            Terminator::Fallthrough => (stmts, Exit::Goto(block.end)),
+            _ => {
+                stmts.push(Stmt::Trap);
+                (stmts, Exit::End)
+            }
+        }
+    }
+
+    /// Lower the jump ending a block
+    #[allow(clippy::too_many_arguments)]
+    fn jump(&self, start: Pos, addr: Pos, op: Opcode, cond: Mode, target: Mode, delta: Option<isize>,
+            mut stmts: Vec<Stmt>) -> (Vec<Stmt>, Exit) {
+        if let Some(call) = self.function.calls.iter().find(|call| call.addr == addr) {
+            // The store of the return address is part of the call
+            stmts.pop();
+            stmts.push(Stmt::Call(call.target));
+            return (stmts, Exit::Goto(call.return_addr));
+        }
+
+        let value = self.operand(cond, delta);
+        let cond = match op {
+            Opcode::JumpNonZero(_, _) => value.truth(),
+            _ => value.not()
+        };
+
+        let mut taken = None;
+        let mut not_taken = None;
+        for edge in self.graph.cfg.successors(start) {
+            match edge.kind {
+                EdgeKind::Taken | EdgeKind::Invalid => taken = Some(edge.to),
+                EdgeKind::NotTaken | EdgeKind::Fallthrough => not_taken = Some(edge.to)
+            }
+        }
+
+        if !matches!(target, Mode::Immediate(_)) {
+            let jump = if self.function.returns.contains(&addr) {
+                Stmt::Return
+            } else {
+                Stmt::JumpIndirect(self.operand(target, delta))
+            };
+            return match not_taken {
+                Some(next) => {
+                    stmts.push(Stmt::If(cond, vec![jump], Vec::new()));
+                    (stmts, Exit::Goto(next))
+                }
+                None => {
+                    stmts.push(jump);
+                    (stmts, Exit::End)
+                }
+            };
+        }
+
+        match (taken, not_taken) {
+            (Some(taken), Some(not_taken)) => (stmts, Exit::Branch(cond, taken, not_taken)),
+            (Some(next), None) | (None, Some(next)) => (stmts, Exit::Goto(next)),
+            (None, None) => {
+                // Jump to a negative address
+                stmts.push(Stmt::Trap);
+                (stmts, Exit::End)
+            }
+        }
+    }
+}
+
+/// Fold assignments whose value is used exactly once, before anything it reads is overwritten,
+/// into that use. `live_out` holds the locations that may be read after the block.
+fn fold(stmts: &mut Vec<Stmt>, live_out: &BTreeSet<Var>) {
+    'restart: loop {
+        for i in 0..stmts.len() {
+            let (var, value) = match &stmts[i] {
+                Stmt::Assign(var, value) if !matches!(var, Var::Rel(_)) => (*var, value.clone()),
+                _ => continue
+            };
+
+            let mut uses = 0;
+            let mut use_at = None;
+            let mut killed = false;
+            for (j, stmt) in stmts.iter().enumerate().skip(i + 1) {
+                if stmt.reads_everything() {
+                    uses += 2;
+                    break;
+                }
+                let count = stmt.count_reads(var);
+                if count > 0 && use_at.is_none() {
+                    use_at = Some(j);
+                }
+                uses += count;
+                if stmt.writes() == Some(var) {
+                    killed = true;
+                    break;
+                }
+            }
+            let j = match use_at {
+                Some(j) if uses == 1 && (killed || !live_out.contains(&var)) => j,
+                _ => continue
+            };
+
+            // Nothing in between may change the value, and inputs and outputs keep their order
+            let mut reads = Vec::new();
+            value.reads(&mut reads);
+            let has_input = value.has_input();
+            let clobbered = stmts[i + 1..j].iter().any(|stmt| {
+                stmt.writes().is_some_and(|written| reads.contains(&written) || matches!(written, Var::Rel(_)))
+                    || (has_input && stmt.has_io())
+            });
+            if clobbered || (has_input && stmts[j].has_io()) {
+                continue;
+            }
+
+            stmts[j].substitute(var, &value);
+            stmts.remove(i);
+            continue 'restart;
+        }
+        return;
+    }
+}
+
+/// What is known about the memory other functions read, so that liveness can cross calls and
+/// returns
+#[derive(Debug, Default)]
+struct Summaries {
+    /// Globals each function may read before writing them, by entry point
+    entry_reads: BTreeMap<Pos, BTreeSet<Var>>,
+
+    /// Globals that may be read after each function returns, by entry point
+    return_live: BTreeMap<Pos, BTreeSet<Var>>,
+
+    /// Every global touched by any function
+    globals: BTreeSet<Var>
+}
+
+/// A function lowered to statements, before folding and structuring
+struct Lowered {
+    entry: Pos,
+    frame_size: Option<Imm>,
+    code: BTreeMap<Pos, (Vec<Stmt>, Exit)>,
+
+    /// Intra-procedural successors of every block
+    succs: BTreeMap<Pos, Vec<Pos>>,
+
+    /// Frame slots the function touches
+    frame: BTreeSet<Var>,
+
+    /// Set if the relative base could not be tracked, so frame slots may alias
+    untracked: bool
+}
+
+impl Lowered {
+    /// Statements of a block, with a branch condition as a trailing empty `if`
+    fn block_stmts(&self, start: Pos) -> Vec<Stmt> {
+        let (stmts, exit) = &self.code[&start];
+        let mut stmts = stmts.clone();
+        if let Exit::Branch(cond, _, _) = exit {
+            stmts.push(Stmt::If(cond.clone(), Vec::new(), Vec::new()));
+        }
+        stmts
+    }
+
+    /// Update the live locations backwards over one statement
+    fn transfer(&self, stmt: &Stmt, live: &mut BTreeSet<Var>, summaries: &Summaries) {
+        match stmt {
+            Stmt::Halt | Stmt::Trap => live.clear(),
+            Stmt::Call(target) => {
+                live.extend(&self.frame);
+                live.extend(summaries.entry_reads.get(target).into_iter().flatten());
+            }
+            Stmt::Return => {
+                *live = self.frame.clone();
+                live.extend(summaries.return_live.get(&self.entry).into_iter().flatten());
+            }
+            Stmt::JumpIndirect(_) => *live = self.frame.union(&summaries.globals).copied().collect(),
+            Stmt::Assign(var, _) => {
+                live.remove(var);
+            }
+            Stmt::If(_, then, other) => {
+                for branch in &[then, other] {
+                    let mut branch_live = live.clone();
+                    for stmt in branch.iter().rev() {
+                        self.transfer(stmt, &mut branch_live, summaries);
+                    }
+                    live.extend(branch_live);
+                }
+            }
+            _ => {}
+        }
+
+        let mut reads = Vec::new();
+        stmt.reads(&mut reads);
+        live.extend(reads);
+    }
+
+    fn live_out(&self, start: Pos, live_in: &BTreeMap<Pos, BTreeSet<Var>>) -> BTreeSet<Var> {
+        self.succs[&start].iter().flat_map(|next| live_in.get(next).into_iter().flatten()).copied().collect()
+    }
+
+    /// Locations live on entry to every block
+    fn liveness(&self, summaries: &Summaries) -> BTreeMap<Pos, BTreeSet<Var>> {
+        let mut live_in = BTreeMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &start in self.code.keys().rev() {
+                let mut live = self.live_out(start, &live_in);
+                for stmt in self.block_stmts(start).iter().rev() {
+                    self.transfer(stmt, &mut live, summaries);
+                }
+                if live_in.get(&start) != Some(&live) {
+                    live_in.insert(start, live);
+                    changed = true;
+                }
+            }
+        }
+        live_in
+    }
+
+    /// Fold expressions and rebuild structured control flow
+    fn finish(mut self, summaries: &Summaries) -> FunctionCode {
+        if !self.untracked {
+            let live_in = self.liveness(summaries);
+            let starts: Vec<Pos> = self.code.keys().copied().collect();
+            for start in starts {
+                let live_out = self.live_out(start, &live_in);
+                let mut stmts = self.block_stmts(start);
+                fold(&mut stmts, &live_out);
+
+                let (block, exit) = self.code.get_mut(&start).unwrap();
+                if let Exit::Branch(old, _, _) = exit {
+                    if let Some(Stmt::If(cond, _, _)) = stmts.pop() {
+                        *old = cond;
+                    }
+                }
+                *block = stmts;
+            }
+        }
+
+        let succs = &self.succs;
+        let ipdom = immediate_post_dominators(succs);
+        let loops = natural_loops(self.entry, succs).into_iter().map(|(header, body)| {
+            let exits: BTreeSet<Pos> = body.iter().flat_map(|node| succs[node].iter())
+                .filter(|next| !body.contains(next))
+                .copied()
+                .collect();
+            let exit = ipdom.get(&header).filter(|exit| exits.contains(exit)).or_else(|| exits.iter().next())
+                .copied();
+            (header, (body, exit))
+        }).collect();
+
+        let mut structurer = Structurer {
+            code: &self.code,
+            ipdom,
+            loops,
+            emitted: BTreeSet::new(),
+            targets: BTreeSet::new()
+        };
+        let mut body = Vec::new();
+        structurer.region(self.entry, Region { header: None, exit: None, follow: None }, &mut body);
+        let body = simplify(body, &structurer.targets);
+
+        FunctionCode { entry: self.entry, frame_size: self.frame_size, body }
+    }
+}
+
+/// Strip `continue` from the end of a loop body, including the ends of a trailing `if`
+fn strip_continue(body: &mut Vec<Stmt>) {
+    match body.last_mut() {
+        Some(Stmt::Continue) => {
+            body.pop();
+        }
+        Some(Stmt::If(_, then, other)) => {
+            strip_continue(then);
+            strip_continue(other);
+        }
+        _ => {}
+    }
+}
+
+/// Tidy up structured code: drop unused labels, avoid empty `then` branches and turn loops that
+/// start with a conditional `break` into `while` loops
+fn simplify(stmts: Vec<Stmt>, targets: &BTreeSet<Pos>) -> Vec<Stmt> {
+    let mut out = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::Label(addr) if !targets.contains(&addr) => {}
+            Stmt::If(cond, then, other) => {
+                let then = simplify(then, targets);
+                let other = simplify(other, targets);
+                if then.is_empty() && other.is_empty() && !cond.has_input() {
+                    continue;
+                }
+                if then.is_empty() {
+                    out.push(Stmt::If(cond.not(), other, then));
+                } else {
+                    out.push(Stmt::If(cond, then, other));
+                }
+            }
+            Stmt::Loop(body) => {
+                let mut body = simplify(body, targets);
+                strip_continue(&mut body);
+                let mut body = simplify(body, targets);
+                match body.first() {
+                    Some(Stmt::If(_, then, other)) if then == &[Stmt::Break] && other.is_empty() => {
+                        let cond = match body.remove(0) {
+                            Stmt::If(cond, _, _) => cond,
+                            _ => unreachable!()
+                        };
+                        out.push(Stmt::While(cond.not(), body));
+                    }
+                    _ => out.push(Stmt::Loop(body))
+                }
+            }
+            stmt => out.push(stmt)
+        }
+    }
+    out
+}
+
+/// Innermost enclosing loop and the block a region stops at
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    header: Option<Pos>,
+    exit: Option<Pos>,
+    follow: Option<Pos>
+}
+
+/// Rebuilds `if` and loops from the lowered blocks of a function
+struct Structurer<'a> {
+    code: &'a BTreeMap<Pos, (Vec<Stmt>, Exit)>,
+
+    /// Immediate post-dominator of each block
+    ipdom: BTreeMap<Pos, Pos>,
+
+    /// Body and chosen exit of the loop at each loop header
+    loops: BTreeMap<Pos, (BTreeSet<Pos>, Option<Pos>)>,
+
+    emitted: BTreeSet<Pos>,
+
+    /// Blocks jumped to with `goto`
+    targets: BTreeSet<Pos>
+}
+
+impl Structurer<'_> {
+    /// `break` or `continue` for a jump leaving the current loop body
+    fn escape(&self, addr: Pos, region: Region) -> Option<Stmt> {
+        if Some(addr) == region.header {
+            Some(Stmt::Continue)
+        } else if Some(addr) == region.exit {
+            Some(Stmt::Break)
+        } else {
+            None
+        }
+    }
+
+    /// Emit the code starting at `addr` until `region.follow` is reached
+    fn region(&mut self, mut addr: Pos, region: Region, out: &mut Vec<Stmt>) {
+        loop {
+            if Some(addr) == region.follow {
+                return;
+            }
+            if let Some(stmt) = self.escape(addr, region) {
+                out.push(stmt);
+                return;
+            }
+            if self.emitted.contains(&addr) || !self.code.contains_key(&addr) {
+                self.targets.insert(addr);
+                out.push(Stmt::Goto(addr));
+                return;
+            }
+
+            if let Some(exit) = self.loops.get(&addr).map(|(_, exit)| *exit) {
+                let inner = Region { header: Some(addr), exit, follow: None };
+                let mut body = Vec::new();
+                if let Some(next) = self.block(addr, inner, &mut body) {
+                    self.region(next, inner, &mut body);
+                }
+                out.push(Stmt::Loop(body));
+                match exit {
+                    Some(exit) => {
+                        addr = exit;
+                        continue;
+                    }
+                    None => return
+                }
+            }
+
+            match self.block(addr, region, out) {
+                Some(next) => addr = next,
+                None => return
+            }
+        }
+    }
+
+    /// Emit one block, returning the block execution continues at
+    fn block(&mut self, addr: Pos, region: Region, out: &mut Vec<Stmt>) -> Option<Pos> {
+        let code = self.code;
+        let (stmts, exit) = &code[&addr];
+        self.emitted.insert(addr);
+        out.push(Stmt::Label(addr));
+        out.extend(stmts.iter().cloned());
+
+        let (cond, taken, not_taken) = match exit {
+            Exit::Goto(next) => return Some(*next),
+            Exit::End => return None,
+            Exit::Branch(cond, taken, not_taken) => (cond.clone(), *taken, *not_taken)
+        };
+
+        // Prefer `if .. { break }` over `if .. { continue }`, the latter is usually implicit
+        let escapes = [(not_taken, cond.clone().not(), taken), (taken, cond.clone(), not_taken)];
+        for stmt in &[Stmt::Break, Stmt::Continue] {
+            for (target, cond, next) in escapes.iter().cloned() {
+                if self.escape(target, region).as_ref() == Some(stmt) {
+                    out.push(Stmt::If(cond, vec![stmt.clone()], Vec::new()));
+                    return Some(next);
+                }
+            }
+        }
+
+        // Both arms run until the post-dominator, unless it lies outside the current loop
+        let merge = self.ipdom.get(&addr).copied().filter(|merge| {
+            region.header.is_none_or(|header| self.loops[&header].0.contains(merge))
+        });
+        let inner = Region { follow: merge, ..region };
+        let mut then = Vec::new();
+        self.region(taken, inner, &mut then);
+        let mut other = Vec::new();
+        self.region(not_taken, inner, &mut other);
+        out.push(Stmt::If(cond, then, other));
+        merge
+    }
+}
+
+impl CallGraph {
+    /// Decompile every recovered function to structured pseudo-code
+    pub fn decompile(&self) -> Decompiled {
+        let lowered: Vec<Lowered> = self.functions.values().map(|function| self.lower(function)).collect();
+
+        // Which globals are read across calls and returns depends on every function, so iterate
+        // until the summaries stop growing
+        let mut summaries = Summaries::default();
+        for function in &lowered {
+            for (stmts, exit) in function.code.values() {
+                let mut vars = Vec::new();
+                for stmt in stmts {
+                    vars.extend(stmt.writes());
+                    stmt.reads(&mut vars);
+                }
+                if let Exit::Branch(cond, _, _) = exit {
+                    cond.reads(&mut vars);
+                }
+                summaries.globals.extend(vars.into_iter().filter(|var| matches!(var, Var::Global(_))));
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for function in &lowered {
+                let live_in = function.liveness(&summaries);
+                let reads: BTreeSet<Var> = live_in[&function.entry].iter()
+                    .filter(|var| matches!(var, Var::Global(_)))
+                    .copied()
+                    .collect();
+                if summaries.entry_reads.get(&function.entry) != Some(&reads) {
+                    summaries.entry_reads.insert(function.entry, reads);
+                    changed = true;
+                }
+
+                for call in &self.functions[&function.entry].calls {
+                    let returned = summaries.return_live.entry(call.target).or_default();
+                    for &var in live_in.get(&call.return_addr).into_iter().flatten() {
+                        if matches!(var, Var::Global(_)) {
+                            changed |= returned.insert(var);
+                        }
+                    }
+                }
+            }
+        }
+
+        let functions = lowered.into_iter().map(|function| function.finish(&summaries)).collect();
+        Decompiled { functions }
+    }
+
+    /// Lower every block of a function to statements
+    fn lower(&self, function: &Function) -> Lowered {
+        let lowering = Lowering { graph: self, function };
+        let deltas = lowering.deltas();
+        let succs = function.blocks.iter().map(|&start| (start, lowering.successors(start))).collect();
+        let code: BTreeMap<Pos, (Vec<Stmt>, Exit)> = function.blocks.iter()
+            .map(|&start| (start, lowering.block(start, deltas.get(&start).copied().flatten())))
+            .collect();
+
+        let mut frame = BTreeSet::new();
+        for (stmts, exit) in code.values() {
+            let mut vars = Vec::new();
+            for stmt in stmts {
+                vars.extend(stmt.writes());
+                stmt.reads(&mut vars);
+            }
+            if let Exit::Branch(cond, _, _) = exit {
+                cond.reads(&mut vars);
+            }
+            frame.extend(vars.into_iter().filter(|var| !matches!(var, Var::Global(_))));
+        }
+        let untracked = frame.iter().any(|var| matches!(var, Var::Rel(_)));
+
+        Lowered { entry: function.entry, frame_size: function.frame_size, code, succs, frame, untracked }
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Recover the program's functions and decompile them to structured pseudo-code
+    pub fn decompile(&self) -> Decompiled {
+        self.recover_functions().decompile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+
+    #[test]
+    fn test_while_loop() {
+        let program = Program::from_input(SUMS);
+        assert_eq!(program.decompile().to_string(), "\
+fn F0() {
+    loop {
+        m100 = input();
+        m101 = m101 + m100;
+        output(m101);
+        if m100 == 0 {
+            break;
+        }
+    }
+    halt;
+}
+");
+    }
+
+    #[test]
+    fn test_expressions() {
+        let x = Expr::Var(Var::Global(10));
+        let sum = Expr::binary(BinOp::Add, Expr::Const(-3), x.clone());
+        assert_eq!(sum.to_string(), "m10 - 3");
+        let product = Expr::binary(BinOp::Mul, Expr::Const(2), sum);
+        assert_eq!(product.to_string(), "(m10 - 3) * 2");
+        assert_eq!(Expr::binary(BinOp::Mul, x.clone(), Expr::Const(-1)).to_string(), "-m10");
+
+        let less = Expr::binary(BinOp::Lt, x, Expr::Var(Var::Arg(1)));
+        assert_eq!(less.clone().truth(), less);
+        assert_eq!(less.not().to_string(), "m10 >= arg1");
+        assert_eq!(Expr::binary(BinOp::Eq, Expr::Const(4), Expr::Const(4)), Expr::Const(1));
+    }
+
+    #[test]
+    fn test_day09_recursive_function() {
+        let program = Program::from_input(include_str!("../../day09/input"));
+        let decompiled = program.decompile();
+        let function = decompiled.functions.iter().find(|function| function.entry == 922).unwrap();
+        assert_eq!(function.to_string(), "\
+fn F922() {  // frame 3
+    if arg1 >= 3 {
+        local1 = arg1 - 1;
+        F922();
+        arg2 = local1;
+        local1 = arg1 - 3;
+        F922();
+        arg1 = local1 + arg2;
+    }
+    return;
+}
+");
+    }
+
+    #[test]
+    fn test_relative_offset_overflow() {
+        // arb #MAX, out [rb+1], halt: the slot past the frame overflows
+        let decompiled = Program::from_input("109,9223372036854775807,204,1,99").decompile();
+        assert_eq!(decompiled.to_string(), "\
+fn F0() {  // frame 9223372036854775807
+    output(rb[1]);
+    halt;
+}
+");
+
+        // arb #MIN, out [rb-1], halt
+        let decompiled = Program::from_input("109,-9223372036854775808,204,-1,99").decompile();
+        assert_eq!(decompiled.to_string(), "\
+fn F0() {
+    output(rb[-1]);
+    halt;
+}
+");
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Most data words shown on a single `.data` line
+const DATA_PER_LINE: usize = 8;
+
+/// Column the address comment of each line is aligned to
+const COMMENT_COLUMN: usize = 36;
+
+/// How the disassembler decides which words are code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Decode every address from 0 in order, falling back to data for words that do not decode
+    LinearSweep,
+
+    /// Follow control flow from the entry point. Only reachable instructions are code, everything
+    /// else is data. Jumps through memory cannot be followed.
+    RecursiveDescent
+}
+
+/// One line of a listing: an instruction or a run of data words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// Address of the first word
+    pub addr: Pos,
+
+    /// Raw words covered by the line
+    pub words: Vec<Imm>,
+
+    /// Decoded instruction, or `None` for data
+    pub op: Option<Opcode>
+}
+
+/// Annotated listing of a program's memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    /// Lines in address order
+    pub lines: Vec<Line>,
+
+    /// Generated labels keyed by address. Every target of an immediate jump that starts a line
+    /// gets a label.
+    pub labels: BTreeMap<Pos, String>
+}
+
+/// Returns the immediate target of a jump instruction
+pub(crate) fn jump_target(op: &Opcode) -> Option<Pos> {
+    match *op {
+        Opcode::JumpNonZero(_, Mode::Immediate(target)) | Opcode::JumpZero(_, Mode::Immediate(target))
+            if target >= 0 => Some(target as usize),
+        _ => None
+    }
+}
+
+/// Returns true if execution never continues to the next instruction
+pub(crate) fn ends_flow(op: &Opcode) -> bool {
+    match *op {
+        Opcode::Halt => true,
+        Opcode::JumpNonZero(Mode::Immediate(cond), _) => cond != 0,
+        Opcode::JumpZero(Mode::Immediate(cond), _) => cond == 0,
+        _ => false
+    }
+}
+
+impl Disassembly {
+    /// Decoded instructions keyed by address
+    pub fn code(&self) -> impl Iterator<Item = (Pos, Opcode)> + '_ {
+        self.lines.iter().filter_map(|line| line.op.map(|op| (line.addr, op)))
+    }
+
+    /// Assembly text of a line without its label or address comment
+    pub fn text(&self, line: &Line) -> String {
+        let op = match line.op {
+            Some(op) => op,
+            None => {
+                let words: Vec<String> = line.words.iter().map(|word| word.to_string()).collect();
+                return format!(".data {}", words.join(", "));
+            }
+        };
+
+        // Show jump targets by label
+        match (op, jump_target(&op).and_then(|target| self.labels.get(&target))) {
+            (Opcode::JumpNonZero(cond, _), Some(label)) | (Opcode::JumpZero(cond, _), Some(label)) => {
+                format!("{} {}, {}", op.mnemonic(), cond, label)
+            }
+            _ => op.to_string()
+        }
+    }
+}
+
+/// Listing in assembler syntax. Labels are on their own line and each line ends in a comment with
+/// its address and raw words, so the listing can be assembled again.
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.labels.get(&line.addr) {
+                writeln!(f, "{}:", label)?;
+            }
+            let words: Vec<String> = line.words.iter().map(|word| word.to_string()).collect();
+            let text = format!("    {}", self.text(line));
+            writeln!(f, "{:width$} ; {:04}: {}", text, line.addr, words.join(","), width = COMMENT_COLUMN)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Address just past the last non-zero word of memory
+    pub(crate) fn image_end(&self) -> Pos {
+        self.memory.regions().iter().rev()
+            .find_map(|(start, values)| values.iter().rposition(|&value| value != 0).map(|i| start + i + 1))
+            .unwrap_or(0)
+    }
+
+    /// Find every instruction reachable from `entries` without executing anything. Instructions
+    /// overlapping one found earlier are not decoded, so the result never overlaps.
+    pub(crate) fn reachable_code(&self, entries: &[Pos]) -> BTreeMap<Pos, Opcode> {
+        let mut code: BTreeMap<Pos, Opcode> = BTreeMap::new();
+        let mut covered = BTreeSet::new();
+        let mut worklist: Vec<Pos> = entries.to_vec();
+
+        while let Some(mut addr) = worklist.pop() {
+            while !covered.contains(&addr) {
+                let op = match self.decode(addr) {
+                    Ok(op) => op,
+                    Err(_) => break
+                };
+                if (addr..addr + op.len()).any(|a| covered.contains(&a)) {
+                    break;
+                }
+
+                code.insert(addr, op);
+                covered.extend(addr..addr + op.len());
+                if let Some(target) = jump_target(&op) {
+                    worklist.push(target);
+                }
+                if ends_flow(&op) {
+                    break;
+                }
+                addr += op.len();
+            }
+        }
+
+        code
+    }
+
+    /// Disassemble the program's memory into an annotated listing
+    pub fn disassemble(&self, strategy: Strategy) -> Disassembly {
+        let end = self.image_end();
+        let code = match strategy {
+            Strategy::RecursiveDescent => self.reachable_code(&[0]),
+            Strategy::LinearSweep => {
+                let mut code = BTreeMap::new();
+                let mut addr = 0;
+                while addr < end {
+                    match self.decode(addr) {
+                        Ok(op) => {
+                            code.insert(addr, op);
+                            addr += op.len();
+                        }
+                        Err(_) => addr += 1
+                    }
+                }
+                code
+            }
+        };
+
+        let labels: BTreeMap<Pos, String> = code.values().filter_map(jump_target)
+            .filter(|target| code.contains_key(target))
+            .map(|target| (target, format!("L{}", target)))
+            .collect();
+
+        // Code may run past the last non-zero word when its final parameters are zero
+        let end = code.iter().next_back().map_or(end, |(&addr, op)| end.max(addr + op.len()));
+
+        let mut lines = Vec::new();
+        let mut addr = 0;
+        while addr < end {
+            if let Some(&op) = code.get(&addr) {
+                let words = (addr..addr + op.len()).map(|a| self.read(a)).collect();
+                lines.push(Line { addr, words, op: Some(op) });
+                addr += op.len();
+                continue;
+            }
+
+            // Group data words until the next instruction or label
+            let start = addr;
+            let mut words = Vec::new();
+            while addr < end && words.len() < DATA_PER_LINE && !code.contains_key(&addr)
+                    && (addr == start || !labels.contains_key(&addr)) {
+                words.push(self.read(addr));
+                addr += 1;
+            }
+            lines.push(Line { addr: start, words, op: None });
+        }
+
+        Disassembly { lines, labels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::sums_with_data;
+
+    #[test]
+    fn test_recursive_descent() {
+        let program = Program::from_input(&sums_with_data());
+        let listing = program.disassemble(Strategy::RecursiveDescent).to_string();
+        assert_eq!(listing, "\
+L0:
+    in -> [100]                      ; 0000: 3,100
+    add [101], [100] -> [101]        ; 0002: 1,101,100,101
+    out [101]                        ; 0006: 4,101
+    jnz [100], L0                    ; 0008: 1005,100,0
+    halt                             ; 0011: 99
+    .data 7, -3                      ; 0012: 7,-3
+");
+    }
+
+    #[test]
+    fn test_linear_sweep_decodes_data() {
+        // Linear sweep also decodes the words the jump skips over
+        let program = Program::from_input("1105,1,5,4,4,99,109,-3");
+        let linear = program.disassemble(Strategy::LinearSweep);
+        let text: Vec<String> = linear.lines.iter().map(|line| linear.text(line)).collect();
+        assert_eq!(text, vec!["jnz #1, L5", "out [4]", "halt", "arb #-3"]);
+
+        let recursive = program.disassemble(Strategy::RecursiveDescent);
+        let text: Vec<String> = recursive.lines.iter().map(|line| recursive.text(line)).collect();
+        assert_eq!(text, vec!["jnz #1, L5", ".data 4, 4", "halt", ".data 109, -3"]);
+        assert_eq!(recursive.code().count(), 2);
+    }
+
+    #[test]
+    fn test_trailing_zero_parameters() {
+        let program = Program::from_input("1105,1,0");
+        let listing = program.disassemble(Strategy::RecursiveDescent);
+        assert_eq!(listing.lines.len(), 1);
+        assert_eq!(listing.text(&listing.lines[0]), "jnz #1, L0");
+    }
+
+    #[test]
+    fn test_invalid_words_are_data() {
+        let program = Program::from_input("42,104,1,302,99");
+        let listing = program.disassemble(Strategy::LinearSweep);
+        let text: Vec<String> = listing.lines.iter().map(|line| listing.text(line)).collect();
+        assert_eq!(text, vec![".data 42", "out #1", ".data 302", "halt"]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::limits::Limit;
+use crate::opcode::{Imm, Pos};
+
+/// Reason the emulator stopped without faulting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// Program executed a `Halt` instruction
+    Halted,
+
+    /// Program executed an `In` instruction with an empty input buffer. The instruction has not
+    /// been executed, so `run` can be called again once more input is available.
+    NeedsInput,
+
+    /// [`Program::run_with_limits`](crate::Program::run_with_limits) stopped between two
+    /// instructions because of the given limit. Execution can be resumed with any of the `run`
+    /// methods.
+    BudgetExhausted(Limit)
+}
+
+/// Precise reason an instruction could not be lifted or executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The low two digits of the instruction are not a known opcode
+    UnknownOpcode(Imm),
+
+    /// A parameter mode digit is not 0, 1 or 2. `param` is the 1-based parameter index.
+    UnknownMode { param: u8, mode: Imm },
+
+    /// An instruction that writes memory was given an immediate destination
+    ImmediateDestination,
+
+    /// A positional or relative parameter resolved to a negative address
+    NegativeAddress(Imm),
+
+    /// A jump targeted a negative address
+    InvalidJumpTarget(Imm),
+
+    /// An `Add`, `Mul` or relative base adjustment overflowed, or an instruction ran past the end
+    /// of the address space
+    Overflow
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownOpcode(op) => write!(f, "unknown opcode {}", op),
+            ErrorKind::UnknownMode { param, mode } =>
+                write!(f, "unknown mode {} for parameter {}", mode, param),
+            ErrorKind::ImmediateDestination => write!(f, "immediate mode destination"),
+            ErrorKind::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            ErrorKind::InvalidJumpTarget(target) => write!(f, "invalid jump target {}", target),
+            ErrorKind::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+/// Fault raised while lifting or executing an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmError {
+    /// Address of the faulting instruction
+    pub ip: Pos,
+
+    /// Raw instruction word at `ip`
+    pub opcode: Imm,
+
+    /// What went wrong
+    pub kind: ErrorKind
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} (opcode {})", self.ip, self.kind, self.opcode)
+    }
+}
+
+impl std::error::Error for VmError {}
+
+/// Error parsing the comma separated text of a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 0-based index of the offending value
+    pub index: usize,
+
+    /// Text that is not an integer
+    pub text: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value {:?} at position {}", self.text, self.index)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,15 @@
+/// Result of executing a single instruction with [`Program::step`](crate::Program::step)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Instruction executed without any I/O
+    Executed,
+
+    /// `In` instruction found the input buffer empty. The IP still points at the `In`.
+    NeedsInput,
+
+    /// `Out` instruction wrote this value to the output buffer
+    Output(isize),
+
+    /// `Halt` instruction reached. The IP still points at the `Halt`.
+    Halted
+}
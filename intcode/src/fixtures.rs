@@ -0,0 +1,10 @@
+//! Programs shared by the tests of several modules
+
+/// Outputs the running sum of its inputs until it reads a 0
+/// in -> [100], add [101], [100] -> [101], out [101], jnz [100], #0, halt
+pub(crate) const SUMS: &str = "3,100,1,101,100,101,4,101,1005,100,0,99";
+
+/// [`SUMS`] followed by two data words, for listings that have to tell code from data
+pub(crate) fn sums_with_data() -> String {
+    format!("{},7,-3", SUMS)
+}
@@ -0,0 +1,486 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::asm::to_intcode;
+use crate::coverage::Coverage;
+use crate::error::{ExitReason, VmError};
+use crate::limits::{Limit, Limits};
+use crate::opcode::{Imm, Pos};
+use crate::program::Program;
+
+/// Values that often reach unusual paths: boundaries, small numbers and their negations
+const INTERESTING: &[Imm] = &[0, 1, -1, 2, 7, 8, 9, 10, 16, 99, 100, -100, 255, 1000, Imm::MAX, Imm::MIN];
+
+/// Point of coverage: a conditional jump going one way a bucketed number of times, or an
+/// address being executed
+type Feature = (Pos, u8, u8);
+
+/// Why a run was recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    /// The program faulted
+    Crash(VmError),
+
+    /// The program did not finish within the instruction budget
+    Budget,
+
+    /// The program produced an output sequence not seen before
+    NovelOutput
+}
+
+impl FindingKind {
+    /// Directory of the corpus the finding is saved in
+    fn dir(&self) -> &'static str {
+        match self {
+            FindingKind::Crash(_) => "crashes",
+            FindingKind::Budget => "hangs",
+            FindingKind::NovelOutput => "outputs"
+        }
+    }
+}
+
+/// Input that made a run worth recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: FindingKind,
+
+    /// Input values, minimised for crashes
+    pub input: Vec<Imm>,
+
+    /// Output of the run
+    pub output: Vec<Imm>
+}
+
+/// Result of running the program on one input
+struct Outcome {
+    result: Result<ExitReason, VmError>,
+    output: Vec<Imm>,
+    features: BTreeSet<Feature>
+}
+
+/// Xorshift generator, so that runs can be repeated from a seed
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number below `bound`, which must not be zero
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Stable name for an input in the corpus directories
+fn name(input: &[Imm]) -> String {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &value in input {
+        for byte in (value as i64).to_le_bytes().iter() {
+            hash = (hash ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Parse the first line of a corpus file
+fn parse(text: &str) -> Option<Vec<Imm>> {
+    let line = text.lines().next().unwrap_or("");
+    line.split(',').filter(|value| !value.trim().is_empty()).map(|value| value.trim().parse().ok()).collect()
+}
+
+/// Coverage-guided fuzzer for the input of a program
+///
+/// Inputs that reach new coverage are kept in the corpus and mutated further. With a corpus
+/// directory, the corpus is loaded from and saved to `queue/` and findings are saved to
+/// `crashes/`, `hangs/` and `outputs/`, one input per file as comma separated values.
+#[derive(Debug, Clone)]
+pub struct Fuzzer {
+    /// Program every run starts from
+    program: Program,
+
+    /// Inputs that reached new coverage
+    corpus: Vec<Vec<Imm>>,
+
+    /// Coverage reached so far
+    features: BTreeSet<Feature>,
+
+    /// Output sequences seen so far
+    outputs: BTreeSet<Vec<Imm>>,
+
+    /// Runs worth looking at
+    findings: Vec<Finding>,
+
+    /// Inputs loaded from the corpus directory whose coverage and output are not collected yet
+    pending: Vec<Vec<Imm>>,
+
+    /// Where the corpus and findings are saved
+    dir: Option<PathBuf>,
+
+    rng: Rng,
+
+    /// Instructions a single run may execute
+    pub budget: u64,
+
+    /// Most input values a mutated input may have
+    pub max_len: usize
+}
+
+impl Fuzzer {
+    /// Fuzz the input of `program`, starting with an empty input
+    pub fn new(program: Program) -> Fuzzer {
+        Fuzzer {
+            program,
+            corpus: Vec::new(),
+            features: BTreeSet::new(),
+            outputs: BTreeSet::new(),
+            findings: Vec::new(),
+            pending: Vec::new(),
+            dir: None,
+            rng: Rng(0x2545_f491_4f6c_dd1d),
+            budget: 1_000_000,
+            max_len: 64
+        }
+    }
+
+    /// Seed the random number generator
+    pub fn seed(mut self, seed: u64) -> Fuzzer {
+        // Xorshift never leaves zero
+        self.rng = Rng(seed.max(1));
+        self
+    }
+
+    /// Save the corpus and findings below `dir`, loading any corpus saved there before. The loaded
+    /// inputs run with the budget of the first [`Fuzzer::run`] or [`Fuzzer::add_input`].
+    pub fn corpus_dir<P: AsRef<Path>>(mut self, dir: P) -> io::Result<Fuzzer> {
+        let dir = dir.as_ref().to_path_buf();
+        for sub in &["queue", "crashes", "hangs", "outputs"] {
+            fs::create_dir_all(dir.join(sub))?;
+        }
+
+        let mut saved = Vec::new();
+        for entry in fs::read_dir(dir.join("queue"))? {
+            if let Some(input) = parse(&fs::read_to_string(entry?.path())?) {
+                saved.push(input);
+            }
+        }
+        saved.sort();
+        self.dir = Some(dir);
+        self.corpus.extend(saved.iter().cloned());
+        self.pending = saved;
+        Ok(self)
+    }
+
+    /// Collect the coverage and output of the inputs loaded from the corpus directory. The run
+    /// that saved them already recorded what they found.
+    fn load_pending(&mut self) {
+        for input in std::mem::take(&mut self.pending) {
+            let outcome = self.execute(&input);
+            self.features.extend(outcome.features);
+            self.outputs.insert(outcome.output);
+        }
+    }
+
+    /// Run `input` and keep it in the corpus if it reaches new coverage
+    pub fn add_input(&mut self, input: Vec<Imm>) -> io::Result<()> {
+        self.load_pending();
+        let outcome = self.execute(&input);
+        self.triage(input, outcome)
+    }
+
+    /// Inputs that reached new coverage, in the order they were found
+    pub fn corpus(&self) -> &[Vec<Imm>] {
+        &self.corpus
+    }
+
+    /// Runs recorded so far
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Number of coverage points reached so far
+    pub fn coverage(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Run `iterations` mutated inputs
+    pub fn run(&mut self, iterations: u64) -> io::Result<()> {
+        self.load_pending();
+        if self.corpus.is_empty() {
+            self.add_input(Vec::new())?;
+        }
+        for _ in 0..iterations {
+            let input = self.mutate();
+            let outcome = self.execute(&input);
+            self.triage(input, outcome)?;
+        }
+        Ok(())
+    }
+
+    /// Run the program on `input` and collect the coverage it reached
+    fn execute(&self, input: &[Imm]) -> Outcome {
+        let mut program = self.program.clone().with_coverage();
+        program.input.extend(input);
+        let result = program.run_with_limits(&Limits::new().instructions(self.budget));
+
+        let coverage = program.coverage.take().unwrap_or_default();
+        Outcome { result, output: program.output, features: features(&coverage) }
+    }
+
+    /// Keep inputs with new coverage and record crashes, hangs and novel outputs
+    fn triage(&mut self, input: Vec<Imm>, outcome: Outcome) -> io::Result<()> {
+        // The first input is always kept so that there is something to mutate. Inputs that crash
+        // or hang are kept as well, the coverage on the way there is still worth mutating.
+        let new_coverage = !outcome.features.is_subset(&self.features) || self.corpus.is_empty();
+        self.features.extend(outcome.features);
+        if new_coverage {
+            self.save("queue", &input)?;
+            self.corpus.push(input.clone());
+        }
+
+        let kind = match outcome.result {
+            Err(err) => Some(FindingKind::Crash(err)),
+            // Hangs are only worth another look when they spin somewhere new
+            Ok(ExitReason::BudgetExhausted(Limit::Instructions))
+                if new_coverage || !self.findings.iter().any(|finding| finding.kind == FindingKind::Budget) =>
+            {
+                Some(FindingKind::Budget)
+            }
+            Ok(ExitReason::BudgetExhausted(_)) => None,
+            Ok(_) if !self.outputs.contains(&outcome.output) => Some(FindingKind::NovelOutput),
+            Ok(_) => None
+        };
+        self.outputs.insert(outcome.output.clone());
+
+        match kind {
+            Some(FindingKind::Crash(err)) => {
+                // Only report each crash site once
+                if self.findings.iter().any(|finding| matches!(finding.kind, FindingKind::Crash(old) if old.ip == err.ip)) {
+                    return Ok(());
+                }
+                let input = self.minimize(&input, |result| matches!(result, Err(new) if new.ip == err.ip));
+                let output = self.execute(&input).output;
+                self.record(Finding { kind: FindingKind::Crash(err), input, output })?;
+            }
+            Some(kind) => self.record(Finding { kind, input, output: outcome.output })?,
+            None => ()
+        }
+        Ok(())
+    }
+
+    /// Add a finding and save it to the corpus directory
+    fn record(&mut self, finding: Finding) -> io::Result<()> {
+        self.save(finding.kind.dir(), &finding.input)?;
+        self.findings.push(finding);
+        Ok(())
+    }
+
+    fn save(&self, sub: &str, input: &[Imm]) -> io::Result<()> {
+        match &self.dir {
+            Some(dir) => fs::write(dir.join(sub).join(name(input)), format!("{}\n", to_intcode(input))),
+            None => Ok(())
+        }
+    }
+
+    /// Shrink `input` while `keep` still holds for the result of running it: first drop runs of
+    /// values, then move each value towards zero.
+    pub fn minimize(&self, input: &[Imm], keep: impl Fn(&Result<ExitReason, VmError>) -> bool) -> Vec<Imm> {
+        let holds = |input: &[Imm]| keep(&self.execute(input).result);
+        let mut input = input.to_vec();
+
+        let mut chunk = input.len().max(1);
+        while chunk > 0 {
+            let mut start = 0;
+            while start < input.len() {
+                let mut smaller = input.clone();
+                smaller.drain(start..(start + chunk).min(input.len()));
+                if holds(&smaller) {
+                    input = smaller;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        for i in 0..input.len() {
+            while input[i] != 0 {
+                let mut smaller = input.clone();
+                if smaller[i].unsigned_abs() > 1 {
+                    smaller[i] = 0;
+                    if holds(&smaller) {
+                        input = smaller;
+                        break;
+                    }
+                    smaller[i] = input[i] / 2;
+                } else {
+                    smaller[i] = 0;
+                }
+                if !holds(&smaller) {
+                    break;
+                }
+                input = smaller;
+            }
+        }
+        input
+    }
+
+    /// Pick an input from the corpus and change it
+    fn mutate(&mut self) -> Vec<Imm> {
+        let mut input = self.corpus[self.rng.below(self.corpus.len())].clone();
+        let mutations = 1 + self.rng.below(4);
+        for _ in 0..mutations {
+            let index = if input.is_empty() { 0 } else { self.rng.below(input.len()) };
+            let interesting = INTERESTING[self.rng.below(INTERESTING.len())];
+            match self.rng.below(7) {
+                0 if !input.is_empty() => input[index] = interesting,
+                1 if !input.is_empty() => {
+                    let delta = 1 + self.rng.below(16) as Imm;
+                    input[index] = if self.rng.next() & 1 == 0 {
+                        input[index].wrapping_add(delta)
+                    } else {
+                        input[index].wrapping_sub(delta)
+                    };
+                }
+                2 if !input.is_empty() => input[index] = self.rng.next() as Imm >> self.rng.below(64),
+                3 if !input.is_empty() => {
+                    input.remove(index);
+                }
+                4 if !input.is_empty() => {
+                    let value = input[index];
+                    input.insert(index, value);
+                }
+                5 => {
+                    // Splice in the tail of another corpus entry
+                    let other = &self.corpus[self.rng.below(self.corpus.len())];
+                    if !other.is_empty() {
+                        let from = self.rng.below(other.len());
+                        input.truncate(index);
+                        input.extend_from_slice(&other[from..]);
+                    }
+                }
+                _ => input.insert(index.min(input.len()), interesting)
+            }
+        }
+        input.truncate(self.max_len);
+        input
+    }
+}
+
+/// Bucket of a hit count, so that loops running a different number of times count as new
+/// coverage only when the difference is large
+fn bucket(count: u64) -> u8 {
+    (64 - count.leading_zeros()).min(8) as u8
+}
+
+/// Coverage points of one run
+fn features(coverage: &Coverage) -> BTreeSet<Feature> {
+    let mut features: BTreeSet<Feature> = coverage.hits.keys().map(|&addr| (addr, 2, 0)).collect();
+    for (&addr, branch) in &coverage.branches {
+        if branch.taken > 0 {
+            features.insert((addr, 1, bucket(branch.taken)));
+        }
+        if branch.not_taken > 0 {
+            features.insert((addr, 0, bucket(branch.not_taken)));
+        }
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    /// Reads values until a 0 and outputs how many were even. A negative value jumps into data
+    /// and 7 spins forever.
+    /// 0: in -> [100], jz [100], #27, lt [100], #0 -> [101], jnz [101], #30, eq [100], #7 -> [101],
+    ///    jnz [101], #31, jz #0, #0
+    /// 27: out [102], halt, .data 42, jz #0, #31
+    const TARGET: &str = "3,100,1006,100,27,1007,100,0,101,1005,101,30,1008,100,7,101,1005,101,31,1106,0,0,\
+                          0,0,0,0,0,4,102,99,42,1106,0,31";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("intcode-fuzz-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_minimize() {
+        let fuzzer = Fuzzer::new(Program::from_input(TARGET));
+        let crashes = |result: &Result<ExitReason, VmError>| result.is_err();
+        assert_eq!(fuzzer.minimize(&[5, 3, -30, 2, 0], crashes), vec![-1]);
+        assert_eq!(fuzzer.minimize(&[5, 3, 2], |result| *result == Ok(ExitReason::NeedsInput)), vec![]);
+    }
+
+    #[test]
+    fn test_fuzz_finds_crash_and_hang() {
+        let dir = temp_dir("crash");
+        let mut fuzzer = Fuzzer::new(Program::from_input(TARGET)).seed(1).corpus_dir(&dir).unwrap();
+        fuzzer.budget = 1000;
+        fuzzer.add_input(vec![4, 0]).unwrap();
+        fuzzer.run(2000).unwrap();
+
+        let crash = fuzzer.findings().iter().find(|finding| matches!(finding.kind, FindingKind::Crash(_))).unwrap();
+        assert_eq!(crash.input, vec![-1]);
+        assert_eq!(crash.kind, FindingKind::Crash(VmError { ip: 30, opcode: 42, kind: ErrorKind::UnknownOpcode(42) }));
+        assert!(fuzzer.findings().iter().any(|finding| finding.kind == FindingKind::Budget));
+        assert_eq!(fs::read_to_string(dir.join("crashes").join(name(&[-1]))).unwrap(), "-1\n");
+
+        // A new fuzzer picks up the saved corpus
+        let mut corpus = fuzzer.corpus().to_vec();
+        corpus.sort();
+        let reloaded = Fuzzer::new(Program::from_input(TARGET)).corpus_dir(&dir).unwrap();
+        assert_eq!(reloaded.corpus(), &corpus[..]);
+        assert_eq!(reloaded.findings(), &[]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keep_coverage_of_findings() {
+        let dir = temp_dir("triage");
+        let mut fuzzer = Fuzzer::new(Program::from_input(TARGET)).corpus_dir(&dir).unwrap();
+        fuzzer.budget = 1000;
+        fuzzer.add_input(vec![-1]).unwrap();
+        // Crashes at the same site after new coverage, then hangs
+        fuzzer.add_input(vec![4, -1]).unwrap();
+        fuzzer.add_input(vec![7]).unwrap();
+        // Halts with a new output
+        fuzzer.add_input(vec![0]).unwrap();
+        assert_eq!(fuzzer.corpus(), &[vec![-1], vec![4, -1], vec![7], vec![0]]);
+        let kinds: Vec<&str> = fuzzer.findings().iter().map(|finding| finding.kind.dir()).collect();
+        assert_eq!(kinds, vec!["crashes", "hangs", "outputs"]);
+
+        // Reloading the corpus records nothing, and the outputs it reaches are not novel
+        let mut reloaded = Fuzzer::new(Program::from_input(TARGET)).corpus_dir(&dir).unwrap();
+        reloaded.budget = 1000;
+        assert_eq!(reloaded.corpus().len(), 4);
+        reloaded.add_input(vec![0]).unwrap();
+        assert_eq!(reloaded.findings(), &[]);
+        // The saved inputs ran with the budget set after loading
+        assert_eq!(reloaded.coverage(), fuzzer.coverage());
+        assert_eq!(fs::read_dir(dir.join("outputs")).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_novel_outputs() {
+        let mut fuzzer = Fuzzer::new(Program::from_input(TARGET)).seed(7);
+        fuzzer.budget = 1000;
+        fuzzer.run(500).unwrap();
+        let outputs: BTreeSet<Vec<Imm>> = fuzzer.findings().iter()
+            .filter(|finding| finding.kind == FindingKind::NovelOutput)
+            .map(|finding| finding.output.clone())
+            .collect();
+        assert!(outputs.len() > 1);
+        assert_eq!(outputs.len(), fuzzer.findings().iter().filter(|f| f.kind == FindingKind::NovelOutput).count());
+    }
+}
@@ -0,0 +1,644 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::debugger::{Command, Debugger, Stop};
+use crate::error::{ErrorKind, ExitReason};
+use crate::limits::Limits;
+use crate::opcode::Pos;
+use crate::program::Program;
+
+/// Size in bytes of an Intcode word as seen by the debugger front-end. Word `n` of Intcode memory
+/// lives at byte address `n * WORD_SIZE`, stored little endian.
+pub const WORD_SIZE: usize = 8;
+
+/// Largest memory read or write accepted in one packet, in bytes
+const MAX_TRANSFER: usize = 0x4000;
+
+/// Instructions executed between checks for an interrupt request while continuing
+const SLICE: u64 = 100_000;
+
+/// Byte a client sends to interrupt a running program
+const INTERRUPT: u8 = 0x03;
+
+/// Signal numbers reported in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGFPE: u8 = 8;
+const SIGSEGV: u8 = 11;
+const SIGTTIN: u8 = 21;
+
+/// Target description advertised to the front-end: two 64-bit registers, the IP and the relative
+/// base
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.intcode.core">
+    <reg name="ip" bitsize="64" type="code_ptr" regnum="0"/>
+    <reg name="rb" bitsize="64" type="int64" regnum="1"/>
+  </feature>
+</target>"#;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+/// Parse an `addr,len` pair of hex numbers
+fn parse_range(text: &str) -> Option<(usize, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+/// Frame `data` as a packet, escaping the characters that are special to the protocol
+fn frame(data: &str) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 4);
+    for &byte in data.as_bytes() {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            body.push(b'}');
+            body.push(byte ^ 0x20);
+        } else {
+            body.push(byte);
+        }
+    }
+
+    let checksum = body.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    let mut packet = Vec::with_capacity(body.len() + 4);
+    packet.push(b'$');
+    packet.extend(body);
+    packet.extend(format!("#{:02x}", checksum).into_bytes());
+    packet
+}
+
+/// Stream a client is served over. A continued program polls it for interrupt requests, so it
+/// must be able to switch to non-blocking reads.
+pub trait Connection: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for std::os::unix::net::UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// Returns true if the client sent an interrupt request or went away, without waiting for it
+fn poll_interrupt<S: Connection>(stream: &mut S) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+
+    let mut byte = [0; 1];
+    let interrupted = loop {
+        match stream.read(&mut byte) {
+            Ok(0) => break true,
+            Ok(_) if byte[0] == INTERRUPT => break true,
+
+            // Nothing but acks is sent while the program runs
+            Ok(_) => continue,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break false,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break true
+        }
+    };
+    let restored = stream.set_nonblocking(false).is_ok();
+    interrupted || !restored
+}
+
+/// GDB remote serial protocol server for a [`Program`].
+///
+/// Intcode memory is exposed as 64-bit little endian words, see [`WORD_SIZE`]. Register 0 is the
+/// IP as a byte address, like every address in packets, so it must be word aligned when written.
+/// Register 1 is the relative base in words. Software and hardware breakpoints (`Z0`/`Z1`) must
+/// be word aligned. Input is queued with the `monitor` command, which accepts
+/// every command of the interactive [`Debugger`], for example `monitor input 1 2 3`.
+///
+/// Stop replies report `SIGTRAP` after a step or breakpoint, `SIGINT` when a continued program
+/// is interrupted with Ctrl-C, `SIGTTIN` when the program waits for input, `SIGILL`, `SIGSEGV` or `SIGFPE` for faults, and an exit status of 0 once the program
+/// halts.
+#[derive(Debug, Clone)]
+pub struct GdbStub {
+    /// Debugger holding the program and its breakpoints
+    pub debugger: Debugger,
+
+    /// Client requested `QStartNoAckMode`
+    no_ack: bool,
+
+    /// Client is still attached
+    attached: bool
+}
+
+impl GdbStub {
+    pub fn new(program: Program) -> GdbStub {
+        GdbStub {
+            debugger: Debugger::new(program),
+            no_ack: false,
+            attached: true
+        }
+    }
+
+    /// Serve a single client until it detaches, kills the program or disconnects
+    pub fn serve<S: Connection>(&mut self, mut stream: S) -> io::Result<()> {
+        self.no_ack = false;
+        self.attached = true;
+
+        while self.attached {
+            let packet = match self.read_packet(&mut stream)? {
+                Some(packet) => packet,
+                None => return Ok(())
+            };
+
+            let replies = self.handle_with(&packet, &mut || poll_interrupt(&mut stream));
+            for reply in replies {
+                stream.write_all(&frame(&reply))?;
+            }
+            stream.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the next packet, acknowledging it unless in no-ack mode. Returns `None` once the
+    /// client disconnects.
+    fn read_packet<S: Read + Write>(&mut self, stream: &mut S) -> io::Result<Option<String>> {
+        let mut byte = [0; 1];
+        loop {
+            // Skip acks and interrupt requests until the start of a packet
+            loop {
+                if stream.read(&mut byte)? == 0 {
+                    return Ok(None);
+                }
+                if byte[0] == b'$' {
+                    break;
+                }
+            }
+
+            let mut body = Vec::new();
+            loop {
+                if stream.read(&mut byte)? == 0 {
+                    return Ok(None);
+                }
+                match byte[0] {
+                    b'#' => break,
+                    b => body.push(b)
+                }
+            }
+
+            let mut checksum = [0; 2];
+            stream.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum).ok().and_then(|c| u8::from_str_radix(c, 16).ok());
+            let actual = body.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+
+            if self.no_ack {
+                return Ok(Some(String::from_utf8_lossy(&body).into_owned()));
+            }
+            if expected == Some(actual) {
+                stream.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&body).into_owned()));
+            }
+            stream.write_all(b"-")?;
+        }
+    }
+
+    /// Handle one packet, returning the packets to send back. A continued program can not be
+    /// interrupted, see [`GdbStub::serve`].
+    pub fn handle(&mut self, packet: &str) -> Vec<String> {
+        self.handle_with(packet, &mut || false)
+    }
+
+    /// Handle one packet, checking `interrupted` every few instructions while continuing
+    fn handle_with(&mut self, packet: &str, interrupted: &mut dyn FnMut() -> bool) -> Vec<String> {
+        let command = packet.get(..1).unwrap_or("");
+        let args = packet.get(1..).unwrap_or("");
+        let reply = match command {
+            "?" => self.stop_reply(Stop::Stepped),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(args),
+            "p" => self.read_register(args),
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "s" | "c" => {
+                if let Some(addr) = parse_hex(args) {
+                    self.debugger.program.ip = addr / WORD_SIZE;
+                }
+                if command == "s" {
+                    let stop = self.debugger.run(Some(1));
+                    self.stop_reply(stop)
+                } else {
+                    self.resume(interrupted)
+                }
+            }
+            "Z" | "z" => self.breakpoint(command == "Z", args),
+            "H" => "OK".to_string(),
+            "D" => {
+                self.attached = false;
+                "OK".to_string()
+            }
+            "k" => {
+                self.attached = false;
+                return Vec::new();
+            }
+            "q" | "Q" => return self.query(packet),
+            _ => String::new()
+        };
+
+        vec![reply]
+    }
+
+    /// Handle general query and set packets
+    fn query(&mut self, packet: &str) -> Vec<String> {
+        let (name, args) = packet.split_once([':', ',']).unwrap_or((packet, ""));
+        let reply = match name {
+            "qSupported" => format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+", MAX_TRANSFER * 2 + 16),
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            "qXfer" => self.target_xml(args),
+            "qRcmd" => return self.monitor(args),
+            _ => String::new()
+        };
+
+        vec![reply]
+    }
+
+    /// Serve `qXfer:features:read:target.xml:offset,length`
+    fn target_xml(&self, args: &str) -> String {
+        let range = args.strip_prefix("features:read:target.xml:").and_then(parse_range);
+        let (offset, len) = match range {
+            Some(range) => range,
+            None => return "E00".to_string()
+        };
+
+        let data = TARGET_XML.get(offset.min(TARGET_XML.len())..).unwrap_or("");
+        if data.len() > len {
+            format!("m{}", &data[..len])
+        } else {
+            format!("l{}", data)
+        }
+    }
+
+    /// Run a debugger command sent with `monitor`, sending its text back as console output
+    fn monitor(&mut self, args: &str) -> Vec<String> {
+        let line = match unhex(args).and_then(|bytes| String::from_utf8(bytes).ok()) {
+            Some(line) => line,
+            None => return vec!["E01".to_string()]
+        };
+
+        let text = match Command::parse(&line) {
+            Ok(Command::Quit) => return vec!["OK".to_string()],
+            Ok(command) => self.debugger.execute(&command),
+            Err(err) => err
+        };
+
+        if text.is_empty() {
+            vec!["OK".to_string()]
+        } else {
+            vec![format!("O{}", hex(format!("{}\n", text).as_bytes())), "OK".to_string()]
+        }
+    }
+
+    /// Continue in slices of [`SLICE`] instructions until the program stops or `interrupted`
+    /// returns true between two slices
+    fn resume(&mut self, interrupted: &mut dyn FnMut() -> bool) -> String {
+        loop {
+            let stop = if self.debugger.breakpoints.is_empty() {
+                match self.debugger.program.run_with_limits(&Limits::new().instructions(SLICE)) {
+                    Ok(ExitReason::BudgetExhausted(_)) => Stop::Stepped,
+                    Ok(ExitReason::NeedsInput) => Stop::NeedsInput,
+                    Ok(ExitReason::Halted) => Stop::Halted,
+                    Err(err) => Stop::Fault(err)
+                }
+            } else {
+                self.debugger.run(Some(SLICE as usize))
+            };
+
+            let ip = self.debugger.program.ip;
+            match stop {
+                // A slice may end on a breakpoint that the next one would skip
+                Stop::Stepped if self.debugger.breakpoints.contains(&ip) => {
+                    return self.stop_reply(Stop::Breakpoint(ip));
+                }
+                Stop::Stepped if interrupted() => return format!("S{:02x}", SIGINT),
+                Stop::Stepped => (),
+                stop => return self.stop_reply(stop)
+            }
+        }
+    }
+
+    fn stop_reply(&self, stop: Stop) -> String {
+        let signal = match stop {
+            Stop::Stepped | Stop::Breakpoint(_) => SIGTRAP,
+            Stop::NeedsInput => SIGTTIN,
+            Stop::Halted => return "W00".to_string(),
+            Stop::Fault(err) => match err.kind {
+                ErrorKind::UnknownOpcode(_) | ErrorKind::UnknownMode { .. }
+                    | ErrorKind::ImmediateDestination => SIGILL,
+                ErrorKind::NegativeAddress(_) | ErrorKind::InvalidJumpTarget(_) => SIGSEGV,
+                ErrorKind::Overflow => SIGFPE
+            }
+        };
+
+        if self.debugger.program.halted {
+            return "W00".to_string();
+        }
+        format!("S{:02x}", signal)
+    }
+
+    fn registers(&self) -> [i64; 2] {
+        // The PC is a byte address like every other address the front-end sees
+        [self.debugger.program.ip.wrapping_mul(WORD_SIZE) as i64, self.debugger.program.relative_base as i64]
+    }
+
+    fn set_register(&mut self, index: usize, value: i64) -> bool {
+        match index {
+            0 if value >= 0 && (value as usize).is_multiple_of(WORD_SIZE) => {
+                self.debugger.program.ip = value as usize / WORD_SIZE;
+            }
+            1 => self.debugger.program.relative_base = value as isize,
+            _ => return false
+        }
+        true
+    }
+
+    fn read_registers(&self) -> String {
+        self.registers().iter().map(|reg| hex(&reg.to_le_bytes())).collect()
+    }
+
+    fn write_registers(&mut self, args: &str) -> String {
+        let bytes = match unhex(args) {
+            Some(bytes) if bytes.len() == 2 * WORD_SIZE => bytes,
+            _ => return "E01".to_string()
+        };
+
+        for (index, chunk) in bytes.chunks(WORD_SIZE).enumerate() {
+            let mut value = [0; WORD_SIZE];
+            value.copy_from_slice(chunk);
+            if !self.set_register(index, i64::from_le_bytes(value)) {
+                return "E22".to_string();
+            }
+        }
+        "OK".to_string()
+    }
+
+    fn read_register(&self, args: &str) -> String {
+        match parse_hex(args).and_then(|index| self.registers().get(index).copied()) {
+            Some(value) => hex(&value.to_le_bytes()),
+            None => "E01".to_string()
+        }
+    }
+
+    fn write_register(&mut self, args: &str) -> String {
+        let parsed = args.split_once('=').and_then(|(index, value)| Some((parse_hex(index)?, unhex(value)?)));
+        match parsed {
+            Some((index, value)) if value.len() == WORD_SIZE => {
+                let mut bytes = [0; WORD_SIZE];
+                bytes.copy_from_slice(&value);
+                if self.set_register(index, i64::from_le_bytes(bytes)) { "OK" } else { "E22" }.to_string()
+            }
+            _ => "E01".to_string()
+        }
+    }
+
+    fn word_bytes(&self, word: Pos) -> [u8; WORD_SIZE] {
+        (self.debugger.program.read(word) as i64).to_le_bytes()
+    }
+
+    fn read_memory(&self, args: &str) -> String {
+        let (addr, len) = match parse_range(args) {
+            Some((addr, len)) if len <= MAX_TRANSFER && addr.checked_add(len).is_some() => (addr, len),
+            _ => return "E01".to_string()
+        };
+
+        let bytes: Vec<u8> = (addr..addr + len).map(|b| self.word_bytes(b / WORD_SIZE)[b % WORD_SIZE]).collect();
+        hex(&bytes)
+    }
+
+    fn write_memory(&mut self, args: &str) -> String {
+        let parsed = args.split_once(':').and_then(|(range, data)| Some((parse_range(range)?, unhex(data)?)));
+        let (addr, data) = match parsed {
+            Some(((addr, len), data)) if data.len() == len && addr.checked_add(len).is_some() => (addr, data),
+            _ => return "E01".to_string()
+        };
+
+        // Patch each touched word and write it back through the program so lifted code is
+        // invalidated
+        let mut offset = 0;
+        while offset < data.len() {
+            let word = (addr + offset) / WORD_SIZE;
+            let mut bytes = self.word_bytes(word);
+            while offset < data.len() && (addr + offset) / WORD_SIZE == word {
+                bytes[(addr + offset) % WORD_SIZE] = data[offset];
+                offset += 1;
+            }
+            self.debugger.program.write(word, i64::from_le_bytes(bytes) as isize);
+        }
+        "OK".to_string()
+    }
+
+    /// Insert or remove a breakpoint from a `Z`/`z` packet
+    fn breakpoint(&mut self, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let kind = fields.next();
+        let addr = fields.next().and_then(parse_hex);
+        match (kind, addr) {
+            (Some("0"), Some(addr)) | (Some("1"), Some(addr)) if addr % WORD_SIZE == 0 => {
+                if insert {
+                    self.debugger.breakpoints.insert(addr / WORD_SIZE);
+                } else {
+                    self.debugger.breakpoints.remove(&(addr / WORD_SIZE));
+                }
+                "OK".to_string()
+            }
+            (Some("0"), Some(_)) | (Some("1"), Some(_)) => "E22".to_string(),
+
+            // Watchpoints are not supported
+            _ => String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+    use std::io::{BufReader, BufRead};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// Minimal scripted client speaking the remote serial protocol
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream
+    }
+
+    impl Client {
+        /// Send a packet and return the body of the reply, checking acks and checksums
+        fn send(&mut self, data: &str) -> String {
+            self.writer.write_all(&frame(data)).unwrap();
+            let mut ack = [0; 1];
+            self.reader.read_exact(&mut ack).unwrap();
+            assert_eq!(ack[0], b'+');
+            self.receive()
+        }
+
+        fn receive(&mut self) -> String {
+            let mut packet = Vec::new();
+            self.reader.read_until(b'#', &mut packet).unwrap();
+            assert_eq!(packet[0], b'$');
+            let body = &packet[1..packet.len() - 1];
+
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum).unwrap();
+            let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+            assert_eq!(checksum, body.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+            self.writer.write_all(b"+").unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+
+        fn monitor(&mut self, command: &str) -> String {
+            let console = self.send(&format!("qRcmd,{}", hex(command.as_bytes())));
+            assert_eq!(self.receive(), "OK");
+            String::from_utf8(unhex(&console[1..]).unwrap()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_scripted_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut stub = GdbStub::new(Program::from_input(SUMS));
+            stub.serve(stream).unwrap();
+            stub.debugger.program.output
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut client = Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream };
+
+        assert!(client.send("qSupported:xmlRegisters=i386").contains("qXfer:features:read+"));
+        assert!(client.send("qXfer:features:read:target.xml:0,1000").contains("name=\"rb\""));
+        assert_eq!(client.send("?"), "S05");
+        assert_eq!(client.send("g"), "00000000000000000000000000000000");
+
+        // Word 1 is 100
+        assert_eq!(client.send("m8,8"), "6400000000000000");
+        assert_eq!(client.send("m9,2"), "0000");
+
+        // No input queued yet
+        assert_eq!(client.send("c"), "S15");
+        assert_eq!(client.monitor("input 4 0"), "2 value(s) queued\n");
+
+        // Break on the Out at word 6
+        assert_eq!(client.send("Z0,30,1"), "OK");
+        assert_eq!(client.send("Z0,31,1"), "E22");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p0"), "3000000000000000");
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("p0"), "4000000000000000");
+
+        // Patch the running sum at word 101 to 10 before it is printed again
+        assert_eq!(client.send("M328,8:0a00000000000000"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("m328,8"), "0a00000000000000");
+        assert_eq!(client.send("z0,30,1"), "OK");
+        assert_eq!(client.send("P1=0500000000000000"), "OK");
+        assert_eq!(client.send("P0=3100000000000000"), "E22");
+        assert_eq!(client.send("g"), "30000000000000000500000000000000");
+        assert_eq!(client.send("c"), "W00");
+        assert_eq!(client.send("D"), "OK");
+
+        assert_eq!(server.join().unwrap(), vec![4, 10]);
+    }
+
+    #[test]
+    fn test_faults_and_unsupported() {
+        let mut stub = GdbStub::new(Program::from_input("42"));
+        assert_eq!(stub.handle("s"), vec!["S04".to_string()]);
+        assert_eq!(stub.handle("vCont?"), vec![String::new()]);
+        assert_eq!(stub.handle("Z2,0,8"), vec![String::new()]);
+        assert_eq!(stub.handle("m0,ffffffff"), vec!["E01".to_string()]);
+
+        let mut stub = GdbStub::new(Program::from_input("1105,1,-1"));
+        assert_eq!(stub.handle("c"), vec!["S0b".to_string()]);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+
+            // Spins forever
+            let mut stub = GdbStub::new(Program::from_input("1105,1,0"));
+            stub.serve(stream).unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut client = Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream };
+
+        client.writer.write_all(&frame("c")).unwrap();
+        let mut ack = [0; 1];
+        client.reader.read_exact(&mut ack).unwrap();
+        thread::sleep(std::time::Duration::from_millis(50));
+        client.writer.write_all(&[INTERRUPT]).unwrap();
+        assert_eq!(client.receive(), "S02");
+        assert_eq!(client.send("p0"), "0000000000000000");
+        assert_eq!(client.send("D"), "OK");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_breakpoint_after_many_slices() {
+        // Counts [100] up to 150000, then halts at word 11
+        let mut stub = GdbStub::new(Program::from_input("1001,100,1,100,1007,100,150000,101,1005,101,0,99"));
+        assert_eq!(stub.handle("Z0,58,1"), vec!["OK".to_string()]);
+        assert_eq!(stub.handle("c"), vec!["S05".to_string()]);
+        assert_eq!(stub.debugger.program.ip, 11);
+        assert_eq!(stub.debugger.program.read(100), 150000);
+    }
+
+    #[test]
+    fn test_pc_is_a_byte_address() {
+        // Word 6 is byte 0x30 for the PC, `s addr` and breakpoints alike
+        let mut stub = GdbStub::new(Program::from_input(SUMS));
+        assert_eq!(stub.handle("P0=3000000000000000"), vec!["OK".to_string()]);
+        assert_eq!(stub.debugger.program.ip, 6);
+        assert_eq!(stub.handle("s30"), vec!["S05".to_string()]);
+        assert_eq!(stub.handle("p0"), vec!["4000000000000000".to_string()]);
+    }
+
+    #[test]
+    fn test_frame_escapes() {
+        assert_eq!(frame("OK"), b"$OK#9a".to_vec());
+        assert_eq!(frame("a#b"), b"$a}\x03b#43".to_vec());
+    }
+}
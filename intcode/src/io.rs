@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Where the emulator gets the values for `In` instructions
+pub trait InputSource {
+    /// Returns the next input value, or `None` if no input is available right now
+    fn read_input(&mut self) -> Option<isize>;
+}
+
+/// Where the emulator sends the values of `Out` instructions
+pub trait OutputSink {
+    /// Accept a value written by the program
+    fn write_output(&mut self, value: isize);
+
+    /// Take back the most recently written value when reverse execution undoes an `Out`. Sinks
+    /// that cannot take values back ignore this.
+    fn retract_output(&mut self) {}
+}
+
+impl InputSource for VecDeque<isize> {
+    fn read_input(&mut self) -> Option<isize> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for VecDeque<isize> {
+    fn write_output(&mut self, value: isize) {
+        self.push_back(value);
+    }
+
+    fn retract_output(&mut self) {
+        self.pop_back();
+    }
+}
+
+impl OutputSink for Vec<isize> {
+    fn write_output(&mut self, value: isize) {
+        self.push(value);
+    }
+
+    fn retract_output(&mut self) {
+        self.pop();
+    }
+}
+
+/// Closures returning `None` signal that no input is available
+impl<F: FnMut() -> Option<isize>> InputSource for F {
+    fn read_input(&mut self) -> Option<isize> {
+        self()
+    }
+}
+
+impl<F: FnMut(isize)> OutputSink for F {
+    fn write_output(&mut self, value: isize) {
+        self(value)
+    }
+}
+
+/// Blocks until a value arrives. Returns `None` once every sender has been dropped.
+impl InputSource for Receiver<isize> {
+    fn read_input(&mut self) -> Option<isize> {
+        self.recv().ok()
+    }
+}
+
+/// Values sent after the receiver has been dropped are discarded
+impl OutputSink for Sender<isize> {
+    fn write_output(&mut self, value: isize) {
+        let _ = self.send(value);
+    }
+}
+
+/// Reads one integer per line. Returns `None` on end of file or on a line that is not an integer.
+impl InputSource for std::io::Stdin {
+    fn read_input(&mut self) -> Option<isize> {
+        let mut line = String::new();
+        match self.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => line.trim().parse().ok()
+        }
+    }
+}
+
+/// Prints one integer per line
+impl OutputSink for std::io::Stdout {
+    fn write_output(&mut self, value: isize) {
+        let _ = writeln!(self.lock(), "{}", value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Event, ExitReason, Program};
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn test_closures() {
+        // Double each input until the input runs out
+        // In(Pos(9)), Mul(Pos(9), Imm(2), Pos(9)), Out(Pos(9)), JumpNonZero(Imm(1), Imm(0))
+        let input = "3,9,1002,9,2,9,4,9,1105,1,0";
+        let mut inputs = vec![3, 2, 1];
+        let mut outputs = Vec::new();
+        let mut program = Program::from_input(input)
+            .with_io(|| inputs.pop(), |value| outputs.push(value));
+        assert_eq!(program.run(), Ok(ExitReason::NeedsInput));
+        assert_eq!(outputs, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn test_channels_between_vms() {
+        // First VM adds one to its input, second VM multiplies its input by ten
+        let (input_tx, input_rx) = channel();
+        let (link_tx, link_rx) = channel();
+        let (output_tx, output_rx) = channel();
+
+        let mut first = Program::from_input("3,9,1001,9,1,9,4,9,99,0").with_io(input_rx, link_tx);
+        let mut second = Program::from_input("3,9,1002,9,10,9,4,9,99,0").with_io(link_rx, output_tx);
+        let first = thread::spawn(move || first.run());
+        let second = thread::spawn(move || second.run());
+
+        input_tx.send(4).unwrap();
+        assert_eq!(output_rx.recv(), Ok(50));
+        assert_eq!(first.join().unwrap(), Ok(ExitReason::Halted));
+        assert_eq!(second.join().unwrap(), Ok(ExitReason::Halted));
+    }
+
+    #[test]
+    fn test_disconnected_channel_needs_input() {
+        let (tx, rx) = channel::<isize>();
+        drop(tx);
+        let mut program = Program::from_input("3,0,99").with_io(rx, Vec::new());
+        assert_eq!(program.step(), Ok(Event::NeedsInput));
+    }
+}
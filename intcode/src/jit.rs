@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::program::Program;
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+use crate::{block::{Dst, Insn, Src}, opcode::{Imm, Mode, Opcode, Pos}};
+
+/// Size of the executable code buffer. Once full, all compiled code is dropped and compiled
+/// again as it is reached.
+#[cfg_attr(not(all(target_arch = "x86_64", target_os = "linux")), allow(dead_code))]
+const CODE_SIZE: usize = 1 << 20;
+
+/// State shared between the dispatcher and compiled code
+#[repr(C)]
+struct Context {
+    /// `Program` being executed, type erased
+    program: *mut (),
+
+    /// Relative base, kept in a register while compiled code runs
+    relative_base: isize,
+
+    /// IP to continue at once compiled code returns
+    ip: usize,
+
+    /// Cache generation the compiled code was built for
+    generation: u64
+}
+
+/// Compiled block. Returns 0 if execution can continue with the compiled block at the new IP, or
+/// 1 if the instruction at the IP must be executed by the interpreter.
+type Native = unsafe extern "sysv64" fn(*mut Context) -> u64;
+
+/// Just-in-time compiler from lifted blocks to x86-64 machine code.
+///
+/// Each [`Block`](crate::Block) from the instruction cache is compiled together with the jump
+/// that ends it. Memory is accessed through calls back into [`Program::read`] and
+/// [`Program::write`], the relative base lives in a register. Compiled code returns to the
+/// interpreter for I/O, halt and faults, and as soon as a write changes lifted code. Any change
+/// to lifted code drops every compiled block, mirroring the instruction cache.
+///
+/// Only available on x86-64 Linux. Elsewhere, or if executable memory cannot be mapped, programs
+/// silently run on the interpreter.
+#[derive(Default)]
+pub struct Jit {
+    /// Executable memory, mapped on first use
+    buffer: Option<CodeBuffer>,
+
+    /// Compiled code keyed by block start. `None` for blocks left to the interpreter.
+    blocks: HashMap<usize, Option<Native>>,
+
+    /// Cache generation the compiled blocks were built for
+    generation: u64,
+
+    /// Set if executable memory could not be mapped
+    unavailable: bool
+}
+
+// Safety: the code buffer is owned by the `Jit` and only accessed through `&mut self`
+unsafe impl Send for Jit {}
+
+/// A copy starts without any compiled code
+impl Clone for Jit {
+    fn clone(&self) -> Self {
+        Jit::default()
+    }
+}
+
+impl std::fmt::Debug for Jit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Jit {{ compiled: {} }}", self.compiled())
+    }
+}
+
+impl Jit {
+    /// Number of blocks currently compiled to machine code
+    pub fn compiled(&self) -> usize {
+        self.blocks.values().filter(|native| native.is_some()).count()
+    }
+
+    /// Drop every compiled block
+    fn flush(&mut self) {
+        self.blocks.clear();
+        if let Some(buffer) = &mut self.buffer {
+            buffer.used = 0;
+        }
+    }
+
+    /// Execute compiled code from the current IP until the interpreter is needed
+    fn run<I: InputSource, O: OutputSink, M: Memory>(&mut self, program: &mut Program<I, O, M>) {
+        loop {
+            if program.instructions.generation() != self.generation {
+                self.flush();
+                self.generation = program.instructions.generation();
+            }
+
+            let native = match self.blocks.get(&program.ip) {
+                Some(&native) => native,
+                None => {
+                    let native = self.compile(program);
+                    self.blocks.insert(program.ip, native);
+                    native
+                }
+            };
+            let native = match native {
+                Some(native) => native,
+                None => return
+            };
+
+            let mut context = Context {
+                program: program as *mut Program<I, O, M> as *mut (),
+                relative_base: program.relative_base,
+                ip: program.ip,
+                generation: self.generation
+            };
+
+            // Safety: the code was generated by `compile` for this program type and the program is
+            // only accessed through the context while it runs
+            let status = unsafe { native(&mut context) };
+            program.relative_base = context.relative_base;
+            program.ip = context.ip;
+            if status != 0 {
+                return;
+            }
+        }
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+    fn compile<I: InputSource, O: OutputSink, M: Memory>(&mut self, _program: &mut Program<I, O, M>)
+            -> Option<Native> {
+        None
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Execute straight-line code and jumps as native code. I/O, halt and faults still go through
+    /// the interpreter, so results are identical to running without the JIT.
+    ///
+    /// The JIT is not used while the undo journal, the shadow call stack, a trace, coverage or the
+    /// profiler is enabled.
+    pub fn with_jit(mut self) -> Self {
+        self.jit = Some(Jit::default());
+        self
+    }
+
+    /// Run compiled code from the current IP if the JIT is enabled. Leaves the IP on the next
+    /// instruction for the interpreter.
+    pub(crate) fn run_native(&mut self) {
+        if self.journal.is_some() || self.call_stack.is_some() || self.trace.is_some()
+                || self.coverage.is_some() || self.profile.is_some() {
+            return;
+        }
+        if let Some(mut jit) = self.jit.take() {
+            jit.run(self);
+            self.jit = Some(jit);
+        }
+    }
+}
+
+/// Executable memory mapped with `mmap`
+#[cfg_attr(not(all(target_arch = "x86_64", target_os = "linux")), allow(dead_code))]
+struct CodeBuffer {
+    ptr: *mut u8,
+    used: usize
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+mod sys {
+    use std::os::raw::{c_int, c_long, c_void};
+
+    pub const PROT_READ: c_int = 1;
+    pub const PROT_WRITE: c_int = 2;
+    pub const PROT_EXEC: c_int = 4;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_ANONYMOUS: c_int = 0x20;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        pub fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: c_long)
+            -> *mut c_void;
+        pub fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+impl CodeBuffer {
+    fn new() -> Option<CodeBuffer> {
+        // Safety: anonymous private mapping, nothing else refers to it
+        let ptr = unsafe {
+            sys::mmap(std::ptr::null_mut(), CODE_SIZE, sys::PROT_READ | sys::PROT_EXEC,
+                      sys::MAP_PRIVATE | sys::MAP_ANONYMOUS, -1, 0)
+        };
+        if ptr == sys::MAP_FAILED {
+            return None;
+        }
+        Some(CodeBuffer { ptr: ptr as *mut u8, used: 0 })
+    }
+
+    /// Copy `code` into the buffer. Returns `None` if it does not fit.
+    fn push(&mut self, code: &[u8]) -> Option<Native> {
+        if self.used + code.len() > CODE_SIZE {
+            return None;
+        }
+
+        // Safety: the range is inside the mapping and no compiled code runs while it is writable
+        unsafe {
+            let base = self.ptr as *mut std::os::raw::c_void;
+            if sys::mprotect(base, CODE_SIZE, sys::PROT_READ | sys::PROT_WRITE) != 0 {
+                return None;
+            }
+            let start = self.ptr.add(self.used);
+            std::ptr::copy_nonoverlapping(code.as_ptr(), start, code.len());
+            if sys::mprotect(base, CODE_SIZE, sys::PROT_READ | sys::PROT_EXEC) != 0 {
+                return None;
+            }
+            self.used += code.len();
+            Some(std::mem::transmute::<*mut u8, Native>(start))
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+impl Drop for CodeBuffer {
+    fn drop(&mut self) {
+        // Safety: the mapping is owned by the buffer and no compiled code can be running
+        unsafe {
+            sys::munmap(self.ptr as *mut std::os::raw::c_void, CODE_SIZE);
+        }
+    }
+}
+
+/// Called from compiled code to read memory
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+unsafe extern "sysv64" fn read<I: InputSource, O: OutputSink, M: Memory>(context: *mut Context, address: Pos)
+        -> Imm {
+    let program = &*((*context).program as *const Program<I, O, M>);
+    program.read(address)
+}
+
+/// Called from compiled code to write memory. Returns 1 if the write changed lifted code.
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+unsafe extern "sysv64" fn write<I: InputSource, O: OutputSink, M: Memory>(context: *mut Context, address: Pos,
+                                                                          value: Imm) -> u64 {
+    let program = &mut *((*context).program as *mut Program<I, O, M>);
+    program.write(address, value);
+    (program.instructions.generation() != (*context).generation) as u64
+}
+
+/// x86-64 registers used by compiled code
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[derive(Clone, Copy)]
+enum Reg {
+    Rax = 0,
+    Rcx = 1,
+    Rdx = 2,
+    Rbx = 3,
+    Rsi = 6,
+    Rdi = 7,
+    R12 = 12,
+    R13 = 13
+}
+
+/// Minimal x86-64 assembler for the instructions the compiler emits.
+///
+/// Register use: `rbx` holds the context, `r12` the relative base and `r13` a value that has to
+/// survive a call. Both are callee saved, everything else is scratch.
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+
+    /// Bound offset of every label
+    labels: Vec<Option<usize>>,
+
+    /// Offsets of rel32 fields with the label they refer to
+    fixups: Vec<(usize, usize)>
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+impl Assembler {
+    const JO: u8 = 0x80;
+    const JS: u8 = 0x88;
+    const JZ: u8 = 0x84;
+    const JNZ: u8 = 0x85;
+    const SETL: u8 = 0x9c;
+    const SETE: u8 = 0x94;
+
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn bind(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    /// REX prefix with W set for a `reg` and `rm` operand
+    fn rex(&mut self, reg: Reg, rm: Reg) {
+        self.emit(&[0x48 | (reg as u8 >> 3) << 2 | rm as u8 >> 3]);
+    }
+
+    /// Register to register instruction `op rm, reg`
+    fn rr(&mut self, op: &[u8], rm: Reg, reg: Reg) {
+        self.rex(reg, rm);
+        self.emit(op);
+        self.emit(&[0xc0 | (reg as u8 & 7) << 3 | rm as u8 & 7]);
+    }
+
+    /// `mov dst, src`
+    fn mov(&mut self, dst: Reg, src: Reg) {
+        self.rr(&[0x89], dst, src);
+    }
+
+    /// `mov dst, imm64`
+    fn mov_imm(&mut self, dst: Reg, imm: i64) {
+        self.emit(&[0x48 | dst as u8 >> 3, 0xb8 + (dst as u8 & 7)]);
+        self.emit(&imm.to_le_bytes());
+    }
+
+    /// `add dst, src`
+    fn add(&mut self, dst: Reg, src: Reg) {
+        self.rr(&[0x01], dst, src);
+    }
+
+    /// `imul dst, src`
+    fn imul(&mut self, dst: Reg, src: Reg) {
+        self.rr(&[0x0f, 0xaf], src, dst);
+    }
+
+    /// `cmp a, b`
+    fn cmp(&mut self, a: Reg, b: Reg) {
+        self.rr(&[0x39], a, b);
+    }
+
+    /// `test reg, reg`
+    fn test(&mut self, reg: Reg) {
+        self.rr(&[0x85], reg, reg);
+    }
+
+    /// `mov [rbx + offset], reg`
+    fn store_context(&mut self, offset: u8, reg: Reg) {
+        self.rex(reg, Reg::Rbx);
+        self.emit(&[0x89, 0x43 | (reg as u8 & 7) << 3, offset]);
+    }
+
+    /// `mov reg, [rbx + offset]`
+    fn load_context(&mut self, reg: Reg, offset: u8) {
+        self.rex(reg, Reg::Rbx);
+        self.emit(&[0x8b, 0x43 | (reg as u8 & 7) << 3, offset]);
+    }
+
+    /// `dst = (a < b)` or `dst = (a == b)` for a `SETcc` opcode
+    fn set(&mut self, setcc: u8, dst: Reg, a: Reg, b: Reg) {
+        self.emit(&[0x31, 0xc9]);
+        self.cmp(a, b);
+        self.emit(&[0x0f, setcc, 0xc1]);
+        self.mov(dst, Reg::Rcx);
+    }
+
+    /// Conditional jump with a 32 bit displacement
+    fn jcc(&mut self, cc: u8, label: usize) {
+        self.emit(&[0x0f, cc]);
+        self.fixup(label);
+    }
+
+    fn jmp(&mut self, label: usize) {
+        self.emit(&[0xe9]);
+        self.fixup(label);
+    }
+
+    fn fixup(&mut self, label: usize) {
+        self.fixups.push((self.code.len(), label));
+        self.emit(&[0; 4]);
+    }
+
+    /// `call` an absolute address through `rax`
+    fn call(&mut self, function: usize) {
+        self.mov_imm(Reg::Rax, function as i64);
+        self.emit(&[0xff, 0xd0]);
+    }
+
+    /// Resolve every jump and return the machine code
+    fn finish(mut self) -> Vec<u8> {
+        for &(offset, label) in &self.fixups {
+            let target = self.labels[label].expect("jump to unbound label");
+            let rel = target as i64 - (offset as i64 + 4);
+            self.code[offset..offset + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+        }
+        self.code
+    }
+}
+
+/// Offsets of the `Context` fields
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+const RELATIVE_BASE: u8 = 8;
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+const IP: u8 = 16;
+
+/// Compiles one block, holding the labels every instruction exits through
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+struct Compiler {
+    asm: Assembler,
+
+    /// Returns to the dispatcher with the status in `rax`
+    epilogue: usize,
+
+    /// Exits to the interpreter, keyed by IP. Used for faults and for the end of the block.
+    interpret: Vec<(Pos, usize)>,
+
+    /// Address of `read::<I, O, M>`
+    read: usize,
+
+    /// Address of `write::<I, O, M>`
+    write: usize
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+impl Compiler {
+    /// Label that leaves the instruction at `ip` to the interpreter
+    fn interpret(&mut self, ip: Pos) -> usize {
+        if let Some(&(_, label)) = self.interpret.iter().find(|&&(addr, _)| addr == ip) {
+            return label;
+        }
+        let label = self.asm.label();
+        self.interpret.push((ip, label));
+        label
+    }
+
+    /// Set the IP in `rax` and continue with compiled code
+    fn continue_at_rax(&mut self) {
+        self.asm.store_context(IP, Reg::Rax);
+        self.asm.emit(&[0x31, 0xc0]);
+        self.asm.jmp(self.epilogue);
+    }
+
+    /// `rsi` = relative base + `offset`, faulting on overflow or a negative address
+    fn relative(&mut self, offset: isize, fault: usize) {
+        self.asm.mov(Reg::Rsi, Reg::R12);
+        self.asm.mov_imm(Reg::Rax, offset as i64);
+        self.asm.add(Reg::Rsi, Reg::Rax);
+        self.asm.jcc(Assembler::JO, fault);
+        self.asm.jcc(Assembler::JS, fault);
+    }
+
+    /// Load the value of a source operand into `reg`
+    fn load(&mut self, src: Src, reg: Reg, fault: usize) {
+        match src {
+            Src::Imm(imm) => {
+                self.asm.mov_imm(reg, imm as i64);
+                return;
+            }
+            Src::Pos(addr) => self.asm.mov_imm(Reg::Rsi, addr as i64),
+            Src::Rel(offset) => self.relative(offset, fault)
+        }
+        self.asm.mov(Reg::Rdi, Reg::Rbx);
+        self.asm.call(self.read);
+        if !matches!(reg, Reg::Rax) {
+            self.asm.mov(reg, Reg::Rax);
+        }
+    }
+
+    /// Write `rdx` to the destination, leaving for `next` if lifted code changed
+    fn store(&mut self, dst: Dst, next: Pos, fault: usize) {
+        match dst {
+            Dst::Pos(addr) => self.asm.mov_imm(Reg::Rsi, addr as i64),
+            Dst::Rel(offset) => self.relative(offset, fault)
+        }
+        self.asm.mov(Reg::Rdi, Reg::Rbx);
+        self.asm.call(self.write);
+        self.asm.test(Reg::Rax);
+        let unchanged = self.asm.label();
+        self.asm.jcc(Assembler::JZ, unchanged);
+        self.asm.mov_imm(Reg::Rax, next as i64);
+        self.continue_at_rax();
+        self.asm.bind(unchanged);
+    }
+
+    fn insn(&mut self, addr: Pos, insn: Insn) {
+        let fault = self.interpret(addr);
+        let next = addr + insn.len();
+        match insn {
+            Insn::Store(_, imm) => self.asm.mov_imm(Reg::R13, imm as i64),
+            Insn::Copy(src, _) => self.load(src, Reg::R13, fault),
+            Insn::AddPosImm(src, imm, _) => {
+                self.load(Src::Pos(src), Reg::R13, fault);
+                self.asm.mov_imm(Reg::Rax, imm as i64);
+                self.asm.add(Reg::R13, Reg::Rax);
+                self.asm.jcc(Assembler::JO, fault);
+            }
+            Insn::Add(a, b, _) | Insn::Mul(a, b, _) | Insn::LessThan(a, b, _) | Insn::Equals(a, b, _) => {
+                self.load(a, Reg::R13, fault);
+                self.load(b, Reg::Rax, fault);
+                match insn {
+                    Insn::Add(..) => {
+                        self.asm.add(Reg::R13, Reg::Rax);
+                        self.asm.jcc(Assembler::JO, fault);
+                    }
+                    Insn::Mul(..) => {
+                        self.asm.imul(Reg::R13, Reg::Rax);
+                        self.asm.jcc(Assembler::JO, fault);
+                    }
+                    Insn::LessThan(..) => self.asm.set(Assembler::SETL, Reg::R13, Reg::R13, Reg::Rax),
+                    _ => self.asm.set(Assembler::SETE, Reg::R13, Reg::R13, Reg::Rax)
+                }
+            }
+            Insn::AdjustRelativeBase(src) => {
+                self.load(src, Reg::Rax, fault);
+                self.asm.mov(Reg::Rcx, Reg::R12);
+                self.asm.add(Reg::Rcx, Reg::Rax);
+                self.asm.jcc(Assembler::JO, fault);
+                self.asm.mov(Reg::R12, Reg::Rcx);
+                return;
+            }
+        }
+
+        let dst = match insn {
+            Insn::Store(dst, _) | Insn::Copy(_, dst) | Insn::Add(_, _, dst) | Insn::Mul(_, _, dst)
+                | Insn::LessThan(_, _, dst) | Insn::Equals(_, _, dst) => dst,
+            Insn::AddPosImm(_, _, dst) => Dst::Pos(dst),
+            Insn::AdjustRelativeBase(_) => unreachable!()
+        };
+        self.asm.mov(Reg::Rdx, Reg::R13);
+        self.store(dst, next, fault);
+    }
+
+    /// Compile the jump ending the block
+    fn jump(&mut self, addr: Pos, op: Opcode) {
+        let (cond, target, nonzero) = match op {
+            Opcode::JumpNonZero(cond, target) => (cond, target, true),
+            Opcode::JumpZero(cond, target) => (cond, target, false),
+            _ => unreachable!()
+        };
+        let fault = self.interpret(addr);
+        let next = addr + op.len();
+
+        self.load(cond.into(), Reg::R13, fault);
+        self.load(target.into(), Reg::Rax, fault);
+        let not_taken = self.asm.label();
+        self.asm.test(Reg::R13);
+        self.asm.jcc(if nonzero { Assembler::JZ } else { Assembler::JNZ }, not_taken);
+        match target {
+            Mode::Immediate(target) if target < 0 => self.asm.jmp(fault),
+            Mode::Immediate(_) => self.continue_at_rax(),
+            _ => {
+                self.asm.test(Reg::Rax);
+                self.asm.jcc(Assembler::JS, fault);
+                self.continue_at_rax();
+            }
+        }
+
+        self.asm.bind(not_taken);
+        self.asm.mov_imm(Reg::Rax, next as i64);
+        self.continue_at_rax();
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+impl Jit {
+    /// Compile the block at the IP with the jump that ends it. Returns `None` if there is nothing
+    /// to compile, so the interpreter executes the instruction at the IP.
+    fn compile<I: InputSource, O: OutputSink, M: Memory>(&mut self, program: &mut Program<I, O, M>)
+            -> Option<Native> {
+        if self.unavailable {
+            return None;
+        }
+
+        let block = program.block_at_ip();
+        let end = block.end();
+        let jump = program.instructions.get(end).copied()
+            .filter(|op| matches!(op, Opcode::JumpNonZero(_, _) | Opcode::JumpZero(_, _)));
+        if block.insns.is_empty() && jump.is_none() {
+            return None;
+        }
+
+        let mut asm = Assembler::default();
+        let epilogue = asm.label();
+
+        // push rbx, r12, r13, which also aligns the stack for calls
+        asm.emit(&[0x53, 0x41, 0x54, 0x41, 0x55]);
+        asm.mov(Reg::Rbx, Reg::Rdi);
+        asm.load_context(Reg::R12, RELATIVE_BASE);
+
+        let mut compiler = Compiler {
+            asm,
+            epilogue,
+            interpret: Vec::new(),
+            read: read::<I, O, M> as *const () as usize,
+            write: write::<I, O, M> as *const () as usize
+        };
+        let mut addr = block.start;
+        for &insn in &block.insns {
+            compiler.insn(addr, insn);
+            addr += insn.len();
+        }
+        match jump {
+            Some(op) => compiler.jump(end, op),
+            None => {
+                let exit = compiler.interpret(end);
+                compiler.asm.jmp(exit);
+            }
+        }
+
+        // Exits to the interpreter
+        for (ip, label) in std::mem::take(&mut compiler.interpret) {
+            compiler.asm.bind(label);
+            compiler.asm.mov_imm(Reg::Rax, ip as i64);
+            compiler.asm.store_context(IP, Reg::Rax);
+            compiler.asm.emit(&[0xb8, 1, 0, 0, 0]);
+            compiler.asm.jmp(epilogue);
+        }
+
+        // Store the relative base, pop r13, r12, rbx and return
+        let mut asm = compiler.asm;
+        asm.bind(epilogue);
+        asm.store_context(RELATIVE_BASE, Reg::R12);
+        asm.emit(&[0x41, 0x5d, 0x41, 0x5c, 0x5b, 0xc3]);
+        let code = asm.finish();
+
+        if self.buffer.is_none() {
+            self.buffer = CodeBuffer::new();
+            if self.buffer.is_none() {
+                self.unavailable = true;
+                return None;
+            }
+        }
+        let buffer = self.buffer.as_mut()?;
+        if let Some(native) = buffer.push(&code) {
+            return Some(native);
+        }
+
+        // Out of space, start over with only this block
+        self.blocks.clear();
+        buffer.used = 0;
+        buffer.push(&code)
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64", target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorKind, ExitReason, VmError};
+
+    /// Run `input` with and without the JIT and compare the resulting state
+    fn run_both(input: &str, inputs: &[isize]) -> Program {
+        let mut interpreted = Program::from_input(input);
+        let mut jitted = Program::from_input(input).with_jit();
+        interpreted.input.extend(inputs);
+        jitted.input.extend(inputs);
+        assert_eq!(jitted.run(), interpreted.run());
+        assert_eq!(jitted.ip, interpreted.ip);
+        assert_eq!(jitted.relative_base, interpreted.relative_base);
+        assert_eq!(jitted.output, interpreted.output);
+        assert_eq!(jitted.memory.regions(), interpreted.memory.regions());
+        jitted
+    }
+
+    #[test]
+    fn test_loops_run_natively() {
+        // Outputs 10 down to 1:
+        // Add(Imm(10), Imm(0), Pos(20)), Out(Pos(20)), Add(Pos(20), Imm(-1), Pos(20)),
+        // JumpNonZero(Pos(20), Imm(4)), Halt
+        let program = run_both("1101,10,0,20,4,20,1001,20,-1,20,1005,20,4,99", &[]);
+        assert_eq!(program.output, vec![10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(program.jit.as_ref().unwrap().compiled(), 2);
+
+        // Relative base, comparisons and multiplication from the day 9 quine
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let program = run_both(quine, &[]);
+        assert_eq!(program.output.len(), 16);
+
+        let program = run_both(include_str!("../../day09/input"), &[2]);
+        assert_eq!(program.output, vec![77944]);
+    }
+
+    #[test]
+    fn test_write_into_compiled_code() {
+        // The first Add overwrites the second Add with a Halt before it executes
+        run_both("1101,99,0,4,1101,7,0,20,4,20,99", &[]);
+
+        // A loop that clears the condition of its own backwards jump in the same block:
+        // Out(Imm(1)), Add(Imm(0), Imm(0), Pos(7)), JumpNonZero(Imm(1), Imm(0)), Halt
+        let program = run_both("104,1,1101,0,0,7,1105,1,0,99", &[]);
+        assert_eq!(program.output, vec![1]);
+
+        run_both(include_str!("../../day05/input"), &[5]);
+    }
+
+    #[test]
+    fn test_faults_match_interpreter() {
+        // AdjustRelativeBase(Imm(-5)) then a read of [rb+0]
+        let mut program = Program::from_input("1101,1,1,10,109,-5,1201,0,1,10,99").with_jit();
+        let err = program.run().unwrap_err();
+        assert_eq!(err, VmError { ip: 6, opcode: 1201, kind: ErrorKind::NegativeAddress(-5) });
+        assert_eq!(program.read(10), 2);
+
+        // Overflow and a negative jump target
+        run_both("1102,4611686018427387904,2,0,99", &[]);
+        let mut program = Program::from_input("1105,1,-1").with_jit();
+        assert_eq!(program.run().unwrap_err().kind, ErrorKind::InvalidJumpTarget(-1));
+    }
+
+    #[test]
+    fn test_resume_after_input() {
+        let mut program = Program::from_input("3,20,1001,20,1,20,4,20,1105,1,0").with_jit();
+        assert_eq!(program.run(), Ok(ExitReason::NeedsInput));
+        program.input.push_back(41);
+        assert_eq!(program.run(), Ok(ExitReason::NeedsInput));
+        assert_eq!(program.output, vec![42]);
+        assert_eq!(program.ip, 0);
+    }
+}
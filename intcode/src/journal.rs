@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Pos};
+use crate::program::Program;
+
+/// Everything needed to undo one executed instruction. An Intcode instruction writes at most one
+/// memory cell, consumes at most one input and produces at most one output.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// IP before the instruction executed
+    ip: Pos,
+
+    /// Relative base before the instruction executed
+    relative_base: isize,
+
+    /// Halted flag before the instruction executed
+    halted: bool,
+
+    /// Address written and the value it held before the write
+    write: Option<(Pos, Imm)>,
+
+    /// Input value consumed by the instruction
+    input: Option<isize>,
+
+    /// The instruction wrote an output
+    output: bool
+}
+
+/// Undo journal for reverse execution, kept in a ring buffer of the most recent instructions.
+///
+/// Recording costs one entry per executed instruction and never allocates once the ring buffer
+/// is full, so the journal can stay enabled during normal runs. Instructions older than the
+/// capacity are forgotten and can no longer be undone.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    /// Undo entries, oldest first
+    entries: VecDeque<Entry>,
+
+    /// Maximum number of entries kept
+    capacity: usize,
+
+    /// Entry for the instruction currently executing
+    current: Option<Entry>,
+
+    /// Inputs given back by undone `In` instructions, most recently undone last. These are
+    /// consumed before the input source so replaying forward reads the same values.
+    replay: Vec<isize>
+}
+
+impl Journal {
+    /// Journal that can undo up to `capacity` instructions
+    pub fn new(capacity: usize) -> Journal {
+        Journal {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            current: None,
+            replay: Vec::new()
+        }
+    }
+
+    /// Maximum number of instructions that can be undone
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of instructions that can currently be undone
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there is nothing to undo
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forget every recorded instruction
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Address written by the most recent undoable instruction, if it wrote memory
+    fn last_write(&self) -> Option<Pos> {
+        self.entries.back().and_then(|entry| entry.write).map(|(address, _)| address)
+    }
+
+    /// Returns true if any recorded instruction wrote `address`
+    fn wrote(&self, address: Pos) -> bool {
+        self.entries.iter().any(|entry| entry.write.is_some_and(|(written, _)| written == address))
+    }
+
+    /// Remember the previous value of a memory cell about to be overwritten
+    pub(crate) fn record_write(&mut self, address: Pos, old: Imm) {
+        if let Some(entry) = &mut self.current {
+            entry.write = Some((address, old));
+        }
+    }
+
+    /// Take the next input given back by an undone `In`, if any
+    pub(crate) fn replay_input(&mut self) -> Option<isize> {
+        self.replay.pop()
+    }
+
+    /// Remember that the current instruction consumed `value`
+    pub(crate) fn record_input(&mut self, value: isize) {
+        if let Some(entry) = &mut self.current {
+            entry.input = Some(value);
+        }
+    }
+
+    /// Remember that the current instruction wrote an output
+    pub(crate) fn record_output(&mut self) {
+        if let Some(entry) = &mut self.current {
+            entry.output = true;
+        }
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Enable reverse execution, remembering up to `capacity` instructions
+    pub fn with_journal(mut self, capacity: usize) -> Self {
+        self.journal = Some(Journal::new(capacity));
+        self
+    }
+
+    /// Start recording the instruction at the current IP
+    pub(crate) fn begin_record(&mut self) {
+        if let Some(journal) = &mut self.journal {
+            journal.current = Some(Entry {
+                ip: self.ip,
+                relative_base: self.relative_base,
+                halted: self.halted,
+                write: None,
+                input: None,
+                output: false
+            });
+        }
+    }
+
+    /// Finish recording the current instruction. Instructions that did not change any state,
+    /// such as a fault or an `In` without input, are dropped.
+    pub(crate) fn end_record(&mut self, changed: bool) {
+        if let Some(journal) = &mut self.journal {
+            let entry = match journal.current.take() {
+                Some(entry) if changed => entry,
+                _ => return
+            };
+
+            if journal.capacity == 0 {
+                return;
+            }
+            if journal.entries.len() == journal.capacity {
+                journal.entries.pop_front();
+            }
+            journal.entries.push_back(entry);
+        }
+    }
+
+    /// Undo the most recently executed instruction.
+    ///
+    /// Memory, IP, relative base and halted flag are restored. An undone `In` gives its value
+    /// back so it is read again when execution resumes, and an undone `Out` is retracted from the
+    /// output sink. Returns false if the journal is disabled or has nothing left to undo.
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.journal.as_mut().and_then(|journal| journal.entries.pop_back()) {
+            Some(entry) => entry,
+            None => return false
+        };
+
+        if let Some((address, old)) = entry.write {
+            // No instruction is being recorded, so this write is not journaled
+            self.write(address, old);
+        }
+        if let Some(value) = entry.input {
+            if let Some(journal) = &mut self.journal {
+                journal.replay.push(value);
+            }
+        }
+        if entry.output {
+            self.output.retract_output();
+        }
+
+        self.ip = entry.ip;
+        self.relative_base = entry.relative_base;
+        self.halted = entry.halted;
+        true
+    }
+
+    /// Step backwards at least once, until the IP is `ip`. Returns false if the journal ran out
+    /// first, leaving the program at the oldest recorded instruction.
+    pub fn run_back_to(&mut self, ip: Pos) -> bool {
+        while self.step_back() {
+            if self.ip == ip {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Reverse-continue to the most recent write of `address`. The program is left just before
+    /// the writing instruction executes, so the IP points at that instruction and memory holds
+    /// the old value. Returns false and leaves the program untouched if no recorded instruction
+    /// wrote `address`.
+    pub fn run_back_to_write(&mut self, address: Pos) -> bool {
+        if !self.journal.as_ref().is_some_and(|journal| journal.wrote(address)) {
+            return false;
+        }
+
+        loop {
+            let last_write = self.journal.as_ref().and_then(Journal::last_write);
+            self.step_back();
+            if last_write == Some(address) {
+                return true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+    use crate::{Event, ExitReason};
+
+    #[test]
+    fn test_step_back_restores_state() {
+        let mut program = Program::from_input("1101,2,3,20,109,7,22201,13,13,5,99").with_journal(16);
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert_eq!(program.read(12), 10);
+        assert_eq!(program.journal.as_ref().unwrap().len(), 4);
+
+        // Undo Halt, then the relative Add
+        assert!(program.step_back());
+        assert!(!program.halted);
+        assert_eq!(program.ip, 10);
+        assert!(program.step_back());
+        assert_eq!(program.read(12), 0);
+        assert_eq!(program.ip, 6);
+
+        assert!(program.step_back());
+        assert_eq!(program.relative_base, 0);
+        assert!(program.step_back());
+        assert_eq!(program.read(20), 0);
+        assert_eq!(program.ip, 0);
+        assert!(!program.step_back());
+    }
+
+    #[test]
+    fn test_rewind_io_and_replay() {
+        let mut program = Program::from_input(SUMS).with_journal(1024);
+        program.input.extend(vec![1, 2, 3, 0]);
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert_eq!(program.output, vec![1, 3, 6, 6]);
+
+        // Back to the third In, retracting the last two outputs
+        assert!(program.run_back_to(0));
+        assert!(program.run_back_to(0));
+        assert_eq!(program.output, vec![1, 3]);
+        assert_eq!(program.read(101), 3);
+        assert!(program.input.is_empty());
+
+        // Replaying forward reads the same inputs again
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert_eq!(program.output, vec![1, 3, 6, 6]);
+    }
+
+    #[test]
+    fn test_run_back_to_write() {
+        let mut program = Program::from_input(SUMS).with_journal(1024);
+        program.input.extend(vec![5, 0]);
+        program.run().unwrap();
+
+        // The last write of the sum is the Add in the second iteration, which added 0
+        assert!(program.run_back_to_write(101));
+        assert_eq!(program.ip, 2);
+        assert_eq!(program.read(100), 0);
+        assert_eq!(program.output, vec![5]);
+
+        assert!(program.run_back_to_write(101));
+        assert_eq!(program.read(101), 0);
+
+        // Nothing earlier wrote the sum, so the program stays where it is
+        assert!(!program.run_back_to_write(101));
+        assert_eq!(program.ip, 2);
+        assert_eq!(program.journal.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ring_buffer_forgets_oldest() {
+        let mut program = Program::from_input(SUMS).with_journal(3);
+        program.input.extend(vec![1, 2, 0]);
+        program.run().unwrap();
+
+        assert!(!program.run_back_to(0));
+        assert_eq!(program.ip, 6);
+        assert_eq!(program.output, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_undo_self_modifying_write() {
+        // Add(Imm(0), Imm(99), Pos(4)) overwrites the following Out(Imm(7)) with a Halt
+        let mut program = Program::from_input("1101,0,99,4,104,7,99").with_journal(8);
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert!(program.output.is_empty());
+
+        assert!(program.run_back_to(0));
+        assert_eq!(program.read(4), 104);
+        program.write(3, 20);
+        assert_eq!(program.run_until_output(), Ok(Event::Output(7)));
+    }
+}
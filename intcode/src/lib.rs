@@ -0,0 +1,81 @@
+//! Intcode emulator shared by all of the Intcode puzzle days
+//!
+//! The emulator lifts instructions out of memory on first execution and caches the lifted
+//! [`Opcode`] keyed by address. Writes that land inside a cached instruction re-lift it so
+//! self-modifying programs keep working.
+
+/// Verbosity of the `info!` and `debug!` macros. 1 for info, 2 for debug.
+pub(crate) const LOGLEVEL: u8 = 0;
+
+macro_rules! debug {
+    ( $($arg:tt)* ) => {
+        if $crate::LOGLEVEL >= 2 {
+            print!("DEBUG: ");
+            print!($($arg)*);
+        }
+    }
+}
+
+macro_rules! info {
+    ( $($arg:tt)* ) => {
+        if $crate::LOGLEVEL >= 1 {
+            print!("INFO:  ");
+            print!($($arg)*);
+        }
+    }
+}
+
+mod aot;
+mod asm;
+mod block;
+mod cache;
+mod calls;
+mod cfg;
+mod coverage;
+mod debugger;
+mod decompile;
+mod disasm;
+mod error;
+mod event;
+#[cfg(test)]
+mod fixtures;
+mod fuzz;
+mod gdb;
+mod io;
+mod jit;
+mod journal;
+mod limits;
+mod lint;
+mod memory;
+mod opcode;
+mod profile;
+mod program;
+mod snapshot;
+mod symbolic;
+mod trace;
+
+pub use asm::{assemble, to_intcode, AsmError, AsmErrorKind};
+pub use block::{Block, Dst, Insn, Src};
+pub use cache::InstructionCache;
+pub use calls::{CallGraph, CallSite, Frame, Function};
+pub use cfg::{BasicBlock, Cfg, Edge, EdgeKind, Terminator};
+pub use coverage::{Branch, Coverage};
+pub use debugger::{Command, Debugger, Stop};
+pub use decompile::{BinOp, Decompiled, Expr, FunctionCode, Stmt, Var};
+pub use disasm::{Disassembly, Line, Strategy};
+pub use error::{ErrorKind, ExitReason, ParseError, VmError};
+pub use event::Event;
+pub use fuzz::{Finding, FindingKind, Fuzzer};
+pub use gdb::{Connection, GdbStub, WORD_SIZE};
+pub use io::{InputSource, OutputSink};
+pub use jit::Jit;
+pub use journal::Journal;
+pub use limits::{Limit, Limits};
+pub use lint::{Lint, LintKind, LintReport, Severity};
+pub use memory::{Memory, PagedMemory, PAGE_SIZE};
+pub use opcode::{Imm, Mode, Opcode, Pos};
+pub use profile::Profile;
+pub use program::Program;
+pub use snapshot::{Pending, SnapshotError, SNAPSHOT_VERSION};
+pub use symbolic::{Constraint, Path, PathEnd, Solution, Symbol, SymbolicExecutor, Term};
+pub use trace::{TraceEntry, TraceError, TraceReader, Tracer, TRACE_VERSION};
@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{ExitReason, VmError};
+use crate::event::Event;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::program::Program;
+
+/// Number of instructions executed between checks of the deadline
+const CLOCK_INTERVAL: u64 = 4096;
+
+/// Which limit stopped a [`Program::run_with_limits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// The instruction budget was used up
+    Instructions,
+
+    /// The deadline passed
+    Deadline,
+
+    /// The cancellation flag was set
+    Cancelled
+}
+
+/// Bounds on a single call to [`Program::run_with_limits`]
+///
+/// ```
+/// use std::time::Duration;
+/// use intcode::{ExitReason, Limit, Limits, Program};
+///
+/// // Jump to itself forever
+/// let mut program = Program::from_input("1105,1,0");
+/// let limits = Limits::new().instructions(1000).timeout(Duration::from_secs(1));
+/// assert_eq!(program.run_with_limits(&limits),
+///            Ok(ExitReason::BudgetExhausted(Limit::Instructions)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Maximum number of instructions to execute
+    pub instructions: Option<u64>,
+
+    /// Point in time after which execution stops
+    pub deadline: Option<Instant>,
+
+    /// Execution stops once this flag is set, possibly from another thread
+    pub cancel: Option<Arc<AtomicBool>>
+}
+
+impl Limits {
+    /// No limits at all
+    pub fn new() -> Limits {
+        Limits::default()
+    }
+
+    /// Execute at most `count` instructions
+    pub fn instructions(mut self, count: u64) -> Limits {
+        self.instructions = Some(count);
+        self
+    }
+
+    /// Stop once `deadline` has passed
+    pub fn deadline(mut self, deadline: Instant) -> Limits {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop once `timeout` has elapsed from now
+    pub fn timeout(self, timeout: Duration) -> Limits {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Stop once `flag` is set
+    pub fn cancel(mut self, flag: Arc<AtomicBool>) -> Limits {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Returns the deadline or cancellation limit that has been hit, if any
+    fn interrupted(&self) -> Option<Limit> {
+        if self.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Some(Limit::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(Limit::Deadline);
+        }
+        None
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Execute like [`Program::run`], but stop once any of the given limits is hit.
+    ///
+    /// Limits are checked between instructions, so a program stopped by a limit is left in a
+    /// consistent state and calling `run` again resumes it exactly where it stopped. The deadline
+    /// is checked every few thousand instructions, and the cancellation flag at least as often.
+    pub fn run_with_limits(&mut self, limits: &Limits) -> Result<ExitReason, VmError> {
+        let mut remaining = limits.instructions.unwrap_or(u64::MAX);
+        let mut next_check = 0;
+
+        loop {
+            if next_check == 0 {
+                if let Some(limit) = limits.interrupted() {
+                    return Ok(ExitReason::BudgetExhausted(limit));
+                }
+                next_check = CLOCK_INTERVAL;
+            }
+
+            if remaining == 0 {
+                return Ok(ExitReason::BudgetExhausted(Limit::Instructions));
+            }
+
+            let executed = self.execute_block(remaining.min(next_check))?;
+            remaining -= executed;
+            next_check -= executed;
+            if remaining == 0 || next_check == 0 {
+                continue;
+            }
+
+            match self.step()? {
+                Event::Executed | Event::Output(_) => {
+                    remaining -= 1;
+                    next_check -= 1;
+                }
+                Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+                Event::Halted => return Ok(ExitReason::Halted)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts down from the input, outputting each value, then halts
+    const COUNTDOWN: &str = "3,20,4,20,1001,20,-1,20,1005,20,2,99";
+
+    #[test]
+    fn test_budget_is_resumable() {
+        let mut expected = Program::from_input(COUNTDOWN);
+        expected.input.push_back(50);
+        expected.run().unwrap();
+
+        // Run in slices of 7 instructions, which regularly stops in the middle of a block
+        let mut program = Program::from_input(COUNTDOWN);
+        program.input.push_back(50);
+        let limits = Limits::new().instructions(7);
+        let mut slices = 0;
+        while program.run_with_limits(&limits).unwrap() != ExitReason::Halted {
+            slices += 1;
+        }
+        assert_eq!(program.output, expected.output);
+
+        // 1 In + 50 * (Out, Add, Jump)
+        assert_eq!(slices, 151 / 7);
+    }
+
+    #[test]
+    fn test_exact_budget() {
+        let mut program = Program::from_input(COUNTDOWN);
+        program.input.push_back(1);
+        let limits = Limits::new().instructions(4);
+        assert_eq!(program.run_with_limits(&limits), Ok(ExitReason::BudgetExhausted(Limit::Instructions)));
+        assert_eq!(program.ip, 11);
+        assert_eq!(program.run_with_limits(&limits), Ok(ExitReason::Halted));
+
+        let mut program = Program::from_input(COUNTDOWN);
+        assert_eq!(program.run_with_limits(&limits), Ok(ExitReason::NeedsInput));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut program = Program::from_input("1105,1,0");
+        let limits = Limits::new().timeout(Duration::from_millis(20));
+        assert_eq!(program.run_with_limits(&limits), Ok(ExitReason::BudgetExhausted(Limit::Deadline)));
+        assert_eq!(program.ip, 0);
+    }
+
+    #[test]
+    fn test_cancel_from_another_thread() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let limits = Limits::new().cancel(flag.clone());
+        let runner = std::thread::spawn(move || {
+            let mut program = Program::from_input("1105,1,0");
+            program.run_with_limits(&limits)
+        });
+
+        flag.store(true, Ordering::Relaxed);
+        assert_eq!(runner.join().unwrap(), Ok(ExitReason::BudgetExhausted(Limit::Cancelled)));
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use crate::cfg::{escape, Terminator};
+use crate::disasm::{ends_flow, jump_target};
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Mode, Opcode, Pos};
+use crate::program::Program;
+use crate::trace::sources;
+
+/// How bad a finding of the lint pass is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Suspicious, but the program may well work as intended
+    Warning,
+
+    /// The program faults or runs garbage if the instruction is executed
+    Error
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        }
+    }
+}
+
+/// Kind of problem a lint reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    /// A write lands inside reachable code
+    SelfModifying,
+
+    /// A destination parameter is in immediate mode
+    ImmediateDestination,
+
+    /// A jump targets a word that is not the start of an instruction
+    BadJumpTarget,
+
+    /// Instructions that no path from the entry point reaches
+    Unreachable,
+
+    /// A read of memory past the image that nothing writes
+    UninitialisedRead
+}
+
+impl LintKind {
+    /// Stable name of the lint, used in both output formats
+    pub fn code(self) -> &'static str {
+        match self {
+            LintKind::SelfModifying => "self-modifying",
+            LintKind::ImmediateDestination => "immediate-destination",
+            LintKind::BadJumpTarget => "bad-jump-target",
+            LintKind::Unreachable => "unreachable",
+            LintKind::UninitialisedRead => "uninitialised-read"
+        }
+    }
+
+    /// Severity of a finding of this kind, unless the finding says otherwise. A bad jump target
+    /// is only reported as a warning when the jump may never be taken.
+    pub fn severity(self) -> Severity {
+        match self {
+            LintKind::ImmediateDestination | LintKind::BadJumpTarget => Severity::Error,
+            _ => Severity::Warning
+        }
+    }
+}
+
+/// A single finding of [`Program::lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Address of the offending instruction, or the start of unreachable code
+    pub addr: Pos,
+
+    pub kind: LintKind,
+
+    pub severity: Severity,
+
+    /// Human readable description
+    pub message: String
+}
+
+/// Findings of [`Program::lint`], ordered by address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub lints: Vec<Lint>
+}
+
+impl LintReport {
+    /// Most severe finding, if there are any
+    pub fn severity(&self) -> Option<Severity> {
+        self.lints.iter().map(|lint| lint.severity).max()
+    }
+
+    /// Serialise the findings as JSON, for tools that check programs in CI
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n  \"lints\": [");
+        for (i, lint) in self.lints.iter().enumerate() {
+            let _ = write!(json, "{}\n    {{\"addr\": {}, \"severity\": \"{}\", \"code\": \"{}\", \"message\": \"{}\"}}",
+                           if i == 0 { "" } else { "," }, lint.addr, lint.severity.name(), lint.kind.code(),
+                           escape(&lint.message));
+        }
+        json.push_str(if self.lints.is_empty() { "]\n}\n" } else { "\n  ]\n}\n" });
+        json
+    }
+}
+
+/// One finding per line, `addr: severity[code]: message`
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for lint in &self.lints {
+            writeln!(f, "{:04}: {}[{}]: {}", lint.addr, lint.severity.name(), lint.kind.code(), lint.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Destination parameter of an instruction
+fn destination(op: &Opcode) -> Option<Mode> {
+    match *op {
+        Opcode::Add(_, _, dest) | Opcode::Mul(_, _, dest) | Opcode::LessThan(_, _, dest)
+            | Opcode::Equals(_, _, dest) | Opcode::In(dest) => Some(dest),
+        _ => None
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Check the program for suspicious code without running it.
+    ///
+    /// The code considered is what [`Program::recover_functions`] finds from address 0. Only
+    /// positional parameters are followed, so writes and reads through `rb` are not checked.
+    /// Unreachable code is not reported for programs that patch their own code, and code a word of
+    /// the image points to counts as reached once there are indirect jumps.
+    pub fn lint(&self) -> LintReport {
+        let graph = self.recover_functions();
+        let code: BTreeMap<Pos, Opcode> = graph.cfg.blocks.values().flat_map(|block| block.insns.iter().copied())
+            .collect();
+        let end = self.image_end();
+
+        // Instruction covering each word of reachable code
+        let mut owner = BTreeMap::new();
+        for (&addr, op) in &code {
+            owner.extend((addr..addr + op.len()).map(|word| (word, addr)));
+        }
+        // Words that do not decode but execution runs into, keyed to the instruction before them
+        let runs_into: BTreeMap<Pos, Pos> = graph.cfg.blocks.values()
+            .filter(|block| block.terminator == Terminator::Invalid && !owner.contains_key(&block.end))
+            .map(|block| (block.end, block.insns.last().expect("blocks are never empty").0))
+            .collect();
+        let written: BTreeSet<Pos> = code.values().filter_map(destination)
+            .filter_map(|dest| match dest {
+                Mode::Positional(addr) => Some(addr),
+                _ => None
+            })
+            .collect();
+
+        let mut lints = Vec::new();
+        let mut lint = |addr: Pos, kind: LintKind, severity: Option<Severity>, message: String| {
+            lints.push(Lint { addr, kind, severity: severity.unwrap_or_else(|| kind.severity()), message });
+        };
+        for (&addr, op) in &code {
+            match destination(op) {
+                Some(Mode::Immediate(_)) => {
+                    lint(addr, LintKind::ImmediateDestination, None,
+                         format!("{} has an immediate destination and faults when executed", op));
+                }
+                Some(Mode::Positional(dest)) if owner.contains_key(&dest) => {
+                    lint(addr, LintKind::SelfModifying, None,
+                         format!("{} writes into the instruction at {:04}", op, owner[&dest]));
+                }
+                Some(Mode::Positional(dest)) if runs_into.contains_key(&dest) => {
+                    lint(addr, LintKind::SelfModifying, None,
+                         format!("{} writes to {}, which does not decode but runs after the instruction at {:04}", op,
+                                 dest, runs_into[&dest]));
+                }
+                _ => ()
+            }
+
+            // Jumps that are never taken may point anywhere, and only jumps with an immediate
+            // condition are known to be taken
+            let taken = match *op {
+                Opcode::JumpNonZero(Mode::Immediate(cond), _) => Some(cond != 0),
+                Opcode::JumpZero(Mode::Immediate(cond), _) => Some(cond == 0),
+                _ => None
+            };
+            if let Opcode::JumpNonZero(_, Mode::Immediate(target)) | Opcode::JumpZero(_, Mode::Immediate(target)) = *op {
+                let reason = match jump_target(op) {
+                    _ if taken == Some(false) => None,
+                    None => Some(format!("{}, a negative address", target)),
+                    Some(target) if code.contains_key(&target) => None,
+                    Some(target) => match owner.get(&target) {
+                        Some(inside) => Some(format!("{}, inside the instruction at {:04}", target, inside)),
+                        None => Some(format!("{}, which does not decode", target))
+                    }
+                };
+                if let Some(reason) = reason {
+                    // A jump that may never be taken is only worth a warning
+                    let severity = if taken == Some(true) { None } else { Some(Severity::Warning) };
+                    lint(addr, LintKind::BadJumpTarget, severity, format!("{} jumps to {}", op, reason));
+                }
+            }
+
+            for source in sources(op) {
+                if let Mode::Positional(read) = source {
+                    if read >= end && !written.contains(&read) {
+                        lint(addr, LintKind::UninitialisedRead, None,
+                             format!("{} reads [{}], which is past the image and never written", op, read));
+                    }
+                }
+            }
+        }
+
+        // Once code is patched anything may run, and indirect jumps may go anywhere a word of the
+        // image points to
+        let patched = lints.iter().any(|lint| lint.kind == LintKind::SelfModifying);
+        let mut live: BTreeSet<Pos> = owner.keys().copied().collect();
+        if graph.cfg.indirect_jumps().next().is_some() {
+            let pointers: Vec<Pos> = (0..end).map(|addr| self.read(addr))
+                .filter(|&word| word >= 0 && (word as Pos) < end)
+                .map(|word| word as Pos)
+                .collect();
+            for (addr, op) in self.reachable_code(&pointers) {
+                live.extend(addr..addr + op.len());
+            }
+        }
+
+        if !patched {
+            // Dead words that decode as a run of at least two instructions ending in a halt or
+            // jump look like code rather than data
+            let mut addr = 0;
+            while addr < end {
+                if live.contains(&addr) {
+                    addr += 1;
+                    continue;
+                }
+                let start = addr;
+                let mut count = 0;
+                let mut dead = false;
+                while let Ok(op) = self.decode(addr) {
+                    if (addr..addr + op.len()).any(|word| live.contains(&word)) {
+                        break;
+                    }
+                    count += 1;
+                    addr += op.len();
+                    if ends_flow(&op) {
+                        dead = count >= 2;
+                        break;
+                    }
+                }
+                if dead {
+                    lints.push(Lint {
+                        addr: start,
+                        kind: LintKind::Unreachable,
+                        severity: LintKind::Unreachable.severity(),
+                        message: format!("{} instructions at {:04}-{:04} are never reached", count, start, addr - 1)
+                    });
+                } else {
+                    addr = start + 1;
+                }
+            }
+        }
+
+        lints.sort_by_key(|lint| (lint.addr, lint.kind));
+        LintReport { lints }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SUMS;
+
+    /// 0: add #1, #2 -> #100, add [200], [7] -> [101], add [101], #1 -> [13], jz #0, #15
+    /// 15: out [101], jnz [101], #13, halt
+    /// 21: out [101], halt, which is not reported since the program patches its own code
+    const SUSPICIOUS: &str = "11101,1,2,100,1,200,7,101,1001,101,1,13,1106,0,15,4,101,1005,101,13,99,4,101,99";
+
+    #[test]
+    fn test_clean() {
+        let program = Program::from_input(SUMS);
+        let report = program.lint();
+        assert_eq!(report.lints, vec![]);
+        assert_eq!(report.severity(), None);
+        assert_eq!(report.to_json(), "{\n  \"lints\": []\n}\n");
+    }
+
+    #[test]
+    fn test_lints() {
+        let report = Program::from_input(SUSPICIOUS).lint();
+        assert_eq!(report.severity(), Some(Severity::Error));
+        assert_eq!(report.to_string(), "\
+0000: error[immediate-destination]: add #1, #2 -> #100 has an immediate destination and faults when executed
+0004: warning[uninitialised-read]: add [200], [7] -> [101] reads [200], which is past the image and never written
+0008: warning[self-modifying]: add [101], #1 -> [13] writes into the instruction at 0012
+0017: warning[bad-jump-target]: jnz [101], #13 jumps to 13, inside the instruction at 0012
+");
+    }
+
+    #[test]
+    fn test_unreachable_needs_known_jumps() {
+        // add #8, #0 -> [100], jnz [100], [100], halt, out [100], halt. The indirect jump may
+        // reach address 8 since a word of the image holds it.
+        let program = Program::from_input("1101,8,0,100,5,100,100,99,4,100,99");
+        assert_eq!(program.lint().lints, vec![]);
+
+        let program = Program::from_input("3,100,5,100,100,99,4,100,99");
+        assert_eq!(program.lint().to_string(), "0006: warning[unreachable]: 2 instructions at 0006-0008 are never reached\n");
+
+        // Execution runs into a word that is patched to a halt, so anything after it may run
+        let report = Program::from_input("1001,4,1,4,98,4,0,99").lint();
+        assert_eq!(report.to_string(), "\
+0000: warning[self-modifying]: add [4], #1 -> [4] writes to 4, which does not decode but runs after the instruction at 0000
+");
+    }
+
+    #[test]
+    fn test_puzzle_inputs() {
+        // Jumps with a condition in memory may never be taken, so day 11 only gets a warning
+        let report = Program::from_input(include_str!("../../day11/input")).lint();
+        assert!(report.lints.iter().any(|lint| lint.kind == LintKind::BadJumpTarget));
+        assert_eq!(report.severity(), Some(Severity::Warning));
+
+        for input in &[include_str!("../../day02/input"), include_str!("../../day05/input"),
+                       include_str!("../../day07/input"), include_str!("../../day09/input")] {
+            assert!(Program::from_input(input).lint().severity() < Some(Severity::Error));
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let report = Program::from_input("1105,1,-1,99").lint();
+        assert_eq!(report.to_json(), "\
+{
+  \"lints\": [
+    {\"addr\": 0, \"severity\": \"error\", \"code\": \"bad-jump-target\", \"message\": \"jnz #1, #-1 jumps to -1, a negative address\"}
+  ]
+}
+");
+    }
+}
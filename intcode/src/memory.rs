@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use crate::opcode::{Imm, Pos};
+
+/// Backing store for the emulator memory. Every address is readable and reads of memory that has
+/// never been written return 0.
+pub trait Memory: Default {
+    /// Read the value at the given address
+    fn read(&self, address: Pos) -> Imm;
+
+    /// Write a value to the given address
+    fn write(&mut self, address: Pos, value: Imm);
+
+    /// Returns the backed regions of memory as (start address, values), sorted by address.
+    /// Addresses outside of these regions read as 0.
+    fn regions(&self) -> Vec<(Pos, &[Imm])>;
+
+    /// Create a memory holding `image` starting at address 0
+    fn load(image: &[Imm]) -> Self {
+        let mut memory = Self::default();
+        for (address, &value) in image.iter().enumerate() {
+            memory.write(address, value);
+        }
+        memory
+    }
+}
+
+/// Flat memory, grown to cover the highest address written.
+///
+/// Fastest option, but a single write to a huge address allocates everything below it.
+impl Memory for Vec<Imm> {
+    fn read(&self, address: Pos) -> Imm {
+        self.get(address).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, address: Pos, value: Imm) {
+        if address >= self.len() {
+            debug!("Resizing to {}\n", address + 1);
+            self.resize(address + 1, 0);
+        }
+        self[address] = value;
+    }
+
+    fn regions(&self) -> Vec<(Pos, &[Imm])> {
+        vec![(0, self.as_slice())]
+    }
+
+    fn load(image: &[Imm]) -> Self {
+        image.to_vec()
+    }
+}
+
+/// Number of cells in a page of [`PagedMemory`]
+pub const PAGE_SIZE: usize = 1024;
+
+/// Pages below this page number are looked up directly by index instead of through the hash map
+const DIRECT_PAGES: usize = 1024;
+
+type Page = Box<[Imm; PAGE_SIZE]>;
+
+/// Sparse memory made of zero-filled pages that are allocated on first write.
+///
+/// Memory use is bounded by the number of distinct pages written, not by the highest address.
+/// The first `DIRECT_PAGES` pages, where code and most data live, are kept in a `Vec` indexed by
+/// page number so the common case avoids hashing.
+#[derive(Debug, Clone, Default)]
+pub struct PagedMemory {
+    /// Pages with a page number below `DIRECT_PAGES`
+    direct: Vec<Option<Page>>,
+
+    /// Pages with a page number of `DIRECT_PAGES` or above, keyed by page number
+    far: HashMap<usize, Page>
+}
+
+impl PagedMemory {
+    /// Number of pages currently allocated
+    pub fn allocated_pages(&self) -> usize {
+        self.direct.iter().filter(|page| page.is_some()).count() + self.far.len()
+    }
+
+    fn page(&self, number: usize) -> Option<&Page> {
+        if number < DIRECT_PAGES {
+            self.direct.get(number).and_then(|page| page.as_ref())
+        } else {
+            self.far.get(&number)
+        }
+    }
+
+    fn page_mut(&mut self, number: usize) -> &mut Page {
+        if number < DIRECT_PAGES {
+            if number >= self.direct.len() {
+                self.direct.resize_with(number + 1, || None);
+            }
+            self.direct[number].get_or_insert_with(|| Box::new([0; PAGE_SIZE]))
+        } else {
+            self.far.entry(number).or_insert_with(|| Box::new([0; PAGE_SIZE]))
+        }
+    }
+}
+
+impl Memory for PagedMemory {
+    fn read(&self, address: Pos) -> Imm {
+        match self.page(address / PAGE_SIZE) {
+            Some(page) => page[address % PAGE_SIZE],
+            None => 0
+        }
+    }
+
+    fn write(&mut self, address: Pos, value: Imm) {
+        let page = self.page_mut(address / PAGE_SIZE);
+        page[address % PAGE_SIZE] = value;
+    }
+
+    fn regions(&self) -> Vec<(Pos, &[Imm])> {
+        let mut regions: Vec<(Pos, &[Imm])> = self.direct.iter().enumerate()
+            .filter_map(|(number, page)| page.as_ref().map(|page| (number, page)))
+            .chain(self.far.iter().map(|(&number, page)| (number, page)))
+            .map(|(number, page)| (number * PAGE_SIZE, &page[..]))
+            .collect();
+        regions.sort_by_key(|&(start, _)| start);
+        regions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paged_reads_default_to_zero() {
+        let memory = PagedMemory::default();
+        assert_eq!(memory.read(0), 0);
+        assert_eq!(memory.read(usize::MAX), 0);
+        assert_eq!(memory.allocated_pages(), 0);
+    }
+
+    #[test]
+    fn test_paged_far_writes_are_bounded() {
+        let mut memory = PagedMemory::load(&[1, 2, 3]);
+        memory.write(1 << 40, 5);
+        memory.write(isize::MAX as usize, 6);
+        assert_eq!(memory.allocated_pages(), 3);
+        assert_eq!(memory.read(1), 2);
+        assert_eq!(memory.read(1 << 40), 5);
+        assert_eq!(memory.read((1 << 40) + 1), 0);
+        assert_eq!(memory.read(isize::MAX as usize), 6);
+
+        let starts: Vec<Pos> = memory.regions().iter().map(|&(start, _)| start).collect();
+        assert_eq!(starts, vec![0, 1 << 40, (isize::MAX as usize) / PAGE_SIZE * PAGE_SIZE]);
+    }
+
+    #[test]
+    fn test_flat_memory_grows() {
+        let mut memory: Vec<Imm> = Memory::load(&[1, 2, 3]);
+        assert_eq!(Memory::read(&memory, 10), 0);
+        memory.write(10, 4);
+        assert_eq!(memory.len(), 11);
+        assert_eq!(Memory::read(&memory, 10), 4);
+    }
+}
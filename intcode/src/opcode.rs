@@ -0,0 +1,253 @@
+use crate::error::ErrorKind;
+
+/// Immediate parameter
+pub type Imm = isize;
+
+/// Position parameter
+pub type Pos = usize;
+
+/// How a parameter of an instruction should be interpretted
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Mode {
+    /// Parameter is an address in memory
+    Positional(Pos),
+
+    /// Parameter is the value itself
+    Immediate(Imm),
+
+    /// Parameter is an offset from the current relative base
+    Relative(isize)
+}
+
+impl Mode {
+    /// Create a parameter from the mode digit of an instruction and the raw parameter.
+    /// Returns `None` if the mode digit is unknown or a positional address is negative.
+    pub fn new(mode: isize, param: isize) -> Option<Mode> {
+        match mode {
+            0 if param >= 0 => Some(Mode::Positional(param as usize)),
+            1 => Some(Mode::Immediate(param)),
+            2 => Some(Mode::Relative(param)),
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Debug for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Positional(addr) => write!(f, "Pos({})", addr),
+            Mode::Immediate(imm) => write!(f, "Imm({})", imm),
+            Mode::Relative(rel) => write!(f, "Rel({})", rel),
+        }
+    }
+}
+
+/// Assembly syntax: `[addr]` for positional, `#imm` for immediate and `[rb+offset]` for relative
+/// parameters
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Mode::Positional(addr) => write!(f, "[{}]", addr),
+            Mode::Immediate(imm) => write!(f, "#{}", imm),
+            Mode::Relative(0) => write!(f, "[rb]"),
+            Mode::Relative(rel) if rel < 0 => write!(f, "[rb-{}]", rel.unsigned_abs()),
+            Mode::Relative(rel) => write!(f, "[rb+{}]", rel)
+        }
+    }
+}
+
+/// Available opcodes in our computer emulator
+///
+/// Each parameter carries how it should be interpretted via its [`Mode`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    Add(Mode, Mode, Mode),
+    Mul(Mode, Mode, Mode),
+    In(Mode),
+    Out(Mode),
+    JumpNonZero(Mode, Mode),
+    JumpZero(Mode, Mode),
+    LessThan(Mode, Mode, Mode),
+    Equals(Mode, Mode, Mode),
+    AdjustRelativeBase(Mode),
+    Halt
+}
+
+impl Opcode {
+    /// Length of the longest instruction
+    pub const MAX_LEN: usize = 4;
+
+    /// Returns the length of the instruction.
+    ///
+    /// This function is used during the instruction caching in order to determine if a given write
+    /// destination is in an already lifted instruction.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        Opcode::len_of(self.code()).expect("every instruction has a known opcode")
+    }
+
+    /// Length of the instruction whose first word is `word`, or `None` if the opcode is unknown
+    pub fn len_of(word: Imm) -> Option<usize> {
+        match word % 100 {
+            3 | 4 | 9 => Some(2),
+            5 | 6 => Some(3),
+            1 | 2 | 7 | 8 => Some(4),
+            99 => Some(1),
+            _ => None
+        }
+    }
+
+    /// Numeric opcode, the last two digits of the first word
+    fn code(&self) -> Imm {
+        use Opcode::*;
+        match self {
+            Add(_,_,_) => 1,
+            Mul(_,_,_) => 2,
+            In(_) => 3,
+            Out(_) => 4,
+            JumpNonZero(_,_) => 5,
+            JumpZero(_,_) => 6,
+            LessThan(_,_,_) => 7,
+            Equals(_,_,_) => 8,
+            AdjustRelativeBase(_) => 9,
+            Halt => 99
+        }
+    }
+
+    /// Encode the instruction as the words it occupies in memory
+    pub fn encode(&self) -> Vec<Imm> {
+        use Opcode::*;
+        let params = match *self {
+            Add(a, b, c) | Mul(a, b, c) | LessThan(a, b, c) | Equals(a, b, c) => vec![a, b, c],
+            JumpNonZero(a, b) | JumpZero(a, b) => vec![a, b],
+            In(a) | Out(a) | AdjustRelativeBase(a) => vec![a],
+            Halt => vec![]
+        };
+
+        let mut words = vec![self.code()];
+        let mut scale = 100;
+        for param in params {
+            let (mode, value) = match param {
+                Mode::Positional(addr) => (0, addr as Imm),
+                Mode::Immediate(imm) => (1, imm),
+                Mode::Relative(rel) => (2, rel)
+            };
+            words[0] += mode * scale;
+            scale *= 10;
+            words.push(value);
+        }
+        words
+    }
+
+    /// Decode an instruction from the words it occupies in memory, the inverse of
+    /// [`Opcode::encode`]. This is the decoder the VM uses, so the digits above the last mode
+    /// digit are taken as the mode of the third parameter. Fails with
+    /// [`ErrorKind::Overflow`] if `words` ends before the instruction does.
+    pub fn decode(words: &[Imm]) -> Result<Opcode, ErrorKind> {
+        use Opcode::*;
+        let word = *words.first().ok_or(ErrorKind::Overflow)?;
+        let modes = [word / 100 % 10, word / 1000 % 10, word / 10000];
+        let param = |index: usize| {
+            let param = *words.get(index).ok_or(ErrorKind::Overflow)?;
+            let mode = modes[index - 1];
+            if mode == 0 && param < 0 {
+                return Err(ErrorKind::NegativeAddress(param));
+            }
+            Mode::new(mode, param).ok_or(ErrorKind::UnknownMode { param: index as u8, mode })
+        };
+
+        let op = match word % 100 {
+            1 => Add(param(1)?, param(2)?, param(3)?),
+            2 => Mul(param(1)?, param(2)?, param(3)?),
+            3 => In(param(1)?),
+            4 => Out(param(1)?),
+            5 => JumpNonZero(param(1)?, param(2)?),
+            6 => JumpZero(param(1)?, param(2)?),
+            7 => LessThan(param(1)?, param(2)?, param(3)?),
+            8 => Equals(param(1)?, param(2)?, param(3)?),
+            9 => AdjustRelativeBase(param(1)?),
+            99 => Halt,
+            opcode => return Err(ErrorKind::UnknownOpcode(opcode))
+        };
+        Ok(op)
+    }
+
+    /// Assembly mnemonic of the instruction
+    pub fn mnemonic(&self) -> &'static str {
+        use Opcode::*;
+        match self {
+            Add(_,_,_) => "add",
+            Mul(_,_,_) => "mul",
+            In(_) => "in",
+            Out(_) => "out",
+            JumpNonZero(_,_) => "jnz",
+            JumpZero(_,_) => "jz",
+            LessThan(_,_,_) => "lt",
+            Equals(_,_,_) => "eq",
+            AdjustRelativeBase(_) => "arb",
+            Halt => "halt"
+        }
+    }
+}
+
+/// Assembly syntax, for example `add [rb+3], #5 -> [100]`. Destinations follow an arrow.
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Opcode::*;
+        let name = self.mnemonic();
+        match *self {
+            Add(a, b, dest) | Mul(a, b, dest) | LessThan(a, b, dest) | Equals(a, b, dest) => {
+                write!(f, "{} {}, {} -> {}", name, a, b, dest)
+            }
+            In(dest) => write!(f, "{} -> {}", name, dest),
+            Out(a) | AdjustRelativeBase(a) => write!(f, "{} {}", name, a),
+            JumpNonZero(cond, target) | JumpZero(cond, target) => write!(f, "{} {}, {}", name, cond, target),
+            Halt => write!(f, "{}", name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Mode::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Opcode::Add(Relative(3), Immediate(5), Positional(100)).to_string(),
+                   "add [rb+3], #5 -> [100]");
+        assert_eq!(Opcode::In(Relative(-2)).to_string(), "in -> [rb-2]");
+        assert_eq!(Opcode::JumpZero(Relative(0), Immediate(-1)).to_string(), "jz [rb], #-1");
+        assert_eq!(Opcode::Halt.to_string(), "halt");
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(Opcode::Add(Relative(3), Immediate(5), Positional(100)).encode(), vec![1201, 3, 5, 100]);
+        assert_eq!(Opcode::JumpZero(Immediate(0), Relative(-1)).encode(), vec![2106, 0, -1]);
+        assert_eq!(Opcode::Halt.encode(), vec![99]);
+    }
+
+    #[test]
+    fn test_decode() {
+        let add = Opcode::Add(Relative(3), Immediate(5), Positional(100));
+        assert_eq!(Opcode::decode(&add.encode()), Ok(add));
+        assert_eq!(Opcode::decode(&[2106, 0, -1]), Ok(Opcode::JumpZero(Immediate(0), Relative(-1))));
+        assert_eq!(Opcode::decode(&[99]), Ok(Opcode::Halt));
+        assert_eq!(Opcode::decode(&[1101, 1]), Err(ErrorKind::Overflow));
+        assert_eq!(Opcode::decode(&[301, 1, 2, 3]), Err(ErrorKind::UnknownMode { param: 1, mode: 3 }));
+        assert_eq!(Opcode::decode(&[100001, 1, 2, 3]), Err(ErrorKind::UnknownMode { param: 3, mode: 10 }));
+        assert_eq!(Opcode::decode(&[1, -5, 2, 3]), Err(ErrorKind::NegativeAddress(-5)));
+        assert_eq!(Opcode::decode(&[42]), Err(ErrorKind::UnknownOpcode(42)));
+        assert_eq!(Mode::new(0, -5), None);
+    }
+
+    #[test]
+    fn test_len_of() {
+        let add = Opcode::Add(Relative(3), Immediate(5), Positional(100));
+        assert_eq!(Opcode::len_of(add.encode()[0]), Some(add.len()));
+        assert_eq!(Opcode::len_of(1105), Some(3));
+        assert_eq!(Opcode::len_of(99), Some(1));
+        assert_eq!(Opcode::len_of(42), None);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::coverage::Coverage;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::Pos;
+use crate::program::Program;
+
+/// Instruction counts collected while a program runs. The count of each address is kept in the
+/// [`Coverage`] of the same run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Number of instructions executed
+    pub total: u64,
+
+    /// Instructions executed per [`Opcode`](crate::Opcode) variant, keyed by mnemonic
+    pub mix: BTreeMap<&'static str, u64>,
+
+    /// Mnemonic of the instruction currently executing
+    current: Option<&'static str>
+}
+
+/// Percentage of `count` in `total`
+fn share(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    count as f64 * 100.0 / total as f64
+}
+
+impl Profile {
+    /// Sort `(key, count)` pairs by descending count and keep the first `top`
+    fn top<T>(mut counts: Vec<(T, u64)>, top: usize) -> Vec<(T, u64)> {
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts.truncate(top);
+        counts
+    }
+
+    /// Report of the `top` hottest instructions, basic blocks and loops followed by the
+    /// instruction mix. `coverage` holds the counts per address of the same run. `program`
+    /// supplies the code the counts are attributed to, usually the program as it was before it
+    /// ran. Blocks and loops come from [`Program::recover_functions`].
+    pub fn report<I: InputSource, O: OutputSink, M: Memory>(&self, coverage: &Coverage, program: &Program<I, O, M>,
+                                                            top: usize) -> String {
+        let counts = &coverage.hits;
+        let graph = program.recover_functions();
+        let function = |addr: Pos| {
+            graph.function_at(addr).map_or_else(|| "-".to_string(), |function| format!("F{}", function.entry))
+        };
+        let block_hits = |start: Pos| {
+            let block = &graph.cfg.blocks[&start];
+            counts.range(block.start..block.end).map(|(_, &hits)| hits).sum::<u64>()
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(out, "Instructions executed: {}", self.total);
+
+        let _ = writeln!(out, "\nHotspots:\n{:>12}  {:>7}  {:>6}  {:<8}  instruction", "count", "share", "addr",
+                         "function");
+        for (addr, hits) in Profile::top(counts.iter().map(|(&addr, &hits)| (addr, hits)).collect(), top) {
+            let text = program.decode(addr).map_or_else(|_| "?".to_string(), |op| op.to_string());
+            let _ = writeln!(out, "{:>12}  {:>6.2}%  {:06}  {:<8}  {}", hits, share(hits, self.total), addr,
+                             function(addr), text);
+        }
+
+        let blocks: Vec<(Pos, u64)> = graph.cfg.blocks.keys().map(|&start| (start, block_hits(start)))
+            .filter(|&(_, hits)| hits > 0)
+            .collect();
+        let recovered: u64 = blocks.iter().map(|(_, hits)| hits).sum();
+        let _ = writeln!(out, "\nBlocks:\n{:>12}  {:>7}  {:<13}  function", "count", "share", "block");
+        for (start, hits) in Profile::top(blocks, top) {
+            let block = format!("{:06}-{:06}", start, graph.cfg.blocks[&start].end);
+            let _ = writeln!(out, "{:>12}  {:>6.2}%  {:<13}  {}", hits, share(hits, self.total), block,
+                             function(start));
+        }
+        if recovered < self.total {
+            let _ = writeln!(out, "{:>12}  {:>6.2}%  outside the recovered code", self.total - recovered,
+                             share(self.total - recovered, self.total));
+        }
+
+        // Loops are keyed by header, the count of the header is the number of times it was entered
+        let mut loops = Vec::new();
+        for &entry in graph.functions.keys() {
+            for (header, body) in graph.loops(entry) {
+                let hits: u64 = body.iter().map(|&start| block_hits(start)).sum();
+                if hits > 0 {
+                    loops.push(((header, entry, body.len()), hits));
+                }
+            }
+        }
+        let _ = writeln!(out, "\nLoops:\n{:>12}  {:>7}  {:>6}  {:<8}  {:>6}  {:>10}", "count", "share", "header",
+                         "function", "blocks", "iterations");
+        for ((header, entry, blocks), hits) in Profile::top(loops, top) {
+            let iterations = counts.get(&header).copied().unwrap_or(0);
+            let _ = writeln!(out, "{:>12}  {:>6.2}%  {:06}  {:<8}  {:>6}  {:>10}", hits, share(hits, self.total),
+                             header, format!("F{}", entry), blocks, iterations);
+        }
+
+        let _ = writeln!(out, "\nInstruction mix:");
+        let mix = self.mix.iter().map(|(&mnemonic, &count)| (mnemonic, count)).collect();
+        for (mnemonic, count) in Profile::top(mix, usize::MAX) {
+            let bar = "#".repeat((share(count, self.total) / 2.0).round() as usize);
+            let line = format!("{:<6}{:>12}  {:>6.2}%  {}", mnemonic, count, share(count, self.total), bar);
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        out
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Count every executed instruction by [`Opcode`](crate::Opcode) variant. The counts are
+    /// collected in [`Program::profile`], see [`Profile::report`]. This turns on
+    /// [`Program::with_coverage`] as well, which counts the instructions by address.
+    pub fn with_profile(mut self) -> Self {
+        self.profile = Some(Profile::default());
+        if self.coverage.is_none() {
+            self.coverage = Some(Coverage::default());
+        }
+        self
+    }
+
+    /// Start profiling the instruction at the current IP
+    pub(crate) fn begin_profile(&mut self) {
+        if self.profile.is_none() {
+            return;
+        }
+
+        let ip = self.ip;
+        let op = self.instructions.get(ip).copied().map_or_else(|| self.decode(ip), Ok);
+        if let Some(profile) = &mut self.profile {
+            profile.current = op.ok().map(|op| op.mnemonic());
+        }
+    }
+
+    /// Count the current instruction if it executed
+    pub(crate) fn end_profile(&mut self, changed: bool) {
+        let profile = match &mut self.profile {
+            Some(profile) => profile,
+            None => return
+        };
+        let mnemonic = match profile.current.take() {
+            Some(mnemonic) if changed => mnemonic,
+            _ => return
+        };
+
+        profile.total += 1;
+        *profile.mix.entry(mnemonic).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Calls a function that counts its input down to zero, then outputs 7
+    /// 0: arb #50, in -> [rb+1], add #11, #0 -> [rb], jnz #1, #14, out #7, halt
+    /// 14: add [rb+1], #-1 -> [rb+1], jnz [rb+1], #14, jnz #1, [rb]
+    const COUNTDOWN: &str = "109,50,203,1,21101,11,0,0,1105,1,14,104,7,99,21201,1,-1,1,1205,1,14,2105,1,0";
+
+    fn profile(inputs: &[isize]) -> (Program, Profile, Coverage) {
+        let program = Program::from_input(COUNTDOWN);
+        let mut run = program.clone().with_profile();
+        run.input.extend(inputs);
+        run.run().unwrap();
+        (program, run.profile.unwrap(), run.coverage.unwrap())
+    }
+
+    #[test]
+    fn test_counts() {
+        let (_, profile, coverage) = profile(&[3]);
+        assert_eq!(profile.total, 13);
+        assert_eq!(coverage.hits.get(&14), Some(&3));
+        assert_eq!(coverage.hits.get(&18), Some(&3));
+        assert_eq!(coverage.hits.values().sum::<u64>(), profile.total);
+        assert_eq!(profile.mix.get("add"), Some(&4));
+        assert_eq!(profile.mix.get("jnz"), Some(&5));
+        assert_eq!(profile.mix.values().sum::<u64>(), profile.total);
+    }
+
+    #[test]
+    fn test_report() {
+        let (program, profile, coverage) = profile(&[3]);
+        assert_eq!(profile.report(&coverage, &program, 2), "\
+Instructions executed: 13
+
+Hotspots:
+       count    share    addr  function  instruction
+           3   23.08%  000014  F14       add [rb+1], #-1 -> [rb+1]
+           3   23.08%  000018  F14       jnz [rb+1], #14
+
+Blocks:
+       count    share  block          function
+           6   46.15%  000014-000021  F14
+           4   30.77%  000000-000011  F0
+
+Loops:
+       count    share  header  function  blocks  iterations
+           6   46.15%  000014  F14            1           3
+
+Instruction mix:
+jnz              5   38.46%  ###################
+add              4   30.77%  ###############
+arb              1    7.69%  ####
+halt             1    7.69%  ####
+in               1    7.69%  ####
+out              1    7.69%  ####
+");
+    }
+
+    #[test]
+    fn test_self_modified_code() {
+        // Code patched at runtime is attributed to no block
+        let program = Program::from_input("1101,1105,0,4,0,1,8,0,99");
+        let mut run = program.clone().with_profile().with_jit();
+        run.run().unwrap();
+        let report = run.profile.as_ref().unwrap().report(run.coverage.as_ref().unwrap(), &program, 10);
+        assert!(report.contains("           2   66.67%  outside the recovered code\n"));
+    }
+}
@@ -0,0 +1,823 @@
+use std::collections::VecDeque;
+
+use crate::cache::InstructionCache;
+use crate::calls::Frame;
+use crate::coverage::Coverage;
+use crate::error::{ErrorKind, ExitReason, ParseError, VmError};
+use crate::event::Event;
+use crate::io::{InputSource, OutputSink};
+use crate::jit::Jit;
+use crate::journal::Journal;
+use crate::memory::{Memory, PagedMemory};
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::opcode::Mode::*;
+use crate::profile::Profile;
+use crate::trace::Tracer;
+
+#[derive(Debug, Clone)]
+/// Program struct containing the current state of the emulator
+///
+/// The emulator is generic over where `In` reads from and where `Out` writes to. By default input
+/// is a `VecDeque` and output is collected into a `Vec`. Memory is generic over its backing store
+/// and defaults to the sparse [`PagedMemory`].
+pub struct Program<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Instruction Pointer
+    pub ip: usize,
+
+    /// Current memory in the emulator
+    pub memory: M,
+
+    /// Lifted instructions to be executed in the emulator, keyed by IP of the instruction
+    pub instructions: InstructionCache,
+
+    /// Source of values for `In` instructions
+    pub input: I,
+
+    /// Destination of values from `Out` instructions
+    pub output: O,
+
+    /// VM has halted
+    pub halted: bool,
+
+    /// Current relative address
+    pub relative_base: isize,
+
+    /// Undo journal for reverse execution, if enabled
+    pub journal: Option<Journal>,
+
+    /// Shadow call stack, innermost frame last, if enabled
+    pub call_stack: Option<Vec<Frame>>,
+
+    /// Native code compiler, if enabled
+    pub jit: Option<Jit>,
+
+    /// Execution trace recorder, if enabled
+    pub trace: Option<Tracer>,
+
+    /// Code coverage counts, if enabled
+    pub coverage: Option<Coverage>,
+
+    /// Instruction counts of the profiler, if enabled
+    pub profile: Option<Profile>
+}
+
+impl Program {
+    /// Parse a comma separated program, panicking on malformed text. See [`Program::parse`].
+    pub fn from_input(input: &str) -> Program {
+        Program::parse(input).unwrap_or_else(|err| panic!("Error parsing: {}", err))
+    }
+
+    /// Parse a comma separated program. Line breaks and empty values are ignored.
+    pub fn parse(input: &str) -> Result<Program, ParseError> {
+        // Remove new lines from input string
+        let input = input.replace("\r", "").replace("\n", "");
+
+        let image = input.split(',')
+                         // Ignore empty strings from split
+                         .filter(|x| !x.is_empty())
+                         .enumerate()
+                         // Parse ints as isize
+                         .map(|(index, x)| x.trim().parse::<isize>()
+                                            .map_err(|_| ParseError { index, text: x.trim().to_string() }))
+                         // Collect into Vec<isize>
+                         .collect::<Result<Vec<isize>, _>>()?;
+
+        Ok(Program::from_image(&image))
+    }
+
+    /// Generate a program with the image loaded at address 0
+    pub fn from_image(image: &[Imm]) -> Program {
+        Program {
+            ip: 0,
+            memory: PagedMemory::load(image),
+            instructions: InstructionCache::default(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+            halted: false,
+            relative_base: 0,
+            journal: None,
+            call_stack: None,
+            jit: None,
+            trace: None,
+            coverage: None,
+            profile: None
+        }
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Replace the input source and output sink of the emulator, keeping the rest of the state
+    pub fn with_io<I2: InputSource, O2: OutputSink>(self, input: I2, output: O2) -> Program<I2, O2, M> {
+        Program {
+            ip: self.ip,
+            memory: self.memory,
+            instructions: self.instructions,
+            input,
+            output,
+            halted: self.halted,
+            relative_base: self.relative_base,
+            journal: self.journal,
+            call_stack: self.call_stack,
+
+            // Compiled code calls back into the old types
+            jit: self.jit.map(|_| Jit::default()),
+            trace: self.trace,
+            coverage: self.coverage,
+            profile: self.profile
+        }
+    }
+
+    /// Copy the contents of memory into a different backing store, keeping the rest of the state
+    pub fn with_memory<M2: Memory>(self) -> Program<I, O, M2> {
+        let mut memory = M2::default();
+        for (start, values) in self.memory.regions() {
+            for (offset, &value) in values.iter().enumerate() {
+                if value != 0 {
+                    memory.write(start + offset, value);
+                }
+            }
+        }
+
+        Program {
+            ip: self.ip,
+            memory,
+            instructions: self.instructions,
+            input: self.input,
+            output: self.output,
+            halted: self.halted,
+            relative_base: self.relative_base,
+            journal: self.journal,
+            call_stack: self.call_stack,
+
+            // Compiled code calls back into the old types
+            jit: self.jit.map(|_| Jit::default()),
+            trace: self.trace,
+            coverage: self.coverage,
+            profile: self.profile
+        }
+    }
+
+    /// Print the current memory state of the emulator
+    pub fn print(&self) {
+        println!("IP: {:06}", self.ip);
+        let chunk_size = 0x8;
+        for (start, values) in self.memory.regions() {
+            for (i, bytes) in values.chunks(chunk_size).enumerate() {
+                print!("{:06} ", start + i*chunk_size);
+                for b in bytes {
+                    print!("{:07} ", b);
+                }
+                println!();
+            }
+        }
+    }
+
+    /// Lift the instruction at the given address and cache it. Returns an error if an unknown
+    /// opcode or parameter mode is found.
+    pub fn lift(&mut self, addr: Pos) -> Result<Opcode, VmError> {
+        let op = self.decode(addr)?;
+        self.instructions.insert(addr, op);
+        Ok(op)
+    }
+
+    /// Decode the instruction at the given address without caching it
+    pub fn decode(&self, addr: Pos) -> Result<Opcode, VmError> {
+        // Words past the end of the address space are left out, so decoding reports an overflow
+        let mut words = [0; Opcode::MAX_LEN];
+        let mut len = 0;
+        for (index, word) in words.iter_mut().enumerate() {
+            match addr.checked_add(index) {
+                Some(addr) => *word = self.read(addr),
+                None => break
+            }
+            len += 1;
+        }
+        debug!("[{}] Lifting {:05} ", addr, words[0]);
+
+        let op = Opcode::decode(&words[..len]).map_err(|kind| {
+            info!("Invalid instruction @ {}: {:?}\n", addr, kind);
+            VmError { ip: addr, opcode: words[0], kind }
+        })?;
+
+        debug!("Lifted [{:4}] {:?}\n", addr, op);
+        Ok(op)
+    }
+
+    /// Build an error for the instruction at the current IP
+    pub(crate) fn fault(&self, kind: ErrorKind) -> VmError {
+        VmError {
+            ip: self.ip,
+            opcode: self.read(self.ip),
+            kind
+        }
+    }
+
+    /// Get the absolute address of a relative parameter
+    pub(crate) fn relative(&self, rel_offset: isize) -> Result<Pos, VmError> {
+        match self.relative_base.checked_add(rel_offset) {
+            Some(addr) if addr >= 0 => Ok(addr as usize),
+            Some(addr) => Err(self.fault(ErrorKind::NegativeAddress(addr))),
+            None => Err(self.fault(ErrorKind::Overflow))
+        }
+    }
+
+    /// Get the value of a parameter, reading memory for positional and relative parameters
+    fn value(&mut self, param: Mode) -> Result<Imm, VmError> {
+        match param {
+            Positional(addr) => Ok(self.read(addr)),
+            Immediate(imm) => Ok(imm),
+            Relative(rel_offset) => {
+                let addr = self.relative(rel_offset)?;
+                Ok(self.read(addr))
+            }
+        }
+    }
+
+    /// Get the address a destination parameter points to
+    fn address(&self, param: Mode) -> Result<Pos, VmError> {
+        match param {
+            Positional(addr) => Ok(addr),
+            Immediate(_imm) => Err(self.fault(ErrorKind::ImmediateDestination)),
+            Relative(rel_offset) => self.relative(rel_offset)
+        }
+    }
+
+    /// Get the new IP for a taken jump
+    fn jump_target(&self, target: Imm) -> Result<Pos, VmError> {
+        if target < 0 {
+            return Err(self.fault(ErrorKind::InvalidJumpTarget(target)));
+        }
+        Ok(target as usize)
+    }
+
+    /// Execute exactly one instruction.
+    ///
+    /// The emulator will see if the current instruction has been lifted already. If not, attempt
+    /// to lift the instruction. If so, use the previously lifted instruction.
+    ///
+    /// An `In` with an empty input buffer and a `Halt` leave the IP where it is, so stepping again
+    /// returns the same event. Any fault leaves the IP on the faulting instruction.
+    pub fn step(&mut self) -> Result<Event, VmError> {
+        let halted = self.halted;
+        let ip = self.ip;
+        self.begin_record();
+        self.begin_trace();
+        self.begin_profile();
+        let result = self.execute();
+        let changed = match result {
+            Ok(Event::Executed) | Ok(Event::Output(_)) => true,
+            Ok(Event::Halted) => !halted,
+            Ok(Event::NeedsInput) | Err(_) => false
+        };
+        self.end_profile(changed);
+        self.end_trace(changed);
+        self.end_record(changed);
+        if changed {
+            self.track_call(ip);
+            self.track_coverage(ip);
+        }
+        result
+    }
+
+    /// Execute the instruction at the current IP without journaling it
+    fn execute(&mut self) -> Result<Event, VmError> {
+        let opcode = match self.instructions.get(self.ip) {
+            // Seen this opcode already, attempt to emulate it
+            Some(op) => *op,
+
+            // Haven't seen this opcode yet, attempt to lift it from memory
+            None => self.lift(self.ip)?
+        };
+        info!("Executing: {:?}\n", opcode);
+        match opcode {
+            Opcode::Add(param1, param2, dest) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                let dest = self.address(dest)?;
+
+                let result = value1.checked_add(value2)
+                                   .ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                debug!("Add: {} = {} + {} ({})\n", dest, value1, value2, result);
+                self.write(dest, result);
+                self.ip += 4;
+            }
+            Opcode::Mul(param1, param2, dest) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                let dest = self.address(dest)?;
+
+                let result = value1.checked_mul(value2)
+                                   .ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                debug!("Mul: [{}] = {} * {} ({})\n", dest, value1, value2, result);
+                self.write(dest, result);
+                self.ip += 4;
+            }
+            Opcode::In(dest) => {
+                let dest = self.address(dest)?;
+                let input_val = match self.read_input() {
+                    Some(val) => val,
+                    None => {
+                        info!("In without any input.. breaking\n");
+                        return Ok(Event::NeedsInput);
+                    }
+                };
+
+                info!("In: [{}] = {}\n", dest, input_val);
+                self.write(dest, input_val);
+                self.ip += 2;
+            }
+            Opcode::Out(value) => {
+                let value = self.value(value)?;
+                debug!("Out: output.push({})\n", value);
+                self.write_output(value);
+                self.ip += 2;
+                return Ok(Event::Output(value));
+            }
+            Opcode::JumpNonZero(param1, param2) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                debug!("JumpNonZero: if {} is nonzero, jmp to {}\n", value1, value2);
+                if value1 != 0 {
+                    debug!("   ip = {}\n", value2);
+                    self.ip = self.jump_target(value2)?;
+                } else {
+                    debug!("   ip += 3\n");
+                    self.ip += 3;
+                }
+            }
+            Opcode::JumpZero(param1, param2) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                debug!("JumpZero: if {} is zero, jmp to {}\n", value1, value2);
+                if value1 == 0 {
+                    debug!("   ip = {}\n", value2);
+                    self.ip = self.jump_target(value2)?;
+                } else {
+                    debug!("   ip += 3\n");
+                    self.ip += 3;
+                }
+            }
+            Opcode::LessThan(param1, param2, dest) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                let dest = self.address(dest)?;
+
+                debug!("LessThan: if {} < {}, [{}] = 1 else [{}] = 0\n", value1, value2, dest, dest);
+                let value = if value1 < value2 { 1 } else { 0 };
+                self.write(dest, value);
+                self.ip += 4;
+            }
+            Opcode::Equals(param1, param2, dest) => {
+                let value1 = self.value(param1)?;
+                let value2 = self.value(param2)?;
+                let dest = self.address(dest)?;
+
+                debug!("Equals: if {} == {}, [{}] = 1 else [{}] = 0\n", value1, value2, dest, dest);
+                let value = if value1 == value2 { 1 } else { 0 };
+                self.write(dest, value);
+                self.ip += 4;
+            }
+            Opcode::AdjustRelativeBase(offset) => {
+                let offset = self.value(offset)?;
+                let new_base = self.relative_base.checked_add(offset)
+                                                 .ok_or_else(|| self.fault(ErrorKind::Overflow))?;
+                info!("New relative base: {} = {} + {}\n", new_base, self.relative_base, offset);
+                self.relative_base = new_base;
+                self.ip += 2;
+            }
+            Opcode::Halt => {
+                self.halted = true;
+                return Ok(Event::Halted);
+            }
+        }
+
+        Ok(Event::Executed)
+    }
+
+    /// Execute until the program outputs a value, needs input or halts
+    ///
+    /// Straight-line code runs a whole block per dispatch, or as native code if the JIT is
+    /// enabled with [`Program::with_jit`]. The instruction ending each block is executed with
+    /// `step`.
+    pub fn run_until_output(&mut self) -> Result<Event, VmError> {
+        loop {
+            self.run_native();
+            self.execute_block(u64::MAX)?;
+            match self.step()? {
+                Event::Executed => {}
+                event => return Ok(event)
+            }
+        }
+    }
+
+    /// Execute until the program needs input or halts. Outputs are written to the output buffer.
+    pub fn run_until_input(&mut self) -> Result<Event, VmError> {
+        loop {
+            self.run_native();
+            self.execute_block(u64::MAX)?;
+            match self.step()? {
+                Event::Executed | Event::Output(_) => {}
+                event => return Ok(event)
+            }
+        }
+    }
+
+    /// Execute the current program loaded into the emulator.
+    ///
+    /// Execution stops when the program halts or when an `In` instruction finds the input buffer
+    /// empty. In the latter case, `run` can be called again after more input is pushed.
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        match self.run_until_input()? {
+            Event::Halted => Ok(ExitReason::Halted),
+            Event::NeedsInput => Ok(ExitReason::NeedsInput),
+            _ => unreachable!()
+        }
+    }
+
+    /// Write a value to the given address.
+    ///
+    /// Since data and code reside in the same memory, a write could corrupt a cached instruction.
+    /// On each write, there is a check to see if the write corrupts a cached instruction and if
+    /// so, the cached instruction is updated.
+    pub fn write(&mut self, address: Pos, value: Imm) {
+        if let Some(journal) = &mut self.journal {
+            journal.record_write(address, self.memory.read(address));
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.record_write(address, value);
+        }
+        self.memory.write(address, value);
+
+        // A write could overwrite a cached instruction. The cache tracks which addresses are
+        // covered by lifted instructions, so this check is constant time.
+        let mut modified = [None; Opcode::MAX_LEN];
+        for (slot, start) in modified.iter_mut().zip(self.instructions.containing(address)) {
+            *slot = Some(start);
+        }
+
+        // If this write modified an instruction, attempt to lift the new instruction:
+        // * If the modified instruction is still a valid instruction, update the cache.
+        // * If the modified instruction results in an invalid instruction, invalidate the cache.
+        for &start in modified.iter().flatten() {
+            let old_op = self.instructions.remove(start);
+            match self.lift(start) {
+                Ok(new_op) => {
+                    info!("[{}] {:?} -> {:?} -- New instruction\n", start, old_op, new_op);
+                }
+                Err(err) => {
+                    info!("[{}] {:?} -> {} -- New instruction is invalid\n", start, old_op, err);
+                }
+            }
+        }
+    }
+
+    /// Read a value from the given address
+    pub fn read(&self, address: Pos) -> Imm {
+        self.memory.read(address)
+    }
+
+    /// Returns the next value from the input source
+    pub fn read_input(&mut self) -> Option<isize> {
+        let value = match &mut self.journal {
+            Some(journal) => {
+                let input = &mut self.input;
+                let value = journal.replay_input().or_else(|| input.read_input())?;
+                journal.record_input(value);
+                value
+            }
+            None => self.input.read_input()?
+        };
+
+        if let Some(trace) = &mut self.trace {
+            trace.record_input(value);
+        }
+        Some(value)
+    }
+
+    /// Write a value to the output sink
+    pub fn write_output(&mut self, value: isize) {
+        if let Some(journal) = &mut self.journal {
+            journal.record_output();
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.record_output(value);
+        }
+        self.output.write_output(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day2() {
+        let input = "1,9,10,3,2,3,11,0,99,30,40,50";
+        let mut program = Program::from_input(input);
+        program.run().unwrap();
+        assert_eq!(program.read(0), 3500);
+    }
+
+    #[test]
+    fn test_decode_end_of_memory() {
+        let mut program = Program::from_input("99");
+        program.write(usize::MAX - 1, 1101);
+        assert_eq!(program.decode(usize::MAX - 1),
+                   Err(VmError { ip: usize::MAX - 1, opcode: 1101, kind: ErrorKind::Overflow }));
+    }
+
+    #[test]
+    fn test_parse() {
+        let program = Program::parse("1,9,10,3,\n2,3,11,0,99,30,40,50\n").unwrap();
+        assert_eq!(program.read(4), 2);
+        assert_eq!(Program::parse("1,2,x").unwrap_err(), ParseError { index: 2, text: "x".to_string() });
+        assert_eq!(Program::parse("1, 2 ,,3").unwrap().read(2), 3);
+    }
+
+    #[test]
+    fn test_day5_position_equals_to_8() {
+        // Checks if input (1) == 8
+        let input = "3,9,8,9,10,9,4,9,99,-1,8";
+        let mut program = Program::from_input(input);
+        program.input.push_back(1);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 0);
+
+        // Checks if input (8) == 8
+        let mut program = Program::from_input(input);
+        program.input.push_back(8);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 1);
+    }
+
+    #[test]
+    fn test_imm_equals_to_8() {
+        // Checks if input (1) == 8
+        let input = "3,3,1108,-1,8,3,4,3,99";
+        let mut program = Program::from_input(input);
+        program.input.push_back(1);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 0);
+
+        // Checks if input (8) == 8
+        let mut program = Program::from_input(input);
+        program.input.push_back(8);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 1);
+    }
+
+    #[test]
+    fn test_day5_position_lessthan_to_8() {
+        // Checks if input (1) < 8
+        let input = "3,9,7,9,10,9,4,9,99,-1,8";
+        let mut program = Program::from_input(input);
+        program.input.push_back(1);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 1);
+
+        // Checks if input (8) < 8
+        let mut program = Program::from_input(input);
+        program.input.push_back(8);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 0);
+    }
+
+    #[test]
+    fn test_day5_imm_lessthan_to_8() {
+        // Checks if input (1) < 8
+        let input = "3,3,1107,-1,8,3,4,3,99";
+        let mut program = Program::from_input(input);
+        program.input.push_back(1);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 1);
+
+        // Checks if input (8) < 8
+        let mut program = Program::from_input(input);
+        program.input.push_back(8);
+        program.run().unwrap();
+        assert!(!program.output.is_empty());
+        assert_eq!(program.output[0], 0);
+    }
+
+    #[test]
+    fn test_day5_larger_example() {
+        // Output:
+        // 999  if input <  8
+        // 1000 if input == 8
+        // 1001 if input >  8
+        let input = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                     1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                     999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+        let mut program = Program::from_input(input);
+        program.input.push_back(2);
+        program.run().unwrap();
+        assert_eq!(program.output[0], 999);
+
+        let mut program = Program::from_input(input);
+        program.input.push_back(8);
+        program.run().unwrap();
+        assert_eq!(program.output[0], 1000);
+
+        let mut program = Program::from_input(input);
+        program.input.push_back(123);
+        program.run().unwrap();
+        assert_eq!(program.output[0], 1001);
+    }
+
+    #[test]
+    fn test_day5_loop_and_cache() {
+        // Test loop
+        // In(9)                      // Read the counter into address 9
+        // OutA(9)                    // Write the counter to the screen
+        // AddAIA(9, -1, 9)           // Subtract 1 from the counter
+        // JumpNonZeroII(counter, 2)  // Loop back to the second instruction if we aren't at zero
+        // Halt
+        let input = "3,9,\
+                     004,9,\
+                     01001,9,-1,9,\
+                     1105,112233,2,\
+                     99";
+
+        let mut program = Program::from_input(input);
+        program.input.push_back(10);
+        program.run().unwrap();
+        assert_eq!(program.output, vec![10,9,8,7,6,5,4,3,2,1]);
+    }
+
+    #[test]
+    fn test_self_modifying_write() {
+        // Add(Imm(4), Imm(0), Pos(4))  // Overwrite the cached Halt at 4 with Out(Pos(0))
+        // Halt                         // Becomes Out(Pos(0))
+        // Halt
+        let input = "1101,4,0,4,99,0,99";
+        let mut program = Program::from_input(input);
+        program.lift(4).unwrap();
+        program.run().unwrap();
+        assert_eq!(program.output, vec![1101]);
+    }
+
+    #[test]
+    fn test_overlapping_instructions_invalidated() {
+        // Jump into the middle of an instruction so two overlapping instructions get lifted, then
+        // overwrite the shared word
+        let mut program = Program::from_input("1,1,1,1,99,0");
+        program.lift(0).unwrap();
+        program.lift(1).unwrap();
+        assert_eq!(program.instructions.containing(2).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(program.instructions.containing(4).collect::<Vec<_>>(), vec![1]);
+
+        // Out(Pos(1)) at address 0 and an invalid opcode at address 1
+        program.write(0, 4);
+        program.write(1, 42);
+        assert_eq!(program.instructions.get(0), Some(&Opcode::Out(Positional(42))));
+        assert_eq!(program.instructions.get(1), None);
+    }
+
+    #[test]
+    fn test_resume_after_input() {
+        // Echo two inputs, pausing when the input buffer is empty
+        let input = "3,0,4,0,3,0,4,0,99";
+        let mut program = Program::from_input(input);
+        program.input.push_back(7);
+        assert_eq!(program.run(), Ok(ExitReason::NeedsInput));
+        assert!(!program.halted);
+        assert_eq!(program.output, vec![7]);
+
+        program.input.push_back(9);
+        assert_eq!(program.run(), Ok(ExitReason::Halted));
+        assert!(program.halted);
+        assert_eq!(program.output, vec![7, 9]);
+    }
+
+    #[test]
+    fn test_step_events() {
+        // In(Pos(0)), Out(Pos(0)), Halt
+        let mut program = Program::from_input("3,0,4,0,99");
+        assert_eq!(program.step(), Ok(Event::NeedsInput));
+        assert_eq!(program.ip, 0);
+
+        program.input.push_back(42);
+        assert_eq!(program.step(), Ok(Event::Executed));
+        assert_eq!(program.step(), Ok(Event::Output(42)));
+        assert_eq!(program.step(), Ok(Event::Halted));
+        assert_eq!(program.step(), Ok(Event::Halted));
+        assert_eq!(program.ip, 4);
+    }
+
+    #[test]
+    fn test_run_until_output_and_input() {
+        // Out(Imm(1)), Out(Imm(2)), In(Pos(0)), Halt
+        let mut program = Program::from_input("104,1,104,2,3,0,99");
+        assert_eq!(program.run_until_output(), Ok(Event::Output(1)));
+        assert_eq!(program.run_until_output(), Ok(Event::Output(2)));
+        assert_eq!(program.run_until_output(), Ok(Event::NeedsInput));
+
+        let mut program = Program::from_input("104,1,104,2,3,0,99");
+        assert_eq!(program.run_until_input(), Ok(Event::NeedsInput));
+        assert_eq!(program.output, vec![1, 2]);
+        program.input.push_back(0);
+        assert_eq!(program.run_until_input(), Ok(Event::Halted));
+    }
+
+    #[test]
+    fn test_far_writes_stay_sparse() {
+        // Write to and output from addresses far beyond the program image
+        // Add(Imm(7), Imm(0), Pos(1 << 40)), Out(Pos(1 << 40)), AdjustRelativeBase(Imm(1 << 50)),
+        // Out(Rel(0)), Halt
+        let input = format!("1101,7,0,{0},4,{0},109,{1},204,0,99", 1isize << 40, 1isize << 50);
+        let mut program = Program::from_input(&input);
+        program.run().unwrap();
+        assert_eq!(program.output, vec![7, 0]);
+        assert_eq!(program.memory.allocated_pages(), 2);
+    }
+
+    #[test]
+    fn test_flat_memory() {
+        let input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut program = Program::from_input(input).with_memory::<Vec<isize>>();
+        program.run().unwrap();
+        assert_eq!(program.output, vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99]);
+        assert_eq!(program.memory.len(), 102);
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let mut program = Program::from_input("1101,1,1,5,42");
+        let err = program.run().unwrap_err();
+        assert_eq!(err, VmError { ip: 4, opcode: 42, kind: ErrorKind::UnknownOpcode(42) });
+        assert_eq!(program.ip, 4);
+    }
+
+    #[test]
+    fn test_unknown_mode() {
+        let mut program = Program::from_input("30001,0,0,0,99");
+        let err = program.run().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownMode { param: 3, mode: 3 });
+        assert_eq!(err.opcode, 30001);
+    }
+
+    #[test]
+    fn test_immediate_destination() {
+        let mut program = Program::from_input("11101,1,1,0,99");
+        let err = program.run().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ImmediateDestination);
+
+        // In must not consume input when the destination is invalid
+        let mut program = Program::from_input("103,0,99");
+        program.input.push_back(5);
+        assert_eq!(program.run().unwrap_err().kind, ErrorKind::ImmediateDestination);
+        assert_eq!(program.input, vec![5]);
+    }
+
+    #[test]
+    fn test_negative_addresses() {
+        let mut program = Program::from_input("4,-1,99");
+        assert_eq!(program.run().unwrap_err().kind, ErrorKind::NegativeAddress(-1));
+
+        let mut program = Program::from_input("109,-5,204,2,99");
+        let err = program.run().unwrap_err();
+        assert_eq!(err, VmError { ip: 2, opcode: 204, kind: ErrorKind::NegativeAddress(-3) });
+
+        let mut program = Program::from_input("1105,1,-7");
+        assert_eq!(program.run().unwrap_err().kind, ErrorKind::InvalidJumpTarget(-7));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = format!("1102,{},2,0,99", isize::MAX);
+        let mut program = Program::from_input(&input);
+        assert_eq!(program.run().unwrap_err().kind, ErrorKind::Overflow);
+    }
+
+    #[test]
+    fn test_day9_example_1() {
+        let input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut program = Program::from_input(input);
+        program.run().unwrap();
+        assert_eq!(program.output, vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99]);
+    }
+
+    #[test]
+    fn test_day9_example_2() {
+        let input = "1102,34915192,34915192,7,4,7,99,0";
+        let mut program = Program::from_input(input);
+        program.run().unwrap();
+        assert_eq!(program.output[0], 1219070632396864);
+    }
+
+    #[test]
+    fn test_day9_example_3() {
+        let input = "104,1125899906842624,99";
+        let mut program = Program::from_input(input);
+        program.run().unwrap();
+        assert_eq!(program.output[0], 1125899906842624);
+    }
+}
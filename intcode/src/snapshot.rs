@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::cache::InstructionCache;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Pos};
+use crate::program::Program;
+
+/// First bytes of every snapshot
+const MAGIC: &[u8; 8] = b"INTCODE\0";
+
+/// Version written by [`Program::snapshot`]. Bump whenever the layout below changes.
+///
+/// Version 1 layout, all integers little endian:
+///
+/// ```text
+/// magic          8 bytes  "INTCODE\0"
+/// version        u32
+/// ip             u64
+/// relative_base  i64
+/// halted         u8
+/// input          u64 count, then count * i64
+/// output         u64 count, then count * i64
+/// regions        u64 count, then per region: u64 start, u64 count, count * i64
+/// ```
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Reasons a snapshot could not be restored
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading the snapshot failed, including a snapshot that ends early
+    Io(io::Error),
+
+    /// The data does not start with the snapshot magic
+    BadMagic,
+
+    /// The snapshot was written by an unknown version of the format
+    UnsupportedVersion(u32),
+
+    /// A value in the snapshot is out of range
+    Corrupt(&'static str)
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "failed to read snapshot: {}", err),
+            SnapshotError::BadMagic => write!(f, "not an Intcode snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {} (expected {})", version, SNAPSHOT_VERSION)
+            }
+            SnapshotError::Corrupt(what) => write!(f, "corrupt snapshot: {}", what)
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+/// Input sources and output sinks whose unconsumed values can be saved in a snapshot
+pub trait Pending: Sized {
+    /// Values not yet consumed, in order
+    fn pending(&self) -> Vec<isize>;
+
+    /// Rebuild the buffer from the values returned by `pending`
+    fn from_pending(values: Vec<isize>) -> Self;
+}
+
+impl Pending for VecDeque<isize> {
+    fn pending(&self) -> Vec<isize> {
+        self.iter().copied().collect()
+    }
+
+    fn from_pending(values: Vec<isize>) -> Self {
+        values.into()
+    }
+}
+
+impl Pending for Vec<isize> {
+    fn pending(&self) -> Vec<isize> {
+        self.clone()
+    }
+
+    fn from_pending(values: Vec<isize>) -> Self {
+        values
+    }
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_values<W: Write>(writer: &mut W, values: &[Imm]) -> io::Result<()> {
+    write_u64(writer, values.len() as u64)?;
+    for &value in values {
+        writer.write_all(&(value as i64).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<isize, SnapshotError> {
+    let value = read_u64(reader)? as i64;
+    isize::try_from(value).map_err(|_| SnapshotError::Corrupt("value does not fit in isize"))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize, SnapshotError> {
+    usize::try_from(read_u64(reader)?).map_err(|_| SnapshotError::Corrupt("address does not fit in usize"))
+}
+
+/// Read a count followed by that many values, calling `f` with each one
+fn read_values<R: Read>(reader: &mut R, mut f: impl FnMut(isize)) -> Result<(), SnapshotError> {
+    let count = read_u64(reader)?;
+    for _ in 0..count {
+        f(read_i64(reader)?);
+    }
+    Ok(())
+}
+
+impl<I, O, M> Program<I, O, M>
+        where I: InputSource + Pending, O: OutputSink + Pending, M: Memory {
+    /// Serialise the complete VM state to `writer`.
+    ///
+    /// The instruction cache, undo journal, shadow call stack, trace, coverage and profile are not
+    /// saved, the cache is rebuilt as the restored program executes. Memory regions that only hold
+    /// zeros are skipped.
+    pub fn snapshot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        write_u64(&mut writer, self.ip as u64)?;
+        writer.write_all(&(self.relative_base as i64).to_le_bytes())?;
+        writer.write_all(&[self.halted as u8])?;
+        write_values(&mut writer, &self.input.pending())?;
+        write_values(&mut writer, &self.output.pending())?;
+
+        let regions: Vec<(Pos, &[Imm])> = self.memory.regions().into_iter()
+            .filter(|(_, values)| values.iter().any(|&value| value != 0))
+            .collect();
+        write_u64(&mut writer, regions.len() as u64)?;
+        for (start, values) in regions {
+            write_u64(&mut writer, start as u64)?;
+            write_values(&mut writer, values)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Load a VM previously saved with [`Program::snapshot`]
+    pub fn restore<R: Read>(mut reader: R) -> Result<Program<I, O, M>, SnapshotError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let ip = read_usize(&mut reader)?;
+        let relative_base = read_i64(&mut reader)?;
+
+        let mut halted = [0; 1];
+        reader.read_exact(&mut halted)?;
+        let halted = match halted[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(SnapshotError::Corrupt("halted flag is not 0 or 1"))
+        };
+
+        let mut input = Vec::new();
+        read_values(&mut reader, |value| input.push(value))?;
+        let mut output = Vec::new();
+        read_values(&mut reader, |value| output.push(value))?;
+
+        let mut memory = M::default();
+        let regions = read_u64(&mut reader)?;
+        for _ in 0..regions {
+            let start = read_usize(&mut reader)?;
+            let mut address = start;
+            read_values(&mut reader, |value| {
+                if value != 0 {
+                    memory.write(address, value);
+                }
+                address = address.wrapping_add(1);
+            })?;
+            if address < start {
+                return Err(SnapshotError::Corrupt("memory region wraps the address space"));
+            }
+        }
+
+        Ok(Program {
+            ip,
+            memory,
+            instructions: InstructionCache::default(),
+            input: I::from_pending(input),
+            output: O::from_pending(output),
+            halted,
+            relative_base,
+            journal: None,
+            call_stack: None,
+            jit: None,
+            trace: None,
+            coverage: None,
+            profile: None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Event, PagedMemory};
+
+    /// Outputs its input doubled forever, with the relative base moving on every iteration
+    /// In(Pos(100)), Mul(Pos(100), Imm(2), Pos(100)), Out(Pos(100)), AdjustRelativeBase(Imm(1)),
+    /// JumpNonZero(Imm(1), Imm(0))
+    const DOUBLER: &str = "3,100,1002,100,2,100,4,100,109,1,1105,1,0";
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut program = Program::from_input(DOUBLER);
+        program.input.extend(vec![3, 4, 5]);
+        assert_eq!(program.run_until_output(), Ok(Event::Output(6)));
+        program.write(1 << 40, -7);
+
+        let mut data = Vec::new();
+        program.snapshot(&mut data).unwrap();
+        let mut restored: Program = Program::restore(&data[..]).unwrap();
+        assert_eq!(restored.ip, program.ip);
+        assert_eq!(restored.relative_base, program.relative_base);
+        assert_eq!(restored.input, VecDeque::from(vec![4, 5]));
+        assert_eq!(restored.output, vec![6]);
+        assert_eq!(restored.read(1 << 40), -7);
+        assert!(!restored.halted);
+
+        // Both copies continue identically
+        program.run().unwrap();
+        restored.run().unwrap();
+        assert_eq!(restored.output, vec![6, 8, 10]);
+        assert_eq!(restored.output, program.output);
+        assert_eq!(restored.relative_base, 3);
+        assert_eq!(restored.relative_base, program.relative_base);
+    }
+
+    #[test]
+    fn test_snapshot_to_file() {
+        let path = std::env::temp_dir().join(format!("intcode-snapshot-{}", std::process::id()));
+        let mut program = Program::from_input("1101,2,3,5,99,0");
+        program.run().unwrap();
+        program.snapshot(std::fs::File::create(&path).unwrap()).unwrap();
+
+        let restored: Program<VecDeque<isize>, Vec<isize>, Vec<Imm>> =
+            Program::restore(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(restored.halted);
+        assert_eq!(restored.read(5), 5);
+        assert_eq!(restored.memory.len(), 6);
+    }
+
+    #[test]
+    fn test_restore_errors() {
+        let mut data = Vec::new();
+        Program::from_input("99").snapshot(&mut data).unwrap();
+
+        let restore = |data: &[u8]| Program::<VecDeque<isize>, Vec<isize>, PagedMemory>::restore(data);
+        assert!(restore(&data).is_ok());
+        assert!(matches!(restore(b"INTCODE"), Err(SnapshotError::Io(_))));
+        assert!(matches!(restore(&data[..data.len() - 1]), Err(SnapshotError::Io(_))));
+
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert!(matches!(restore(&bad), Err(SnapshotError::BadMagic)));
+
+        let mut bad = data.clone();
+        bad[8] = 2;
+        assert!(matches!(restore(&bad), Err(SnapshotError::UnsupportedVersion(2))));
+
+        let mut bad = data;
+        bad[28] = 2;
+        assert!(matches!(restore(&bad), Err(SnapshotError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,788 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use crate::error::ErrorKind;
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// Largest range of addresses a read through a symbolic address may cover
+const MAX_SELECT: i128 = 4096;
+
+/// Nodes the solver may visit before giving up with [`Solution::Unknown`]
+const SOLVER_NODES: u64 = 1 << 20;
+
+/// Value computed by a symbolic execution, in terms of the symbols
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Const(Imm),
+
+    /// The symbol with the given index, see [`SymbolicExecutor::symbols`]
+    Symbol(usize),
+
+    Add(Rc<Term>, Rc<Term>),
+    Mul(Rc<Term>, Rc<Term>),
+
+    /// 1 if the first term is less than the second, 0 otherwise
+    LessThan(Rc<Term>, Rc<Term>),
+
+    /// 1 if both terms are equal, 0 otherwise
+    Equals(Rc<Term>, Rc<Term>),
+
+    /// Memory read through a symbolic address: `values[address - base]`
+    Select(Rc<Term>, Pos, Rc<[Term]>)
+}
+
+impl Term {
+    fn binary(op: fn(Rc<Term>, Rc<Term>) -> Term, a: Term, b: Term) -> Term {
+        op(Rc::new(a), Rc::new(b))
+    }
+
+    /// `a + b`. Returns an error if both are constants and the sum overflows.
+    pub fn sum(a: Term, b: Term) -> Result<Term, ErrorKind> {
+        match (&a, &b) {
+            (Term::Const(x), Term::Const(y)) => x.checked_add(*y).map(Term::Const).ok_or(ErrorKind::Overflow),
+            (Term::Const(0), _) => Ok(b),
+            (_, Term::Const(0)) => Ok(a),
+            _ => Ok(Term::binary(Term::Add, a, b))
+        }
+    }
+
+    /// `a * b`. Returns an error if both are constants and the product overflows.
+    pub fn product(a: Term, b: Term) -> Result<Term, ErrorKind> {
+        match (&a, &b) {
+            (Term::Const(x), Term::Const(y)) => x.checked_mul(*y).map(Term::Const).ok_or(ErrorKind::Overflow),
+            (Term::Const(0), _) | (_, Term::Const(0)) => Ok(Term::Const(0)),
+            (Term::Const(1), _) => Ok(b),
+            (_, Term::Const(1)) => Ok(a),
+            _ => Ok(Term::binary(Term::Mul, a, b))
+        }
+    }
+
+    /// 1 if `a < b`, 0 otherwise
+    pub fn less_than(a: Term, b: Term) -> Term {
+        match (&a, &b) {
+            (Term::Const(x), Term::Const(y)) => Term::Const(Imm::from(x < y)),
+            _ => Term::binary(Term::LessThan, a, b)
+        }
+    }
+
+    /// 1 if `a == b`, 0 otherwise
+    pub fn equals(a: Term, b: Term) -> Term {
+        match (&a, &b) {
+            (Term::Const(x), Term::Const(y)) => Term::Const(Imm::from(x == y)),
+            _ if a == b => Term::Const(1),
+            _ => Term::binary(Term::Equals, a, b)
+        }
+    }
+
+    /// Returns the value if the term does not depend on any symbol
+    pub fn as_const(&self) -> Option<Imm> {
+        match *self {
+            Term::Const(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Evaluate with the given symbol values. Returns `None` if any step overflows or a
+    /// [`Term::Select`] address is out of its range.
+    pub fn eval(&self, values: &[Imm]) -> Option<Imm> {
+        match self {
+            Term::Const(value) => Some(*value),
+            Term::Symbol(index) => Some(values[*index]),
+            Term::Add(a, b) => a.eval(values)?.checked_add(b.eval(values)?),
+            Term::Mul(a, b) => a.eval(values)?.checked_mul(b.eval(values)?),
+            Term::LessThan(a, b) => Some(Imm::from(a.eval(values)? < b.eval(values)?)),
+            Term::Equals(a, b) => Some(Imm::from(a.eval(values)? == b.eval(values)?)),
+            Term::Select(addr, base, options) => {
+                let index = usize::try_from(addr.eval(values)?).ok()?.checked_sub(*base)?;
+                options.get(index)?.eval(values)
+            }
+        }
+    }
+
+    /// Range of values the term can take when every symbol stays within its domain, or `None`
+    /// if it can take none at all
+    fn interval(&self, domains: &[(i128, i128)]) -> Option<(i128, i128)> {
+        let (lo, hi) = self.range(domains)?;
+
+        // Values outside the word size overflowed, which the VM reports as a fault
+        let (lo, hi) = (lo.max(Imm::MIN as i128), hi.min(Imm::MAX as i128));
+        if lo > hi {
+            return None;
+        }
+        Some((lo, hi))
+    }
+
+    /// Returns true if the term is outside the word size for some values of the symbols
+    fn may_overflow(&self, domains: &[(i128, i128)]) -> bool {
+        self.range(domains).is_some_and(|(lo, hi)| lo < Imm::MIN as i128 || hi > Imm::MAX as i128)
+    }
+
+    /// Range of the term computed from the intervals of its operands, before overflow is taken
+    /// into account
+    fn range(&self, domains: &[(i128, i128)]) -> Option<(i128, i128)> {
+        let range = match self {
+            Term::Const(value) => (*value as i128, *value as i128),
+            Term::Symbol(index) => domains[*index],
+            Term::Add(a, b) => {
+                let (a, b) = (a.interval(domains)?, b.interval(domains)?);
+                (a.0 + b.0, a.1 + b.1)
+            }
+            Term::Mul(a, b) => {
+                let (a, b) = (a.interval(domains)?, b.interval(domains)?);
+                let products = [a.0 * b.0, a.0 * b.1, a.1 * b.0, a.1 * b.1];
+                (*products.iter().min().unwrap(), *products.iter().max().unwrap())
+            }
+            Term::LessThan(a, b) => {
+                let (a, b) = (a.interval(domains)?, b.interval(domains)?);
+                if a.1 < b.0 {
+                    (1, 1)
+                } else if a.0 >= b.1 {
+                    (0, 0)
+                } else {
+                    (0, 1)
+                }
+            }
+            Term::Equals(a, b) => {
+                let (a, b) = (a.interval(domains)?, b.interval(domains)?);
+                if a.1 < b.0 || b.1 < a.0 {
+                    (0, 0)
+                } else if a.0 == a.1 && a == b {
+                    (1, 1)
+                } else {
+                    (0, 1)
+                }
+            }
+            Term::Select(addr, base, options) => {
+                let (lo, hi) = addr.interval(domains)?;
+                let first = lo.max(*base as i128);
+                let last = hi.min(*base as i128 + options.len() as i128 - 1);
+                (first..=last).filter_map(|addr| options[(addr - *base as i128) as usize].interval(domains))
+                    .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))?
+            }
+        };
+        Some(range)
+    }
+}
+
+/// Infix notation with symbols written as `s<index>`
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Const(value) => write!(f, "{}", value),
+            Term::Symbol(index) => write!(f, "s{}", index),
+            Term::Add(a, b) => write!(f, "({} + {})", a, b),
+            Term::Mul(a, b) => write!(f, "({} * {})", a, b),
+            Term::LessThan(a, b) => write!(f, "({} < {})", a, b),
+            Term::Equals(a, b) => write!(f, "({} == {})", a, b),
+            Term::Select(addr, _, _) => write!(f, "[{}]", addr)
+        }
+    }
+}
+
+/// Condition a path depends on: `term` is non-zero, or zero if `nonzero` is false
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub term: Term,
+    pub nonzero: bool
+}
+
+impl Constraint {
+    /// `term` must be non-zero
+    pub fn holds(term: Term) -> Constraint {
+        Constraint { term, nonzero: true }
+    }
+
+    /// `term` must be zero
+    pub fn fails(term: Term) -> Constraint {
+        Constraint { term, nonzero: false }
+    }
+
+    /// Returns false if the constraint cannot hold for any value in the domains
+    fn feasible(&self, domains: &[(i128, i128)]) -> bool {
+        match self.term.interval(domains) {
+            Some((lo, hi)) if self.nonzero => lo != 0 || hi != 0,
+            Some((lo, hi)) => lo <= 0 && 0 <= hi,
+            None => false
+        }
+    }
+}
+
+/// Unknown value with the range of values it may take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub min: Imm,
+    pub max: Imm
+}
+
+/// Result of solving a set of constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// A value for every symbol that satisfies all constraints, keyed by symbol name
+    Sat(BTreeMap<String, Imm>),
+
+    /// No values satisfy the constraints
+    Unsat,
+
+    /// The solver gave up
+    Unknown
+}
+
+/// How a path ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathEnd {
+    /// Executed a `Halt` instruction
+    Halted,
+
+    /// An `In` found neither concrete nor symbolic input left
+    NeedsInput,
+
+    /// The instruction at the path's IP faults
+    Fault(ErrorKind),
+
+    /// The instruction at the path's IP needs a concrete value where the path has a symbolic one,
+    /// such as a symbolic opcode, jump target or write address, or adds or multiplies symbols in
+    /// a way that may overflow
+    Unsupported(&'static str),
+
+    /// The path executed more instructions than allowed
+    StepLimit
+}
+
+/// One feasible way through the program
+#[derive(Debug, Clone)]
+pub struct Path {
+    /// How the path ended
+    pub end: PathEnd,
+
+    /// Address of the instruction the path ended on
+    pub ip: Pos,
+
+    /// Conditions on the symbols for execution to take this path
+    pub constraints: Vec<Constraint>,
+
+    /// Values written by `Out` instructions
+    pub outputs: Vec<Term>,
+
+    /// Number of instructions executed
+    pub steps: u64,
+
+    memory: BTreeMap<Pos, Term>,
+    relative_base: Imm,
+    inputs_read: usize
+}
+
+impl Path {
+    /// Value of a memory cell at the end of the path
+    pub fn read(&self, address: Pos) -> Term {
+        self.memory.get(&address).cloned().unwrap_or(Term::Const(0))
+    }
+
+    fn write(&mut self, address: Pos, value: Term) {
+        self.memory.insert(address, value);
+    }
+
+    /// Value of a memory cell at a possibly symbolic address
+    fn read_at(&self, address: &Term, domains: &[(i128, i128)]) -> Result<Term, PathEnd> {
+        if let Some(address) = address.as_const() {
+            return match usize::try_from(address) {
+                Ok(address) => Ok(self.read(address)),
+                Err(_) => Err(PathEnd::Fault(ErrorKind::NegativeAddress(address)))
+            };
+        }
+
+        let (lo, hi) = address.interval(domains).ok_or(PathEnd::Unsupported("address out of range"))?;
+        let (lo, hi) = (lo.max(0), hi);
+        if hi < lo || hi - lo >= MAX_SELECT {
+            return Err(PathEnd::Unsupported("symbolic address with a large range"));
+        }
+        let base = lo as Pos;
+        let values: Vec<Term> = (base..=hi as Pos).map(|addr| self.read(addr)).collect();
+        Ok(Term::Select(Rc::new(address.clone()), base, values.into()))
+    }
+
+    /// Decode the instruction at the IP with the decoder of the VM. Returns the instruction and
+    /// its raw parameters, which may be symbolic. Symbolic parameters are decoded as 0, so their
+    /// value in the instruction must not be used.
+    fn decode(&self) -> Result<(Opcode, Vec<Term>), PathEnd> {
+        let word = self.read(self.ip).as_const().ok_or(PathEnd::Unsupported("symbolic opcode"))?;
+        let params: Vec<Term> = (1..Opcode::MAX_LEN).map_while(|index| self.ip.checked_add(index))
+            .map(|addr| self.read(addr))
+            .collect();
+        let words: Vec<Imm> = std::iter::once(word).chain(params.iter().map(|param| param.as_const().unwrap_or(0)))
+            .collect();
+        let op = Opcode::decode(&words).map_err(PathEnd::Fault)?;
+        Ok((op, params))
+    }
+
+    /// Parameter `param` in `mode` as a value
+    fn load(&self, mode: Mode, param: &Term, domains: &[(i128, i128)]) -> Result<Term, PathEnd> {
+        match mode {
+            Mode::Positional(_) => self.read_at(param, domains),
+            Mode::Immediate(_) => Ok(param.clone()),
+            Mode::Relative(_) => {
+                let address = Term::sum(Term::Const(self.relative_base), param.clone()).map_err(PathEnd::Fault)?;
+                self.read_at(&address, domains)
+            }
+        }
+    }
+
+    /// Parameter `param` in `mode` as a destination address
+    fn store(&self, mode: Mode, param: &Term) -> Result<Pos, PathEnd> {
+        let param = param.as_const().ok_or(PathEnd::Unsupported("symbolic write address"))?;
+        let address = match mode {
+            Mode::Positional(_) => param,
+            Mode::Immediate(_) => return Err(PathEnd::Fault(ErrorKind::ImmediateDestination)),
+            Mode::Relative(_) => self.relative_base.checked_add(param).ok_or(PathEnd::Fault(ErrorKind::Overflow))?
+        };
+        usize::try_from(address).map_err(|_| PathEnd::Fault(ErrorKind::NegativeAddress(address)))
+    }
+}
+
+/// Explores the paths of a program in which some memory cells or inputs are symbols
+///
+/// ```
+/// use intcode::{Constraint, Program, Solution, Term};
+///
+/// // Outputs three times its input
+/// let program = Program::from_input("3,9,1002,9,3,9,4,9,99");
+/// let mut executor = program.symbolic();
+/// executor.symbolic_inputs("in", 1, 0..=1000);
+/// let model = executor.find(|path| {
+///     Some(Constraint::holds(Term::equals(path.outputs[0].clone(), Term::Const(123))))
+/// });
+/// assert_eq!(model, Solution::Sat(vec![("in0".to_string(), 41)].into_iter().collect()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SymbolicExecutor {
+    /// Every symbol, indexed by [`Term::Symbol`]
+    symbols: Vec<Symbol>,
+
+    /// State the exploration starts from
+    start: Path,
+
+    /// Values for the first `In` instructions
+    inputs: Vec<Imm>,
+
+    /// Symbols for the `In` instructions after `inputs` is used up
+    input_symbols: Vec<usize>,
+
+    /// Instructions a single path may execute
+    pub max_steps: u64,
+
+    /// Paths explored before giving up
+    pub max_paths: usize
+}
+
+impl SymbolicExecutor {
+    /// Symbols declared so far, indexed by [`Term::Symbol`]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    fn symbol(&mut self, name: String, range: RangeInclusive<Imm>) -> Term {
+        self.symbols.push(Symbol { name, min: *range.start(), max: *range.end() });
+        Term::Symbol(self.symbols.len() - 1)
+    }
+
+    /// Replace the memory cell at `address` with a symbol that takes a value in `range`
+    pub fn symbolic_memory(&mut self, address: Pos, name: &str, range: RangeInclusive<Imm>) -> Term {
+        let term = self.symbol(name.to_string(), range);
+        self.start.write(address, term.clone());
+        term
+    }
+
+    /// Give the first `In` instructions concrete values
+    pub fn inputs(&mut self, values: &[Imm]) {
+        self.inputs.extend_from_slice(values);
+    }
+
+    /// Let the next `count` `In` instructions after the concrete inputs read symbols named
+    /// `name0`, `name1`, ... that each take a value in `range`
+    pub fn symbolic_inputs(&mut self, name: &str, count: usize, range: RangeInclusive<Imm>) -> Vec<Term> {
+        (0..count).map(|_| {
+            let term = self.symbol(format!("{}{}", name, self.input_symbols.len()), range.clone());
+            if let Term::Symbol(index) = term {
+                self.input_symbols.push(index);
+            }
+            term
+        }).collect()
+    }
+
+    fn domains(&self) -> Vec<(i128, i128)> {
+        self.symbols.iter().map(|symbol| (symbol.min as i128, symbol.max as i128)).collect()
+    }
+
+    /// Find values for every symbol that satisfy all `constraints`.
+    ///
+    /// The solver splits the domain of one symbol at a time and drops every half in which some
+    /// constraint cannot hold according to interval arithmetic.
+    pub fn solve(&self, constraints: &[Constraint]) -> Solution {
+        let mut nodes = 0;
+        let mut domains = self.domains();
+        match search(constraints, &mut domains, &mut nodes) {
+            Some(values) => {
+                Solution::Sat(self.symbols.iter().map(|symbol| symbol.name.clone()).zip(values).collect())
+            }
+            None if nodes > SOLVER_NODES => Solution::Unknown,
+            None => Solution::Unsat
+        }
+    }
+
+    /// Explore every feasible path, up to [`SymbolicExecutor::max_paths`]
+    pub fn explore(&self) -> Vec<Path> {
+        let mut paths = Vec::new();
+        self.explore_with(|path| {
+            paths.push(path);
+            true
+        });
+        paths
+    }
+
+    /// Explore paths until `goal` returns a constraint for a path that can be satisfied together
+    /// with the constraints of the path. Returns the values of the symbols for that path, or
+    /// [`Solution::Unknown`] if the solver or the path limit gave up before every path was
+    /// checked.
+    ///
+    /// Paths that add or multiply symbols in a way that may overflow end as
+    /// [`PathEnd::Unsupported`].
+    pub fn find(&self, goal: impl Fn(&Path) -> Option<Constraint>) -> Solution {
+        let mut result = Solution::Unsat;
+        let complete = self.explore_with(|path| {
+            let goal = match goal(&path) {
+                Some(goal) => goal,
+                None => return true
+            };
+            let mut constraints = path.constraints;
+            constraints.push(goal);
+            match self.solve(&constraints) {
+                Solution::Unsat => true,
+                Solution::Unknown => {
+                    result = Solution::Unknown;
+                    true
+                }
+                sat => {
+                    result = sat;
+                    false
+                }
+            }
+        });
+        if !complete && result == Solution::Unsat {
+            return Solution::Unknown;
+        }
+        result
+    }
+
+    /// Call `visit` with every path as it ends until it returns false. Returns false if paths
+    /// were left unexplored because of the path limit.
+    fn explore_with(&self, mut visit: impl FnMut(Path) -> bool) -> bool {
+        let domains = self.domains();
+        let mut worklist = vec![self.start.clone()];
+        let mut explored = 0;
+        while let Some(mut path) = worklist.pop() {
+            if explored == self.max_paths {
+                return false;
+            }
+            explored += 1;
+
+            path.end = loop {
+                if path.steps == self.max_steps {
+                    break PathEnd::StepLimit;
+                }
+                match self.execute(&mut path, &domains) {
+                    Ok(Some(fork)) => worklist.push(fork),
+                    Ok(None) => (),
+                    Err(end) => break end
+                }
+                path.steps += 1;
+            };
+            if !visit(path) {
+                return true;
+            }
+        }
+        true
+    }
+
+    /// Execute the instruction at the IP of `path`. If the instruction is a jump that can go
+    /// either way, `path` takes it and the path that falls through is returned.
+    fn execute(&self, path: &mut Path, domains: &[(i128, i128)]) -> Result<Option<Path>, PathEnd> {
+        let (op, params) = path.decode()?;
+        match op {
+            Opcode::Add(a, b, dest) | Opcode::Mul(a, b, dest) | Opcode::LessThan(a, b, dest)
+                | Opcode::Equals(a, b, dest) => {
+                let a = path.load(a, &params[0], domains)?;
+                let b = path.load(b, &params[1], domains)?;
+                let dest = path.store(dest, &params[2])?;
+                let value = match op {
+                    Opcode::Add(_, _, _) => Term::sum(a, b).map_err(PathEnd::Fault)?,
+                    Opcode::Mul(_, _, _) => Term::product(a, b).map_err(PathEnd::Fault)?,
+                    Opcode::LessThan(_, _, _) => Term::less_than(a, b),
+                    _ => Term::equals(a, b)
+                };
+                // The VM faults on overflow, which the path cannot tell apart from a valid result
+                if value.may_overflow(domains) {
+                    return Err(PathEnd::Unsupported("symbolic overflow"));
+                }
+                path.write(dest, value);
+                path.ip += 4;
+            }
+            Opcode::In(dest) => {
+                let dest = path.store(dest, &params[0])?;
+                let value = match self.inputs.get(path.inputs_read) {
+                    Some(&value) => Term::Const(value),
+                    None => match self.input_symbols.get(path.inputs_read - self.inputs.len()) {
+                        Some(&index) => Term::Symbol(index),
+                        None => return Err(PathEnd::NeedsInput)
+                    }
+                };
+                path.inputs_read += 1;
+                path.write(dest, value);
+                path.ip += 2;
+            }
+            Opcode::Out(a) => {
+                let value = path.load(a, &params[0], domains)?;
+                path.outputs.push(value);
+                path.ip += 2;
+            }
+            Opcode::JumpNonZero(cond, target) | Opcode::JumpZero(cond, target) => {
+                let cond = path.load(cond, &params[0], domains)?;
+                let target = path.load(target, &params[1], domains)?;
+                let jump = |path: &mut Path| -> Result<(), PathEnd> {
+                    let target = target.as_const().ok_or(PathEnd::Unsupported("symbolic jump target"))?;
+                    path.ip = usize::try_from(target).map_err(|_| PathEnd::Fault(ErrorKind::InvalidJumpTarget(target)))?;
+                    Ok(())
+                };
+                let taken = match op {
+                    Opcode::JumpNonZero(_, _) => Constraint::holds(cond.clone()),
+                    _ => Constraint::fails(cond.clone())
+                };
+
+                if let Some(cond) = cond.as_const() {
+                    if (cond != 0) == taken.nonzero {
+                        jump(path)?;
+                    } else {
+                        path.ip += 3;
+                    }
+                    return Ok(None);
+                }
+
+                let not_taken = Constraint { term: taken.term.clone(), nonzero: !taken.nonzero };
+                let mut fallthrough = path.clone();
+                fallthrough.constraints.push(not_taken);
+                fallthrough.ip += 3;
+                fallthrough.steps += 1;
+                let fallthrough = match self.solve(&fallthrough.constraints) {
+                    Solution::Unsat => None,
+                    _ => Some(fallthrough)
+                };
+
+                path.constraints.push(taken);
+                if self.solve(&path.constraints) == Solution::Unsat {
+                    // Only the fallthrough is feasible, continue with it in place
+                    return match fallthrough {
+                        Some(fallthrough) => {
+                            *path = Path { steps: path.steps, ..fallthrough };
+                            Ok(None)
+                        }
+                        None => Err(PathEnd::Unsupported("no feasible branch"))
+                    };
+                }
+                jump(path)?;
+                return Ok(fallthrough);
+            }
+            Opcode::AdjustRelativeBase(a) => {
+                let offset = path.load(a, &params[0], domains)?.as_const()
+                    .ok_or(PathEnd::Unsupported("symbolic relative base"))?;
+                path.relative_base = path.relative_base.checked_add(offset)
+                    .ok_or(PathEnd::Fault(ErrorKind::Overflow))?;
+                path.ip += 2;
+            }
+            Opcode::Halt => return Err(PathEnd::Halted)
+        }
+        Ok(None)
+    }
+}
+
+/// Depth-first search for values in `domains` that satisfy every constraint, splitting the first
+/// undecided symbol in half at each level
+fn search(constraints: &[Constraint], domains: &mut Vec<(i128, i128)>, nodes: &mut u64) -> Option<Vec<Imm>> {
+    *nodes += 1;
+    if *nodes > SOLVER_NODES || !constraints.iter().all(|constraint| constraint.feasible(domains)) {
+        return None;
+    }
+
+    let index = match domains.iter().position(|&(lo, hi)| lo < hi) {
+        Some(index) => index,
+        None => {
+            let values: Vec<Imm> = domains.iter().map(|&(value, _)| value as Imm).collect();
+            let holds = constraints.iter().all(|constraint| {
+                constraint.term.eval(&values).is_some_and(|value| (value != 0) == constraint.nonzero)
+            });
+            return if holds { Some(values) } else { None };
+        }
+    };
+
+    let (lo, hi) = domains[index];
+    let mid = lo + (hi - lo) / 2;
+    for half in [(lo, mid), (mid + 1, hi)] {
+        domains[index] = half;
+        if let Some(values) = search(constraints, domains, nodes) {
+            return Some(values);
+        }
+    }
+    domains[index] = (lo, hi);
+    None
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Start a symbolic execution from the current memory, IP and relative base. The input
+    /// source is not used, give the executor inputs with [`SymbolicExecutor::inputs`].
+    pub fn symbolic(&self) -> SymbolicExecutor {
+        let mut memory = BTreeMap::new();
+        for (start, values) in self.memory.regions() {
+            for (offset, &value) in values.iter().enumerate() {
+                if value != 0 {
+                    memory.insert(start + offset, Term::Const(value));
+                }
+            }
+        }
+
+        SymbolicExecutor {
+            symbols: Vec::new(),
+            start: Path {
+                end: PathEnd::StepLimit,
+                ip: self.ip,
+                constraints: Vec::new(),
+                outputs: Vec::new(),
+                steps: 0,
+                memory,
+                relative_base: self.relative_base,
+                inputs_read: 0
+            },
+            inputs: Vec::new(),
+            input_symbols: Vec::new(),
+            max_steps: 100_000,
+            max_paths: 1024
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day02_stage2() {
+        let program = Program::from_input(include_str!("../../day02/input"));
+        let mut executor = program.symbolic();
+        executor.symbolic_memory(1, "noun", 0..=99);
+        executor.symbolic_memory(2, "verb", 0..=99);
+        let paths = executor.explore();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].end, PathEnd::Halted);
+
+        let goal = |path: &Path| Some(Constraint::holds(Term::equals(path.read(0), Term::Const(19690720))));
+        let model = match executor.find(goal) {
+            Solution::Sat(model) => model,
+            other => panic!("{:?}", other)
+        };
+
+        // Check the answer on the real thing
+        let mut concrete = program.clone();
+        concrete.write(1, model["noun"]);
+        concrete.write(2, model["verb"]);
+        concrete.run().unwrap();
+        assert_eq!(concrete.read(0), 19690720);
+    }
+
+    #[test]
+    fn test_fork_on_input() {
+        // in -> [20], eq [20], #42 -> [21], jnz [21], #13, out #0, halt, out #1, halt
+        let program = Program::from_input("3,20,1008,20,42,21,1005,21,13,104,0,99,0,104,1,99");
+        let mut executor = program.symbolic();
+        executor.symbolic_inputs("in", 1, -1000..=1000);
+        let paths = executor.explore();
+        assert_eq!(paths.len(), 2);
+        let outputs: Vec<Option<Imm>> = paths.iter().map(|path| path.outputs[0].as_const()).collect();
+        assert_eq!(outputs, vec![Some(1), Some(0)]);
+        assert_eq!(paths[0].constraints[0].term.to_string(), "(s0 == 42)");
+        assert!(paths[0].constraints[0].nonzero && !paths[1].constraints[0].nonzero);
+
+        let model = executor.find(|path| {
+            Some(Constraint::holds(Term::equals(path.outputs[0].clone(), Term::Const(1))))
+        });
+        assert_eq!(model, Solution::Sat(vec![("in0".to_string(), 42)].into_iter().collect()));
+
+        // Out of range for the symbol
+        let mut executor = program.symbolic();
+        executor.symbolic_inputs("in", 1, 0..=10);
+        assert_eq!(executor.explore().len(), 1);
+    }
+
+    #[test]
+    fn test_solver() {
+        let mut executor = Program::from_input("99").symbolic();
+        let x = executor.symbolic_memory(0, "x", -1_000_000..=1_000_000);
+        let y = executor.symbolic_memory(1, "y", -1_000_000..=1_000_000);
+
+        // x * y == 391 with 10 < x < y: 17 * 23
+        let product = Term::product(x.clone(), y.clone()).unwrap();
+        let constraints = vec![
+            Constraint::holds(Term::equals(product, Term::Const(391))),
+            Constraint::holds(Term::less_than(Term::Const(10), x.clone())),
+            Constraint::holds(Term::less_than(x.clone(), y))
+        ];
+        let model = match executor.solve(&constraints) {
+            Solution::Sat(model) => model,
+            other => panic!("{:?}", other)
+        };
+        assert_eq!((model["x"], model["y"]), (17, 23));
+
+        // x * x == 2 has no integer solution
+        let square = Term::product(x.clone(), x).unwrap();
+        assert_eq!(executor.solve(&[Constraint::holds(Term::equals(square, Term::Const(2)))]), Solution::Unsat);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // jnz #1, [0] jumps to a symbolic target
+        let mut program = Program::from_input("0,105,1,0");
+        program.ip = 1;
+        let mut executor = program.symbolic();
+        executor.symbolic_memory(0, "target", 0..=10);
+        let paths = executor.explore();
+        assert_eq!(paths[0].end, PathEnd::Unsupported("symbolic jump target"));
+        assert_eq!(paths[0].ip, 1);
+    }
+
+    #[test]
+    fn test_decode_like_the_vm() {
+        // A mode digit of 10 for the third parameter faults, as it does when running
+        let mut program = Program::from_input("100001,0,0,0,99");
+        let paths = program.symbolic().explore();
+        let fault = ErrorKind::UnknownMode { param: 3, mode: 10 };
+        assert_eq!(paths[0].end, PathEnd::Fault(fault));
+        assert_eq!(program.run().unwrap_err().kind, fault);
+    }
+
+    #[test]
+    fn test_symbolic_overflow() {
+        // mul [5], #2 -> [5], halt, with a symbol in [5]
+        let program = Program::from_input("1002,5,2,5,99,0");
+        let mut executor = program.symbolic();
+        executor.symbolic_memory(5, "x", 0..=Imm::MAX);
+        let paths = executor.explore();
+        assert_eq!(paths[0].end, PathEnd::Unsupported("symbolic overflow"));
+        assert_eq!(paths[0].ip, 0);
+
+        let mut executor = program.symbolic();
+        executor.symbolic_memory(5, "x", 0..=Imm::MAX / 2);
+        assert_eq!(executor.explore()[0].end, PathEnd::Halted);
+    }
+}
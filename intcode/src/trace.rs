@@ -0,0 +1,529 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, BufWriter, Read, Write};
+
+use crate::io::{InputSource, OutputSink};
+use crate::memory::Memory;
+use crate::opcode::{Imm, Mode, Opcode, Pos};
+use crate::program::Program;
+
+/// First bytes of every trace
+const MAGIC: &[u8; 8] = b"ICTRACE\0";
+
+/// Version written by [`Program::with_trace`]. Bump whenever the layout below changes.
+///
+/// Version 1 layout: the magic, the version as a little endian u32, then one record per executed
+/// instruction. Record fields are LEB128 varints, signed values are zigzag encoded first.
+///
+/// ```text
+/// flags     u8         bit 0: wrote memory, bit 1: read input, bit 2: wrote output
+/// ip        varint
+/// words     signed     the instruction as encoded in memory, its length follows from the opcode
+/// operands  signed     value of every parameter the instruction reads, in order
+/// write     varint address and signed value, if flag bit 0 is set
+/// input     signed     if flag bit 1 is set
+/// output    signed     if flag bit 2 is set
+/// ```
+pub const TRACE_VERSION: u32 = 1;
+
+const WROTE: u8 = 1;
+const INPUT: u8 = 2;
+const OUTPUT: u8 = 4;
+
+/// Reasons a trace could not be read
+#[derive(Debug)]
+pub enum TraceError {
+    /// Reading the trace failed, including a trace that ends in the middle of a record
+    Io(io::Error),
+
+    /// The data does not start with the trace magic
+    BadMagic,
+
+    /// The trace was written by an unknown version of the format
+    UnsupportedVersion(u32),
+
+    /// A value in the trace is out of range
+    Corrupt(&'static str)
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "failed to read trace: {}", err),
+            TraceError::BadMagic => write!(f, "not an Intcode trace"),
+            TraceError::UnsupportedVersion(version) => {
+                write!(f, "unsupported trace version {} (expected {})", version, TRACE_VERSION)
+            }
+            TraceError::Corrupt(what) => write!(f, "corrupt trace: {}", what)
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> TraceError {
+        TraceError::Io(err)
+    }
+}
+
+/// One executed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Number of instructions executed before this one since recording started
+    pub step: u64,
+
+    /// Address of the instruction
+    pub ip: Pos,
+
+    /// The instruction as it was executed
+    pub op: Opcode,
+
+    /// Value of every parameter the instruction read, in order
+    pub operands: Vec<Imm>,
+
+    /// Address written and the value written to it
+    pub write: Option<(Pos, Imm)>,
+
+    /// Input value consumed
+    pub input: Option<Imm>,
+
+    /// Output value produced
+    pub output: Option<Imm>
+}
+
+/// Parameters of an instruction that are read rather than written
+pub(crate) fn sources(op: &Opcode) -> Vec<Mode> {
+    match *op {
+        Opcode::Add(a, b, _) | Opcode::Mul(a, b, _) | Opcode::LessThan(a, b, _) | Opcode::Equals(a, b, _)
+            | Opcode::JumpNonZero(a, b) | Opcode::JumpZero(a, b) => vec![a, b],
+        Opcode::Out(a) | Opcode::AdjustRelativeBase(a) => vec![a],
+        Opcode::In(_) | Opcode::Halt => Vec::new()
+    }
+}
+
+impl TraceEntry {
+    /// Returns true if the instruction is at `address` or wrote to it
+    pub fn touches(&self, address: Pos) -> bool {
+        self.ip == address || self.write.is_some_and(|(written, _)| written == address)
+    }
+
+    /// Returns true if `value` was read, written, input or output by the instruction
+    pub fn contains_value(&self, value: Imm) -> bool {
+        self.operands.contains(&value) || self.write.is_some_and(|(_, written)| written == value)
+            || self.input == Some(value) || self.output == Some(value)
+    }
+}
+
+/// One line per entry: step, address, instruction, then the values it read and produced
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut notes = Vec::new();
+        if !self.operands.is_empty() {
+            let operands: Vec<String> = self.operands.iter().map(|value| value.to_string()).collect();
+            notes.push(format!("reads {}", operands.join(", ")));
+        }
+        if let Some((address, value)) = self.write {
+            notes.push(format!("[{}] = {}", address, value));
+        }
+        if let Some(input) = self.input {
+            notes.push(format!("in {}", input));
+        }
+        if let Some(output) = self.output {
+            notes.push(format!("out {}", output));
+        }
+        write!(f, "{:>8}  {:06}  {:<32} ; {}", self.step, self.ip, self.op.to_string(), notes.join("; "))
+    }
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut bytes = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes[len] = byte;
+            len += 1;
+            break;
+        }
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&bytes[..len])
+}
+
+fn write_signed<W: Write>(writer: &mut W, value: Imm) -> io::Result<()> {
+    let value = value as i64;
+    write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+/// Read a varint
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, TraceError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        if reader.read(&mut byte)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        // Only the lowest bit of the tenth byte fits in 64 bits
+        if shift == 63 && byte[0] > 1 {
+            return Err(TraceError::Corrupt("varint longer than 64 bits"));
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    unreachable!("the tenth byte of a varint ends it")
+}
+
+fn read_unsigned<R: Read>(reader: &mut R) -> Result<Pos, TraceError> {
+    let value = read_varint(reader)?;
+    usize::try_from(value).map_err(|_| TraceError::Corrupt("address does not fit in usize"))
+}
+
+fn read_signed<R: Read>(reader: &mut R) -> Result<Imm, TraceError> {
+    let value = read_varint(reader)?;
+    let value = (value >> 1) as i64 ^ -((value & 1) as i64);
+    isize::try_from(value).map_err(|_| TraceError::Corrupt("value does not fit in isize"))
+}
+
+/// Records executed instructions to a writer in the binary trace format
+pub struct Tracer {
+    /// Destination of the trace. `None` once a write failed or for a copy of a tracer.
+    writer: Option<BufWriter<Box<dyn Write + Send>>>,
+
+    /// Entry for the instruction currently executing
+    current: Option<TraceEntry>,
+
+    /// Number of instructions recorded
+    steps: u64,
+
+    /// First error writing the trace
+    error: Option<io::Error>
+}
+
+/// A copy does not record anything, so that two programs never write into the same trace
+impl Clone for Tracer {
+    fn clone(&self) -> Self {
+        Tracer { writer: None, current: None, steps: self.steps, error: None }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracer {{ steps: {}, recording: {} }}", self.steps, self.writer.is_some())
+    }
+}
+
+impl Tracer {
+    fn new(writer: Box<dyn Write + Send>) -> Tracer {
+        let mut tracer = Tracer { writer: Some(BufWriter::new(writer)), current: None, steps: 0, error: None };
+        tracer.emit(|writer| {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&TRACE_VERSION.to_le_bytes())
+        });
+        tracer
+    }
+
+    /// Number of instructions recorded so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Write to the trace, disabling it on the first error
+    fn emit(&mut self, f: impl FnOnce(&mut BufWriter<Box<dyn Write + Send>>) -> io::Result<()>) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(err) = f(writer) {
+                self.error = Some(err);
+                self.writer = None;
+            }
+        }
+    }
+
+    /// Remember the value written by the current instruction
+    pub(crate) fn record_write(&mut self, address: Pos, value: Imm) {
+        if let Some(entry) = &mut self.current {
+            entry.write = Some((address, value));
+        }
+    }
+
+    /// Remember that the current instruction consumed `value`
+    pub(crate) fn record_input(&mut self, value: isize) {
+        if let Some(entry) = &mut self.current {
+            entry.input = Some(value);
+        }
+    }
+
+    /// Remember that the current instruction wrote `value` to the output
+    pub(crate) fn record_output(&mut self, value: isize) {
+        if let Some(entry) = &mut self.current {
+            entry.output = Some(value);
+        }
+    }
+
+    /// Write the current instruction if it executed
+    fn end(&mut self, changed: bool) {
+        let entry = match self.current.take() {
+            Some(entry) if changed => entry,
+            _ => return
+        };
+
+        let flags = if entry.write.is_some() { WROTE } else { 0 }
+            | if entry.input.is_some() { INPUT } else { 0 }
+            | if entry.output.is_some() { OUTPUT } else { 0 };
+        self.emit(|writer| {
+            writer.write_all(&[flags])?;
+            write_varint(writer, entry.ip as u64)?;
+            for word in entry.op.encode() {
+                write_signed(writer, word)?;
+            }
+            for &value in &entry.operands {
+                write_signed(writer, value)?;
+            }
+            if let Some((address, value)) = entry.write {
+                write_varint(writer, address as u64)?;
+                write_signed(writer, value)?;
+            }
+            if let Some(input) = entry.input {
+                write_signed(writer, input)?;
+            }
+            if let Some(output) = entry.output {
+                write_signed(writer, output)?;
+            }
+            Ok(())
+        });
+        self.steps += 1;
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Program<I, O, M> {
+    /// Record every executed instruction to `writer` in the binary trace format, see
+    /// [`TRACE_VERSION`]. Read the trace back with [`TraceReader`].
+    ///
+    /// Write errors stop the recording and are reported by [`Program::finish_trace`]. The JIT is
+    /// not used while tracing.
+    pub fn with_trace<W: Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.trace = Some(Tracer::new(Box::new(writer)));
+        self
+    }
+
+    /// Stop tracing and flush the trace. Returns the number of instructions recorded, or the
+    /// first error writing the trace.
+    pub fn finish_trace(&mut self) -> io::Result<u64> {
+        let mut tracer = match self.trace.take() {
+            Some(tracer) => tracer,
+            None => return Ok(0)
+        };
+        tracer.emit(|writer| writer.flush());
+        match tracer.error {
+            Some(err) => Err(err),
+            None => Ok(tracer.steps)
+        }
+    }
+
+    /// Start tracing the instruction at the current IP. Operands are read up front because the
+    /// instruction may overwrite them.
+    pub(crate) fn begin_trace(&mut self) {
+        if self.trace.is_none() {
+            return;
+        }
+
+        let op = match self.instructions.get(self.ip).copied().map_or_else(|| self.decode(self.ip), Ok) {
+            Ok(op) => op,
+            Err(_) => return
+        };
+        let mut operands = Vec::new();
+        for param in sources(&op) {
+            let value = match param {
+                Mode::Immediate(imm) => imm,
+                Mode::Positional(address) => self.read(address),
+                Mode::Relative(offset) => match self.relative(offset) {
+                    Ok(address) => self.read(address),
+
+                    // The instruction faults and is not recorded
+                    Err(_) => return
+                }
+            };
+            operands.push(value);
+        }
+
+        if let Some(tracer) = &mut self.trace {
+            tracer.current = Some(TraceEntry {
+                step: tracer.steps,
+                ip: self.ip,
+                op,
+                operands,
+                write: None,
+                input: None,
+                output: None
+            });
+        }
+    }
+
+    /// Finish tracing the current instruction. Instructions that did not change any state, such
+    /// as a fault or an `In` without input, are not recorded.
+    pub(crate) fn end_trace(&mut self, changed: bool) {
+        if let Some(tracer) = &mut self.trace {
+            tracer.end(changed);
+        }
+    }
+}
+
+/// Reads a trace written by [`Program::with_trace`], one [`TraceEntry`] at a time
+pub struct TraceReader<R> {
+    reader: R,
+
+    /// Step of the next entry
+    step: u64
+}
+
+impl<R: Read> TraceReader<R> {
+    /// Check the header of the trace
+    pub fn new(mut reader: R) -> Result<TraceReader<R>, TraceError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(TraceError::BadMagic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != TRACE_VERSION {
+            return Err(TraceError::UnsupportedVersion(version));
+        }
+
+        Ok(TraceReader { reader, step: 0 })
+    }
+
+    /// Read the next entry, or `None` at the end of the trace
+    fn entry(&mut self) -> Result<Option<TraceEntry>, TraceError> {
+        let mut flags = [0; 1];
+        if self.reader.read(&mut flags)? == 0 {
+            return Ok(None);
+        }
+        let flags = flags[0];
+        if flags & !(WROTE | INPUT | OUTPUT) != 0 {
+            return Err(TraceError::Corrupt("unknown record flags"));
+        }
+
+        let ip = read_unsigned(&mut self.reader)?;
+        let mut words = vec![read_signed(&mut self.reader)?];
+        // Unknown opcodes fail to decode below
+        let len = Opcode::len_of(words[0]).unwrap_or(1);
+        for _ in 1..len {
+            words.push(read_signed(&mut self.reader)?);
+        }
+        let op = Opcode::decode(&words).map_err(|_| TraceError::Corrupt("invalid instruction"))?;
+
+        let mut operands = Vec::new();
+        for _ in sources(&op) {
+            operands.push(read_signed(&mut self.reader)?);
+        }
+        let write = match flags & WROTE {
+            0 => None,
+            _ => Some((read_unsigned(&mut self.reader)?, read_signed(&mut self.reader)?))
+        };
+        let input = match flags & INPUT {
+            0 => None,
+            _ => Some(read_signed(&mut self.reader)?)
+        };
+        let output = match flags & OUTPUT {
+            0 => None,
+            _ => Some(read_signed(&mut self.reader)?)
+        };
+
+        let step = self.step;
+        self.step += 1;
+        Ok(Some(TraceEntry { step, ip, op, operands, write, input, output }))
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = Result<TraceEntry, TraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entry().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer whose contents can be read back after the program is done with it
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(input: &str, inputs: &[isize]) -> Vec<TraceEntry> {
+        let shared = Shared::default();
+        let mut program = Program::from_input(input).with_trace(shared.clone());
+        program.input.extend(inputs);
+        program.run().unwrap();
+        let steps = program.finish_trace().unwrap();
+
+        let bytes = shared.0.lock().unwrap().clone();
+        let entries: Vec<TraceEntry> = TraceReader::new(&bytes[..]).unwrap().map(Result::unwrap).collect();
+        assert_eq!(entries.len() as u64, steps);
+        entries
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Adds 1 to its input and outputs it: In(Pos(9)), Add(Pos(9), Imm(1), Pos(9)), Out(Pos(9)), Halt
+        let entries = record("3,9,1001,9,1,9,4,9,99", &[41]);
+        assert_eq!(entries, vec![
+            TraceEntry { step: 0, ip: 0, op: Opcode::In(Mode::Positional(9)), operands: vec![],
+                         write: Some((9, 41)), input: Some(41), output: None },
+            TraceEntry { step: 1, ip: 2, op: Opcode::Add(Mode::Positional(9), Mode::Immediate(1), Mode::Positional(9)),
+                         operands: vec![41, 1], write: Some((9, 42)), input: None, output: None },
+            TraceEntry { step: 2, ip: 6, op: Opcode::Out(Mode::Positional(9)), operands: vec![42],
+                         write: None, input: None, output: Some(42) },
+            TraceEntry { step: 3, ip: 8, op: Opcode::Halt, operands: vec![], write: None, input: None, output: None }
+        ]);
+        assert_eq!(entries[1].to_string(),
+                   "       1  000002  add [9], #1 -> [9]               ; reads 41, 1; [9] = 42");
+        assert!(entries[1].touches(9) && entries[1].touches(2) && !entries[1].touches(3));
+        assert!(entries[2].contains_value(42) && !entries[2].contains_value(41));
+    }
+
+    #[test]
+    fn test_day09_trace() {
+        let entries = record(include_str!("../../day09/input"), &[1]);
+        assert_eq!(entries.last().unwrap().op, Opcode::Halt);
+        assert_eq!(entries.iter().filter_map(|entry| entry.output).collect::<Vec<_>>().len(), 1);
+
+        // Negative values and relative operands survive the round trip
+        assert!(entries.iter().any(|entry| entry.operands.iter().any(|&value| value < 0)));
+        assert!(entries.iter().any(|entry| matches!(entry.op, Opcode::AdjustRelativeBase(_))));
+    }
+
+    #[test]
+    fn test_corrupt_traces() {
+        assert!(matches!(TraceReader::new(&b"INTCODE\0\x01\0\0\0"[..]), Err(TraceError::BadMagic)));
+        assert!(matches!(TraceReader::new(&b"ICTRACE\0\x02\0\0\0"[..]),
+                         Err(TraceError::UnsupportedVersion(2))));
+
+        // A record cut off after its flags
+        let mut reader = TraceReader::new(&b"ICTRACE\0\x01\0\0\0\x00"[..]).unwrap();
+        assert!(matches!(reader.next(), Some(Err(TraceError::Io(_)))));
+
+        // An IP whose tenth varint byte has bits past the 64th
+        let mut reader = TraceReader::new(&b"ICTRACE\0\x01\0\0\0\x00\x80\x80\x80\x80\x80\x80\x80\x80\x80\x02"[..])
+            .unwrap();
+        assert!(matches!(reader.next(), Some(Err(TraceError::Corrupt("varint longer than 64 bits")))));
+    }
+}
@@ -0,0 +1,154 @@
+//! Checks that programs translated with `Program::translate` behave exactly like the interpreter.
+//!
+//! The modules in `tests/aot` are generated with the `aot` binary, for example
+//! `cargo run --bin aot ../day09/input > tests/aot/day09.rs`.
+
+use std::collections::BTreeSet;
+
+use intcode::{ExitReason, Memory, Program};
+
+mod day02 {
+    include!("aot/day02.rs");
+}
+
+mod day05 {
+    include!("aot/day05.rs");
+}
+
+mod day07 {
+    include!("aot/day07.rs");
+}
+
+mod day09 {
+    include!("aot/day09.rs");
+}
+
+const DAY02: &str = include_str!("../../day02/input");
+const DAY05: &str = include_str!("../../day05/input");
+const DAY07: &str = include_str!("../../day07/input");
+const DAY09: &str = include_str!("../../day09/input");
+
+/// Entry points of the amplifier programs of day07, reached through a jump table on the phase
+const DAY07_ENTRIES: &[usize] = &[0, 21, 38, 47, 64, 89, 110, 191, 272, 353, 434];
+
+/// Assert that the translated and the interpreted program ended up in the same state
+fn assert_same_state(translated: &Program, interpreted: &Program) {
+    assert_eq!(translated.ip, interpreted.ip);
+    assert_eq!(translated.relative_base, interpreted.relative_base);
+    assert_eq!(translated.halted, interpreted.halted);
+    assert_eq!(translated.output, interpreted.output);
+    assert_eq!(translated.input, interpreted.input);
+    assert_eq!(translated.memory.regions(), interpreted.memory.regions());
+}
+
+/// Run the translation in `$module` and the interpreter from the same state with the same inputs,
+/// assert that both end in the same state and evaluate to the translated program
+macro_rules! run_both {
+    ($module:ident, $program:expr, $inputs:expr) => {{
+        let mut interpreted: Program = $program;
+        let mut translated = $module::Translated::from_program(interpreted.clone());
+        for &value in $inputs {
+            interpreted.input.push_back(value);
+            translated.program.input.push_back(value);
+        }
+        assert_eq!(translated.run(), interpreted.run());
+        assert_same_state(&translated.program, &interpreted);
+        translated
+    }};
+}
+
+#[test]
+fn test_modules_in_sync() {
+    assert_eq!(Program::from_input(DAY02).translate(), include_str!("aot/day02.rs"));
+    assert_eq!(Program::from_input(DAY05).translate_from(&[0, 10]), include_str!("aot/day05.rs"));
+    assert_eq!(Program::from_input(DAY07).translate_from(DAY07_ENTRIES), include_str!("aot/day07.rs"));
+    assert_eq!(Program::from_input(DAY09).translate(), include_str!("aot/day09.rs"));
+}
+
+#[test]
+fn test_day02_patched_code_is_interpreted() {
+    for &(noun, verb) in &[(12, 2), (0, 0), (64, 21), (99, 99)] {
+        let mut program = Program::from_input(DAY02);
+        program.write(1, noun);
+        program.write(2, verb);
+        let translated = run_both!(day02, program, &[]);
+        assert_eq!(translated.modified, vec![0].into_iter().collect::<BTreeSet<_>>());
+    }
+
+    // Unpatched, the program overwrites its own code right away
+    let mut translated = day02::Translated::new();
+    assert!(translated.modified.is_empty());
+    assert_eq!(translated.run(), Ok(ExitReason::Halted));
+    let mut interpreted = Program::from_input(DAY02);
+    interpreted.run().unwrap();
+    assert_same_state(&translated.program, &interpreted);
+    assert!(!translated.modified.is_empty());
+}
+
+#[test]
+fn test_day05_diagnostics() {
+    let translated = run_both!(day05, Program::from_input(DAY05), &[1]);
+    assert_eq!(translated.program.output.iter().filter(|&&value| value != 0).count(), 1);
+
+    // The instruction at address 6 is patched by the input, so it is never translated
+    assert!(translated.modified.is_empty());
+
+    for input in 0..12 {
+        run_both!(day05, Program::from_input(DAY05), &[input]);
+    }
+}
+
+#[test]
+fn test_day07_feedback_loop() {
+    for phases in &[[9, 8, 7, 6, 5], [5, 6, 7, 8, 9], [7, 9, 5, 8, 6]] {
+        let mut interpreted: Vec<Program> = Vec::new();
+        let mut translated = Vec::new();
+        for &phase in phases {
+            let mut program = Program::from_input(DAY07);
+            program.input.push_back(phase);
+            translated.push(day07::Translated::from_program(program.clone()));
+            interpreted.push(program);
+        }
+
+        // Resume each amplifier after it runs out of input until the last one halts
+        let mut signal = 0;
+        for i in (0..5).cycle() {
+            interpreted[i].input.push_back(signal);
+            translated[i].program.input.push_back(signal);
+            let exit = interpreted[i].run();
+            assert_eq!(translated[i].run(), exit);
+            assert_same_state(&translated[i].program, &interpreted[i]);
+
+            signal = *interpreted[i].output.last().unwrap();
+            interpreted[i].output.clear();
+            translated[i].program.output.clear();
+            if i == 4 && exit == Ok(ExitReason::Halted) {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_day09_boost() {
+    let translated = run_both!(day09, Program::from_input(DAY09), &[1]);
+    assert_eq!(translated.program.output.len(), 1);
+    assert!(translated.modified.is_empty());
+
+    let translated = run_both!(day09, Program::from_input(DAY09), &[2]);
+    assert_eq!(translated.program.output, vec![77944]);
+}
+
+#[test]
+fn test_needs_input_resumes() {
+    let mut translated = day09::Translated::default();
+    assert_eq!(translated.run(), Ok(ExitReason::NeedsInput));
+    let ip = translated.program.ip;
+    assert_eq!(translated.run(), Ok(ExitReason::NeedsInput));
+    assert_eq!(translated.program.ip, ip);
+
+    translated.program.input.push_back(2);
+    assert_eq!(translated.run(), Ok(ExitReason::Halted));
+    assert_eq!(translated.run(), Ok(ExitReason::Halted));
+    assert_eq!(translated.program.output, vec![77944]);
+}
@@ -0,0 +1,150 @@
+// Generated by `intcode::Program::translate`. Do not edit.
+//
+// 153 words of code in 1 block, translated from a 156 word image.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use intcode::{Event, ExitReason, InputSource, Memory, Mode, Opcode, OutputSink, PagedMemory, Pos, Program, VmError};
+
+/// Memory image the module was translated from
+pub const IMAGE: &[isize] = &[
+    1, 0, 0, 3, 1, 1, 2, 3, 1, 3, 4, 3, 1, 5, 0, 3,
+    2, 1, 13, 19, 1, 9, 19, 23, 1, 6, 23, 27, 2, 27, 9, 31,
+    2, 6, 31, 35, 1, 5, 35, 39, 1, 10, 39, 43, 1, 43, 13, 47,
+    1, 47, 9, 51, 1, 51, 9, 55, 1, 55, 9, 59, 2, 9, 59, 63,
+    2, 9, 63, 67, 1, 5, 67, 71, 2, 13, 71, 75, 1, 6, 75, 79,
+    1, 10, 79, 83, 2, 6, 83, 87, 1, 87, 5, 91, 1, 91, 9, 95,
+    1, 95, 10, 99, 2, 9, 99, 103, 1, 5, 103, 107, 1, 5, 107, 111,
+    2, 111, 10, 115, 1, 6, 115, 119, 2, 10, 119, 123, 1, 6, 123, 127,
+    1, 127, 5, 131, 2, 9, 131, 135, 1, 5, 135, 139, 1, 139, 10, 143,
+    1, 143, 2, 147, 1, 147, 5, 0, 99, 2, 0, 14,
+];
+
+/// Start and end address of every translated block
+const BLOCKS: &[(Pos, Pos)] = &[
+    (0, 153),
+];
+
+/// Execute the instruction at the IP, or at `$addr`, with the interpreter
+#[allow(unused_macros)]
+macro_rules! interpret {
+    ($p:ident) => {
+        match $p.step()? {
+            Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+            Event::Halted => return Ok(ExitReason::Halted),
+            _ => continue
+        }
+    };
+    ($p:ident, $addr:expr) => {{
+        $p.ip = $addr;
+        interpret!($p)
+    }};
+}
+
+/// Address of a relative parameter of the instruction at `$addr`. Negative addresses and
+/// overflow are left to the interpreter.
+#[allow(unused_macros)]
+macro_rules! rel {
+    ($p:ident, $offset:expr, $addr:expr) => {
+        match $p.relative_base.checked_add($offset) {
+            Some(address) if address >= 0 => address as Pos,
+            _ => interpret!($p, $addr)
+        }
+    };
+}
+
+/// Execute the instruction at the IP with the interpreter, noting writes into translated code
+#[allow(unused_macros)]
+macro_rules! fallback {
+    ($p:ident, $modified:expr) => {{
+        if let Some(block) = written_block($p) {
+            $modified.insert(block);
+        }
+        interpret!($p)
+    }};
+}
+
+/// Start address of the translated block containing `address`
+fn block_at(address: Pos) -> Option<Pos> {
+    let index = BLOCKS.partition_point(|&(start, _)| start <= address).checked_sub(1)?;
+    let (start, end) = BLOCKS[index];
+    if address < end { Some(start) } else { None }
+}
+
+/// Start address of the translated block the instruction at the IP writes into
+fn written_block<I: InputSource, O: OutputSink, M: Memory>(p: &Program<I, O, M>) -> Option<Pos> {
+    let dest = match p.decode(p.ip) {
+        Ok(Opcode::Add(_, _, dest)) | Ok(Opcode::Mul(_, _, dest)) | Ok(Opcode::LessThan(_, _, dest))
+        | Ok(Opcode::Equals(_, _, dest)) | Ok(Opcode::In(dest)) => dest,
+        _ => return None
+    };
+    match dest {
+        Mode::Positional(address) => block_at(address),
+        Mode::Relative(offset) => match p.relative_base.checked_add(offset) {
+            Some(address) if address >= 0 => block_at(address as Pos),
+            _ => None
+        },
+        Mode::Immediate(_) => None
+    }
+}
+
+/// Translated program with the emulator state it runs on
+pub struct Translated<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Emulator state, shared with the interpreter fallback
+    pub program: Program<I, O, M>,
+
+    /// Start addresses of the blocks whose code was overwritten. These only run on the
+    /// interpreter from then on.
+    pub modified: BTreeSet<Pos>
+}
+
+impl Translated {
+    /// Translated program with the image loaded at address 0
+    pub fn new() -> Self {
+        Translated::from_program(Program::from_image(IMAGE))
+    }
+}
+
+impl Default for Translated {
+    fn default() -> Self {
+        Translated::new()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Translated<I, O, M> {
+    /// Run translated code on an existing emulator state. Blocks whose code differs from the
+    /// image, for example after patching the program, are interpreted.
+    pub fn from_program(program: Program<I, O, M>) -> Self {
+        let modified = BLOCKS.iter()
+            .filter(|&&(start, end)| {
+                (start..end).any(|address| program.read(address) != IMAGE.get(address).copied().unwrap_or(0))
+            })
+            .map(|&(start, _)| start)
+            .collect();
+        Translated { program, modified }
+    }
+
+    /// Execute until the program halts or needs input, like `Program::run`
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        loop {
+            let p = &mut self.program;
+            match p.ip {
+                ip if self.modified.contains(&ip) => fallback!(p, self.modified),
+                0 => {
+                    // 0000  add [0], [0] -> [3]
+                    let a = p.read(0);
+                    let b = p.read(0);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 0)
+                    };
+                    p.write(3, v);
+                    self.modified.insert(0);
+                    p.ip = 4;
+                    continue;
+                }
+                _ => fallback!(p, self.modified)
+            }
+        }
+    }
+}
@@ -0,0 +1,559 @@
+// Generated by `intcode::Program::translate`. Do not edit.
+//
+// 219 words of code in 2 blocks, translated from a 678 word image.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use intcode::{Event, ExitReason, InputSource, Memory, Mode, Opcode, OutputSink, PagedMemory, Pos, Program, VmError};
+
+/// Memory image the module was translated from
+pub const IMAGE: &[isize] = &[
+    3, 225, 1, 225, 6, 6, 1100, 1, 238, 225, 104, 0, 1102, 67, 92, 225,
+    1101, 14, 84, 225, 1002, 217, 69, 224, 101, -5175, 224, 224, 4, 224, 102, 8,
+    223, 223, 101, 2, 224, 224, 1, 224, 223, 223, 1, 214, 95, 224, 101, -127,
+    224, 224, 4, 224, 102, 8, 223, 223, 101, 3, 224, 224, 1, 223, 224, 223,
+    1101, 8, 41, 225, 2, 17, 91, 224, 1001, 224, -518, 224, 4, 224, 1002, 223,
+    8, 223, 101, 2, 224, 224, 1, 223, 224, 223, 1101, 37, 27, 225, 1101, 61,
+    11, 225, 101, 44, 66, 224, 101, -85, 224, 224, 4, 224, 1002, 223, 8, 223,
+    101, 6, 224, 224, 1, 224, 223, 223, 1102, 7, 32, 224, 101, -224, 224, 224,
+    4, 224, 102, 8, 223, 223, 1001, 224, 6, 224, 1, 224, 223, 223, 1001, 14,
+    82, 224, 101, -174, 224, 224, 4, 224, 102, 8, 223, 223, 101, 7, 224, 224,
+    1, 223, 224, 223, 102, 65, 210, 224, 101, -5525, 224, 224, 4, 224, 102, 8,
+    223, 223, 101, 3, 224, 224, 1, 224, 223, 223, 1101, 81, 9, 224, 101, -90,
+    224, 224, 4, 224, 102, 8, 223, 223, 1001, 224, 3, 224, 1, 224, 223, 223,
+    1101, 71, 85, 225, 1102, 61, 66, 225, 1102, 75, 53, 225, 4, 223, 99, 0,
+    0, 0, 677, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1105, 0,
+    99999, 1105, 227, 247, 1105, 1, 99999, 1005, 227, 99999, 1005, 0, 256, 1105, 1, 99999,
+    1106, 227, 99999, 1106, 0, 265, 1105, 1, 99999, 1006, 0, 99999, 1006, 227, 274, 1105,
+    1, 99999, 1105, 1, 280, 1105, 1, 99999, 1, 225, 225, 225, 1101, 294, 0, 0,
+    105, 1, 0, 1105, 1, 99999, 1106, 0, 300, 1105, 1, 99999, 1, 225, 225, 225,
+    1101, 314, 0, 0, 106, 0, 0, 1105, 1, 99999, 8, 226, 226, 224, 102, 2,
+    223, 223, 1005, 224, 329, 1001, 223, 1, 223, 1108, 677, 677, 224, 1002, 223, 2,
+    223, 1006, 224, 344, 101, 1, 223, 223, 1007, 226, 677, 224, 102, 2, 223, 223,
+    1005, 224, 359, 101, 1, 223, 223, 1007, 677, 677, 224, 1002, 223, 2, 223, 1006,
+    224, 374, 101, 1, 223, 223, 1108, 677, 226, 224, 1002, 223, 2, 223, 1005, 224,
+    389, 1001, 223, 1, 223, 108, 226, 677, 224, 102, 2, 223, 223, 1006, 224, 404,
+    101, 1, 223, 223, 1108, 226, 677, 224, 102, 2, 223, 223, 1005, 224, 419, 101,
+    1, 223, 223, 1008, 677, 677, 224, 102, 2, 223, 223, 1005, 224, 434, 101, 1,
+    223, 223, 7, 677, 226, 224, 1002, 223, 2, 223, 1005, 224, 449, 101, 1, 223,
+    223, 1008, 226, 226, 224, 102, 2, 223, 223, 1005, 224, 464, 1001, 223, 1, 223,
+    107, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 479, 1001, 223, 1, 223, 107,
+    677, 677, 224, 102, 2, 223, 223, 1005, 224, 494, 1001, 223, 1, 223, 1008, 226,
+    677, 224, 102, 2, 223, 223, 1006, 224, 509, 1001, 223, 1, 223, 1107, 677, 226,
+    224, 102, 2, 223, 223, 1005, 224, 524, 101, 1, 223, 223, 1007, 226, 226, 224,
+    1002, 223, 2, 223, 1006, 224, 539, 1001, 223, 1, 223, 107, 226, 226, 224, 102,
+    2, 223, 223, 1006, 224, 554, 101, 1, 223, 223, 108, 677, 677, 224, 1002, 223,
+    2, 223, 1006, 224, 569, 1001, 223, 1, 223, 7, 226, 677, 224, 102, 2, 223,
+    223, 1006, 224, 584, 1001, 223, 1, 223, 8, 677, 226, 224, 102, 2, 223, 223,
+    1005, 224, 599, 101, 1, 223, 223, 1107, 677, 677, 224, 1002, 223, 2, 223, 1005,
+    224, 614, 101, 1, 223, 223, 8, 226, 677, 224, 102, 2, 223, 223, 1005, 224,
+    629, 1001, 223, 1, 223, 7, 226, 226, 224, 1002, 223, 2, 223, 1006, 224, 644,
+    1001, 223, 1, 223, 108, 226, 226, 224, 1002, 223, 2, 223, 1006, 224, 659, 101,
+    1, 223, 223, 1107, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 674, 101, 1,
+    223, 223, 4, 223, 99, 226,
+];
+
+/// Start and end address of every translated block
+const BLOCKS: &[(Pos, Pos)] = &[
+    (0, 6), (10, 223),
+];
+
+/// Execute the instruction at the IP, or at `$addr`, with the interpreter
+#[allow(unused_macros)]
+macro_rules! interpret {
+    ($p:ident) => {
+        match $p.step()? {
+            Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+            Event::Halted => return Ok(ExitReason::Halted),
+            _ => continue
+        }
+    };
+    ($p:ident, $addr:expr) => {{
+        $p.ip = $addr;
+        interpret!($p)
+    }};
+}
+
+/// Address of a relative parameter of the instruction at `$addr`. Negative addresses and
+/// overflow are left to the interpreter.
+#[allow(unused_macros)]
+macro_rules! rel {
+    ($p:ident, $offset:expr, $addr:expr) => {
+        match $p.relative_base.checked_add($offset) {
+            Some(address) if address >= 0 => address as Pos,
+            _ => interpret!($p, $addr)
+        }
+    };
+}
+
+/// Execute the instruction at the IP with the interpreter, noting writes into translated code
+#[allow(unused_macros)]
+macro_rules! fallback {
+    ($p:ident, $modified:expr) => {{
+        if let Some(block) = written_block($p) {
+            $modified.insert(block);
+        }
+        interpret!($p)
+    }};
+}
+
+/// Start address of the translated block containing `address`
+fn block_at(address: Pos) -> Option<Pos> {
+    let index = BLOCKS.partition_point(|&(start, _)| start <= address).checked_sub(1)?;
+    let (start, end) = BLOCKS[index];
+    if address < end { Some(start) } else { None }
+}
+
+/// Start address of the translated block the instruction at the IP writes into
+fn written_block<I: InputSource, O: OutputSink, M: Memory>(p: &Program<I, O, M>) -> Option<Pos> {
+    let dest = match p.decode(p.ip) {
+        Ok(Opcode::Add(_, _, dest)) | Ok(Opcode::Mul(_, _, dest)) | Ok(Opcode::LessThan(_, _, dest))
+        | Ok(Opcode::Equals(_, _, dest)) | Ok(Opcode::In(dest)) => dest,
+        _ => return None
+    };
+    match dest {
+        Mode::Positional(address) => block_at(address),
+        Mode::Relative(offset) => match p.relative_base.checked_add(offset) {
+            Some(address) if address >= 0 => block_at(address as Pos),
+            _ => None
+        },
+        Mode::Immediate(_) => None
+    }
+}
+
+/// Translated program with the emulator state it runs on
+pub struct Translated<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Emulator state, shared with the interpreter fallback
+    pub program: Program<I, O, M>,
+
+    /// Start addresses of the blocks whose code was overwritten. These only run on the
+    /// interpreter from then on.
+    pub modified: BTreeSet<Pos>
+}
+
+impl Translated {
+    /// Translated program with the image loaded at address 0
+    pub fn new() -> Self {
+        Translated::from_program(Program::from_image(IMAGE))
+    }
+}
+
+impl Default for Translated {
+    fn default() -> Self {
+        Translated::new()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Translated<I, O, M> {
+    /// Run translated code on an existing emulator state. Blocks whose code differs from the
+    /// image, for example after patching the program, are interpreted.
+    pub fn from_program(program: Program<I, O, M>) -> Self {
+        let modified = BLOCKS.iter()
+            .filter(|&&(start, end)| {
+                (start..end).any(|address| program.read(address) != IMAGE.get(address).copied().unwrap_or(0))
+            })
+            .map(|&(start, _)| start)
+            .collect();
+        Translated { program, modified }
+    }
+
+    /// Execute until the program halts or needs input, like `Program::run`
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        loop {
+            let p = &mut self.program;
+            match p.ip {
+                ip if self.modified.contains(&ip) => fallback!(p, self.modified),
+                0 => {
+                    // 0000  in -> [225]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(225, v);
+                    // 0002  add [225], [6] -> [6]
+                    let a = p.read(225);
+                    let b = p.read(6);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 2)
+                    };
+                    p.write(6, v);
+                    p.ip = 6;
+                    continue;
+                }
+                10 => {
+                    // 0010  out #0
+                    p.write_output(0);
+                    // 0012  mul #67, #92 -> [225]
+                    let v = match isize::checked_mul(67, 92) {
+                        Some(v) => v,
+                        None => interpret!(p, 12)
+                    };
+                    p.write(225, v);
+                    // 0016  add #14, #84 -> [225]
+                    let v = match isize::checked_add(14, 84) {
+                        Some(v) => v,
+                        None => interpret!(p, 16)
+                    };
+                    p.write(225, v);
+                    // 0020  mul [217], #69 -> [224]
+                    let a = p.read(217);
+                    let v = match isize::checked_mul(a, 69) {
+                        Some(v) => v,
+                        None => interpret!(p, 20)
+                    };
+                    p.write(224, v);
+                    // 0024  add #-5175, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-5175, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 24)
+                    };
+                    p.write(224, v);
+                    // 0028  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0030  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 30)
+                    };
+                    p.write(223, v);
+                    // 0034  add #2, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 34)
+                    };
+                    p.write(224, v);
+                    // 0038  add [224], [223] -> [223]
+                    let a = p.read(224);
+                    let b = p.read(223);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 38)
+                    };
+                    p.write(223, v);
+                    // 0042  add [214], [95] -> [224]
+                    let a = p.read(214);
+                    let b = p.read(95);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 42)
+                    };
+                    p.write(224, v);
+                    // 0046  add #-127, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-127, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 46)
+                    };
+                    p.write(224, v);
+                    // 0050  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0052  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 52)
+                    };
+                    p.write(223, v);
+                    // 0056  add #3, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(3, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 56)
+                    };
+                    p.write(224, v);
+                    // 0060  add [223], [224] -> [223]
+                    let a = p.read(223);
+                    let b = p.read(224);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 60)
+                    };
+                    p.write(223, v);
+                    // 0064  add #8, #41 -> [225]
+                    let v = match isize::checked_add(8, 41) {
+                        Some(v) => v,
+                        None => interpret!(p, 64)
+                    };
+                    p.write(225, v);
+                    // 0068  mul [17], [91] -> [224]
+                    let a = p.read(17);
+                    let b = p.read(91);
+                    let v = match isize::checked_mul(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 68)
+                    };
+                    p.write(224, v);
+                    // 0072  add [224], #-518 -> [224]
+                    let a = p.read(224);
+                    let v = match isize::checked_add(a, -518) {
+                        Some(v) => v,
+                        None => interpret!(p, 72)
+                    };
+                    p.write(224, v);
+                    // 0076  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0078  mul [223], #8 -> [223]
+                    let a = p.read(223);
+                    let v = match isize::checked_mul(a, 8) {
+                        Some(v) => v,
+                        None => interpret!(p, 78)
+                    };
+                    p.write(223, v);
+                    // 0082  add #2, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 82)
+                    };
+                    p.write(224, v);
+                    // 0086  add [223], [224] -> [223]
+                    let a = p.read(223);
+                    let b = p.read(224);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 86)
+                    };
+                    p.write(223, v);
+                    // 0090  add #37, #27 -> [225]
+                    let v = match isize::checked_add(37, 27) {
+                        Some(v) => v,
+                        None => interpret!(p, 90)
+                    };
+                    p.write(225, v);
+                    // 0094  add #61, #11 -> [225]
+                    let v = match isize::checked_add(61, 11) {
+                        Some(v) => v,
+                        None => interpret!(p, 94)
+                    };
+                    p.write(225, v);
+                    // 0098  add #44, [66] -> [224]
+                    let b = p.read(66);
+                    let v = match isize::checked_add(44, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 98)
+                    };
+                    p.write(224, v);
+                    // 0102  add #-85, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-85, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 102)
+                    };
+                    p.write(224, v);
+                    // 0106  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0108  mul [223], #8 -> [223]
+                    let a = p.read(223);
+                    let v = match isize::checked_mul(a, 8) {
+                        Some(v) => v,
+                        None => interpret!(p, 108)
+                    };
+                    p.write(223, v);
+                    // 0112  add #6, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(6, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 112)
+                    };
+                    p.write(224, v);
+                    // 0116  add [224], [223] -> [223]
+                    let a = p.read(224);
+                    let b = p.read(223);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 116)
+                    };
+                    p.write(223, v);
+                    // 0120  mul #7, #32 -> [224]
+                    let v = match isize::checked_mul(7, 32) {
+                        Some(v) => v,
+                        None => interpret!(p, 120)
+                    };
+                    p.write(224, v);
+                    // 0124  add #-224, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-224, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 124)
+                    };
+                    p.write(224, v);
+                    // 0128  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0130  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 130)
+                    };
+                    p.write(223, v);
+                    // 0134  add [224], #6 -> [224]
+                    let a = p.read(224);
+                    let v = match isize::checked_add(a, 6) {
+                        Some(v) => v,
+                        None => interpret!(p, 134)
+                    };
+                    p.write(224, v);
+                    // 0138  add [224], [223] -> [223]
+                    let a = p.read(224);
+                    let b = p.read(223);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 138)
+                    };
+                    p.write(223, v);
+                    // 0142  add [14], #82 -> [224]
+                    let a = p.read(14);
+                    let v = match isize::checked_add(a, 82) {
+                        Some(v) => v,
+                        None => interpret!(p, 142)
+                    };
+                    p.write(224, v);
+                    // 0146  add #-174, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-174, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 146)
+                    };
+                    p.write(224, v);
+                    // 0150  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0152  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 152)
+                    };
+                    p.write(223, v);
+                    // 0156  add #7, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(7, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 156)
+                    };
+                    p.write(224, v);
+                    // 0160  add [223], [224] -> [223]
+                    let a = p.read(223);
+                    let b = p.read(224);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 160)
+                    };
+                    p.write(223, v);
+                    // 0164  mul #65, [210] -> [224]
+                    let b = p.read(210);
+                    let v = match isize::checked_mul(65, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 164)
+                    };
+                    p.write(224, v);
+                    // 0168  add #-5525, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-5525, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 168)
+                    };
+                    p.write(224, v);
+                    // 0172  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0174  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 174)
+                    };
+                    p.write(223, v);
+                    // 0178  add #3, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(3, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 178)
+                    };
+                    p.write(224, v);
+                    // 0182  add [224], [223] -> [223]
+                    let a = p.read(224);
+                    let b = p.read(223);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 182)
+                    };
+                    p.write(223, v);
+                    // 0186  add #81, #9 -> [224]
+                    let v = match isize::checked_add(81, 9) {
+                        Some(v) => v,
+                        None => interpret!(p, 186)
+                    };
+                    p.write(224, v);
+                    // 0190  add #-90, [224] -> [224]
+                    let b = p.read(224);
+                    let v = match isize::checked_add(-90, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 190)
+                    };
+                    p.write(224, v);
+                    // 0194  out [224]
+                    let a = p.read(224);
+                    p.write_output(a);
+                    // 0196  mul #8, [223] -> [223]
+                    let b = p.read(223);
+                    let v = match isize::checked_mul(8, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 196)
+                    };
+                    p.write(223, v);
+                    // 0200  add [224], #3 -> [224]
+                    let a = p.read(224);
+                    let v = match isize::checked_add(a, 3) {
+                        Some(v) => v,
+                        None => interpret!(p, 200)
+                    };
+                    p.write(224, v);
+                    // 0204  add [224], [223] -> [223]
+                    let a = p.read(224);
+                    let b = p.read(223);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 204)
+                    };
+                    p.write(223, v);
+                    // 0208  add #71, #85 -> [225]
+                    let v = match isize::checked_add(71, 85) {
+                        Some(v) => v,
+                        None => interpret!(p, 208)
+                    };
+                    p.write(225, v);
+                    // 0212  mul #61, #66 -> [225]
+                    let v = match isize::checked_mul(61, 66) {
+                        Some(v) => v,
+                        None => interpret!(p, 212)
+                    };
+                    p.write(225, v);
+                    // 0216  mul #75, #53 -> [225]
+                    let v = match isize::checked_mul(75, 53) {
+                        Some(v) => v,
+                        None => interpret!(p, 216)
+                    };
+                    p.write(225, v);
+                    // 0220  out [223]
+                    let a = p.read(223);
+                    p.write_output(a);
+                    // 0222  halt
+                    p.ip = 222;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                _ => fallback!(p, self.modified)
+            }
+        }
+    }
+}
@@ -0,0 +1,1374 @@
+// Generated by `intcode::Program::translate`. Do not edit.
+//
+// 503 words of code in 56 blocks, translated from a 515 word image.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use intcode::{Event, ExitReason, InputSource, Memory, Mode, Opcode, OutputSink, PagedMemory, Pos, Program, VmError};
+
+/// Memory image the module was translated from
+pub const IMAGE: &[isize] = &[
+    3, 8, 1001, 8, 10, 8, 105, 1, 0, 0, 21, 38, 47, 64, 89, 110,
+    191, 272, 353, 434, 99999, 3, 9, 101, 4, 9, 9, 102, 3, 9, 9, 101,
+    5, 9, 9, 4, 9, 99, 3, 9, 1002, 9, 5, 9, 4, 9, 99, 3,
+    9, 101, 2, 9, 9, 102, 5, 9, 9, 1001, 9, 5, 9, 4, 9, 99,
+    3, 9, 1001, 9, 5, 9, 102, 4, 9, 9, 1001, 9, 5, 9, 1002, 9,
+    2, 9, 1001, 9, 3, 9, 4, 9, 99, 3, 9, 102, 2, 9, 9, 101,
+    4, 9, 9, 1002, 9, 4, 9, 1001, 9, 4, 9, 4, 9, 99, 3, 9,
+    101, 1, 9, 9, 4, 9, 3, 9, 101, 1, 9, 9, 4, 9, 3, 9,
+    1002, 9, 2, 9, 4, 9, 3, 9, 102, 2, 9, 9, 4, 9, 3, 9,
+    101, 2, 9, 9, 4, 9, 3, 9, 101, 1, 9, 9, 4, 9, 3, 9,
+    1001, 9, 2, 9, 4, 9, 3, 9, 102, 2, 9, 9, 4, 9, 3, 9,
+    1001, 9, 1, 9, 4, 9, 3, 9, 101, 2, 9, 9, 4, 9, 99, 3,
+    9, 101, 2, 9, 9, 4, 9, 3, 9, 1002, 9, 2, 9, 4, 9, 3,
+    9, 102, 2, 9, 9, 4, 9, 3, 9, 101, 2, 9, 9, 4, 9, 3,
+    9, 1002, 9, 2, 9, 4, 9, 3, 9, 101, 2, 9, 9, 4, 9, 3,
+    9, 1002, 9, 2, 9, 4, 9, 3, 9, 101, 2, 9, 9, 4, 9, 3,
+    9, 1001, 9, 2, 9, 4, 9, 3, 9, 102, 2, 9, 9, 4, 9, 99,
+    3, 9, 1001, 9, 2, 9, 4, 9, 3, 9, 1001, 9, 2, 9, 4, 9,
+    3, 9, 101, 1, 9, 9, 4, 9, 3, 9, 1001, 9, 1, 9, 4, 9,
+    3, 9, 1001, 9, 1, 9, 4, 9, 3, 9, 1002, 9, 2, 9, 4, 9,
+    3, 9, 102, 2, 9, 9, 4, 9, 3, 9, 1002, 9, 2, 9, 4, 9,
+    3, 9, 101, 1, 9, 9, 4, 9, 3, 9, 101, 1, 9, 9, 4, 9,
+    99, 3, 9, 102, 2, 9, 9, 4, 9, 3, 9, 1001, 9, 1, 9, 4,
+    9, 3, 9, 1001, 9, 1, 9, 4, 9, 3, 9, 1002, 9, 2, 9, 4,
+    9, 3, 9, 102, 2, 9, 9, 4, 9, 3, 9, 1001, 9, 1, 9, 4,
+    9, 3, 9, 1001, 9, 2, 9, 4, 9, 3, 9, 102, 2, 9, 9, 4,
+    9, 3, 9, 1001, 9, 1, 9, 4, 9, 3, 9, 1002, 9, 2, 9, 4,
+    9, 99, 3, 9, 101, 1, 9, 9, 4, 9, 3, 9, 102, 2, 9, 9,
+    4, 9, 3, 9, 1001, 9, 2, 9, 4, 9, 3, 9, 1001, 9, 2, 9,
+    4, 9, 3, 9, 102, 2, 9, 9, 4, 9, 3, 9, 102, 2, 9, 9,
+    4, 9, 3, 9, 1001, 9, 2, 9, 4, 9, 3, 9, 1002, 9, 2, 9,
+    4, 9, 3, 9, 1002, 9, 2, 9, 4, 9, 3, 9, 1002, 9, 2, 9,
+    4, 9, 99,
+];
+
+/// Start and end address of every translated block
+const BLOCKS: &[(Pos, Pos)] = &[
+    (0, 9), (21, 38), (38, 47), (47, 64), (64, 89), (89, 110), (110, 118), (118, 126),
+    (126, 134), (134, 142), (142, 150), (150, 158), (158, 166), (166, 174), (174, 182), (182, 191),
+    (191, 199), (199, 207), (207, 215), (215, 223), (223, 231), (231, 239), (239, 247), (247, 255),
+    (255, 263), (263, 272), (272, 280), (280, 288), (288, 296), (296, 304), (304, 312), (312, 320),
+    (320, 328), (328, 336), (336, 344), (344, 353), (353, 361), (361, 369), (369, 377), (377, 385),
+    (385, 393), (393, 401), (401, 409), (409, 417), (417, 425), (425, 434), (434, 442), (442, 450),
+    (450, 458), (458, 466), (466, 474), (474, 482), (482, 490), (490, 498), (498, 506), (506, 515),
+];
+
+/// Execute the instruction at the IP, or at `$addr`, with the interpreter
+#[allow(unused_macros)]
+macro_rules! interpret {
+    ($p:ident) => {
+        match $p.step()? {
+            Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+            Event::Halted => return Ok(ExitReason::Halted),
+            _ => continue
+        }
+    };
+    ($p:ident, $addr:expr) => {{
+        $p.ip = $addr;
+        interpret!($p)
+    }};
+}
+
+/// Address of a relative parameter of the instruction at `$addr`. Negative addresses and
+/// overflow are left to the interpreter.
+#[allow(unused_macros)]
+macro_rules! rel {
+    ($p:ident, $offset:expr, $addr:expr) => {
+        match $p.relative_base.checked_add($offset) {
+            Some(address) if address >= 0 => address as Pos,
+            _ => interpret!($p, $addr)
+        }
+    };
+}
+
+/// Execute the instruction at the IP with the interpreter, noting writes into translated code
+#[allow(unused_macros)]
+macro_rules! fallback {
+    ($p:ident, $modified:expr) => {{
+        if let Some(block) = written_block($p) {
+            $modified.insert(block);
+        }
+        interpret!($p)
+    }};
+}
+
+/// Start address of the translated block containing `address`
+fn block_at(address: Pos) -> Option<Pos> {
+    let index = BLOCKS.partition_point(|&(start, _)| start <= address).checked_sub(1)?;
+    let (start, end) = BLOCKS[index];
+    if address < end { Some(start) } else { None }
+}
+
+/// Start address of the translated block the instruction at the IP writes into
+fn written_block<I: InputSource, O: OutputSink, M: Memory>(p: &Program<I, O, M>) -> Option<Pos> {
+    let dest = match p.decode(p.ip) {
+        Ok(Opcode::Add(_, _, dest)) | Ok(Opcode::Mul(_, _, dest)) | Ok(Opcode::LessThan(_, _, dest))
+        | Ok(Opcode::Equals(_, _, dest)) | Ok(Opcode::In(dest)) => dest,
+        _ => return None
+    };
+    match dest {
+        Mode::Positional(address) => block_at(address),
+        Mode::Relative(offset) => match p.relative_base.checked_add(offset) {
+            Some(address) if address >= 0 => block_at(address as Pos),
+            _ => None
+        },
+        Mode::Immediate(_) => None
+    }
+}
+
+/// Translated program with the emulator state it runs on
+pub struct Translated<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Emulator state, shared with the interpreter fallback
+    pub program: Program<I, O, M>,
+
+    /// Start addresses of the blocks whose code was overwritten. These only run on the
+    /// interpreter from then on.
+    pub modified: BTreeSet<Pos>
+}
+
+impl Translated {
+    /// Translated program with the image loaded at address 0
+    pub fn new() -> Self {
+        Translated::from_program(Program::from_image(IMAGE))
+    }
+}
+
+impl Default for Translated {
+    fn default() -> Self {
+        Translated::new()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Translated<I, O, M> {
+    /// Run translated code on an existing emulator state. Blocks whose code differs from the
+    /// image, for example after patching the program, are interpreted.
+    pub fn from_program(program: Program<I, O, M>) -> Self {
+        let modified = BLOCKS.iter()
+            .filter(|&&(start, end)| {
+                (start..end).any(|address| program.read(address) != IMAGE.get(address).copied().unwrap_or(0))
+            })
+            .map(|&(start, _)| start)
+            .collect();
+        Translated { program, modified }
+    }
+
+    /// Execute until the program halts or needs input, like `Program::run`
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        loop {
+            let p = &mut self.program;
+            match p.ip {
+                ip if self.modified.contains(&ip) => fallback!(p, self.modified),
+                0 => {
+                    // 0000  in -> [8]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(8, v);
+                    self.modified.insert(0);
+                    p.ip = 2;
+                    continue;
+                }
+                21 => {
+                    // 0021  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0023  add #4, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(4, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 23)
+                    };
+                    p.write(9, v);
+                    // 0027  mul #3, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(3, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 27)
+                    };
+                    p.write(9, v);
+                    // 0031  add #5, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(5, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 31)
+                    };
+                    p.write(9, v);
+                    // 0035  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0037  halt
+                    p.ip = 37;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                38 => {
+                    // 0038  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0040  mul [9], #5 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 5) {
+                        Some(v) => v,
+                        None => interpret!(p, 40)
+                    };
+                    p.write(9, v);
+                    // 0044  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0046  halt
+                    p.ip = 46;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                47 => {
+                    // 0047  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0049  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 49)
+                    };
+                    p.write(9, v);
+                    // 0053  mul #5, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(5, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 53)
+                    };
+                    p.write(9, v);
+                    // 0057  add [9], #5 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 5) {
+                        Some(v) => v,
+                        None => interpret!(p, 57)
+                    };
+                    p.write(9, v);
+                    // 0061  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0063  halt
+                    p.ip = 63;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                64 => {
+                    // 0064  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0066  add [9], #5 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 5) {
+                        Some(v) => v,
+                        None => interpret!(p, 66)
+                    };
+                    p.write(9, v);
+                    // 0070  mul #4, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(4, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 70)
+                    };
+                    p.write(9, v);
+                    // 0074  add [9], #5 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 5) {
+                        Some(v) => v,
+                        None => interpret!(p, 74)
+                    };
+                    p.write(9, v);
+                    // 0078  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 78)
+                    };
+                    p.write(9, v);
+                    // 0082  add [9], #3 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 3) {
+                        Some(v) => v,
+                        None => interpret!(p, 82)
+                    };
+                    p.write(9, v);
+                    // 0086  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0088  halt
+                    p.ip = 88;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                89 => {
+                    // 0089  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0091  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 91)
+                    };
+                    p.write(9, v);
+                    // 0095  add #4, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(4, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 95)
+                    };
+                    p.write(9, v);
+                    // 0099  mul [9], #4 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 4) {
+                        Some(v) => v,
+                        None => interpret!(p, 99)
+                    };
+                    p.write(9, v);
+                    // 0103  add [9], #4 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 4) {
+                        Some(v) => v,
+                        None => interpret!(p, 103)
+                    };
+                    p.write(9, v);
+                    // 0107  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0109  halt
+                    p.ip = 109;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                110 => {
+                    // 0110  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0112  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 112)
+                    };
+                    p.write(9, v);
+                    // 0116  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 118;
+                    continue;
+                }
+                118 => {
+                    // 0118  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0120  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 120)
+                    };
+                    p.write(9, v);
+                    // 0124  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 126;
+                    continue;
+                }
+                126 => {
+                    // 0126  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0128  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 128)
+                    };
+                    p.write(9, v);
+                    // 0132  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 134;
+                    continue;
+                }
+                134 => {
+                    // 0134  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0136  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 136)
+                    };
+                    p.write(9, v);
+                    // 0140  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 142;
+                    continue;
+                }
+                142 => {
+                    // 0142  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0144  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 144)
+                    };
+                    p.write(9, v);
+                    // 0148  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 150;
+                    continue;
+                }
+                150 => {
+                    // 0150  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0152  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 152)
+                    };
+                    p.write(9, v);
+                    // 0156  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 158;
+                    continue;
+                }
+                158 => {
+                    // 0158  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0160  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 160)
+                    };
+                    p.write(9, v);
+                    // 0164  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 166;
+                    continue;
+                }
+                166 => {
+                    // 0166  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0168  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 168)
+                    };
+                    p.write(9, v);
+                    // 0172  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 174;
+                    continue;
+                }
+                174 => {
+                    // 0174  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0176  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 176)
+                    };
+                    p.write(9, v);
+                    // 0180  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 182;
+                    continue;
+                }
+                182 => {
+                    // 0182  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0184  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 184)
+                    };
+                    p.write(9, v);
+                    // 0188  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0190  halt
+                    p.ip = 190;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                191 => {
+                    // 0191  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0193  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 193)
+                    };
+                    p.write(9, v);
+                    // 0197  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 199;
+                    continue;
+                }
+                199 => {
+                    // 0199  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0201  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 201)
+                    };
+                    p.write(9, v);
+                    // 0205  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 207;
+                    continue;
+                }
+                207 => {
+                    // 0207  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0209  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 209)
+                    };
+                    p.write(9, v);
+                    // 0213  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 215;
+                    continue;
+                }
+                215 => {
+                    // 0215  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0217  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 217)
+                    };
+                    p.write(9, v);
+                    // 0221  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 223;
+                    continue;
+                }
+                223 => {
+                    // 0223  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0225  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 225)
+                    };
+                    p.write(9, v);
+                    // 0229  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 231;
+                    continue;
+                }
+                231 => {
+                    // 0231  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0233  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 233)
+                    };
+                    p.write(9, v);
+                    // 0237  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 239;
+                    continue;
+                }
+                239 => {
+                    // 0239  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0241  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 241)
+                    };
+                    p.write(9, v);
+                    // 0245  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 247;
+                    continue;
+                }
+                247 => {
+                    // 0247  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0249  add #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 249)
+                    };
+                    p.write(9, v);
+                    // 0253  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 255;
+                    continue;
+                }
+                255 => {
+                    // 0255  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0257  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 257)
+                    };
+                    p.write(9, v);
+                    // 0261  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 263;
+                    continue;
+                }
+                263 => {
+                    // 0263  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0265  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 265)
+                    };
+                    p.write(9, v);
+                    // 0269  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0271  halt
+                    p.ip = 271;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                272 => {
+                    // 0272  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0274  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 274)
+                    };
+                    p.write(9, v);
+                    // 0278  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 280;
+                    continue;
+                }
+                280 => {
+                    // 0280  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0282  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 282)
+                    };
+                    p.write(9, v);
+                    // 0286  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 288;
+                    continue;
+                }
+                288 => {
+                    // 0288  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0290  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 290)
+                    };
+                    p.write(9, v);
+                    // 0294  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 296;
+                    continue;
+                }
+                296 => {
+                    // 0296  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0298  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 298)
+                    };
+                    p.write(9, v);
+                    // 0302  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 304;
+                    continue;
+                }
+                304 => {
+                    // 0304  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0306  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 306)
+                    };
+                    p.write(9, v);
+                    // 0310  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 312;
+                    continue;
+                }
+                312 => {
+                    // 0312  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0314  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 314)
+                    };
+                    p.write(9, v);
+                    // 0318  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 320;
+                    continue;
+                }
+                320 => {
+                    // 0320  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0322  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 322)
+                    };
+                    p.write(9, v);
+                    // 0326  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 328;
+                    continue;
+                }
+                328 => {
+                    // 0328  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0330  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 330)
+                    };
+                    p.write(9, v);
+                    // 0334  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 336;
+                    continue;
+                }
+                336 => {
+                    // 0336  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0338  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 338)
+                    };
+                    p.write(9, v);
+                    // 0342  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 344;
+                    continue;
+                }
+                344 => {
+                    // 0344  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0346  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 346)
+                    };
+                    p.write(9, v);
+                    // 0350  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0352  halt
+                    p.ip = 352;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                353 => {
+                    // 0353  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0355  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 355)
+                    };
+                    p.write(9, v);
+                    // 0359  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 361;
+                    continue;
+                }
+                361 => {
+                    // 0361  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0363  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 363)
+                    };
+                    p.write(9, v);
+                    // 0367  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 369;
+                    continue;
+                }
+                369 => {
+                    // 0369  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0371  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 371)
+                    };
+                    p.write(9, v);
+                    // 0375  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 377;
+                    continue;
+                }
+                377 => {
+                    // 0377  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0379  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 379)
+                    };
+                    p.write(9, v);
+                    // 0383  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 385;
+                    continue;
+                }
+                385 => {
+                    // 0385  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0387  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 387)
+                    };
+                    p.write(9, v);
+                    // 0391  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 393;
+                    continue;
+                }
+                393 => {
+                    // 0393  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0395  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 395)
+                    };
+                    p.write(9, v);
+                    // 0399  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 401;
+                    continue;
+                }
+                401 => {
+                    // 0401  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0403  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 403)
+                    };
+                    p.write(9, v);
+                    // 0407  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 409;
+                    continue;
+                }
+                409 => {
+                    // 0409  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0411  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 411)
+                    };
+                    p.write(9, v);
+                    // 0415  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 417;
+                    continue;
+                }
+                417 => {
+                    // 0417  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0419  add [9], #1 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 419)
+                    };
+                    p.write(9, v);
+                    // 0423  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 425;
+                    continue;
+                }
+                425 => {
+                    // 0425  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0427  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 427)
+                    };
+                    p.write(9, v);
+                    // 0431  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0433  halt
+                    p.ip = 433;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                434 => {
+                    // 0434  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0436  add #1, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_add(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 436)
+                    };
+                    p.write(9, v);
+                    // 0440  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 442;
+                    continue;
+                }
+                442 => {
+                    // 0442  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0444  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 444)
+                    };
+                    p.write(9, v);
+                    // 0448  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 450;
+                    continue;
+                }
+                450 => {
+                    // 0450  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0452  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 452)
+                    };
+                    p.write(9, v);
+                    // 0456  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 458;
+                    continue;
+                }
+                458 => {
+                    // 0458  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0460  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 460)
+                    };
+                    p.write(9, v);
+                    // 0464  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 466;
+                    continue;
+                }
+                466 => {
+                    // 0466  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0468  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 468)
+                    };
+                    p.write(9, v);
+                    // 0472  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 474;
+                    continue;
+                }
+                474 => {
+                    // 0474  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0476  mul #2, [9] -> [9]
+                    let b = p.read(9);
+                    let v = match isize::checked_mul(2, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 476)
+                    };
+                    p.write(9, v);
+                    // 0480  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 482;
+                    continue;
+                }
+                482 => {
+                    // 0482  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0484  add [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_add(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 484)
+                    };
+                    p.write(9, v);
+                    // 0488  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 490;
+                    continue;
+                }
+                490 => {
+                    // 0490  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0492  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 492)
+                    };
+                    p.write(9, v);
+                    // 0496  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 498;
+                    continue;
+                }
+                498 => {
+                    // 0498  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0500  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 500)
+                    };
+                    p.write(9, v);
+                    // 0504  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    p.ip = 506;
+                    continue;
+                }
+                506 => {
+                    // 0506  in -> [9]
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(9, v);
+                    // 0508  mul [9], #2 -> [9]
+                    let a = p.read(9);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 508)
+                    };
+                    p.write(9, v);
+                    // 0512  out [9]
+                    let a = p.read(9);
+                    p.write_output(a);
+                    // 0514  halt
+                    p.ip = 514;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                _ => fallback!(p, self.modified)
+            }
+        }
+    }
+}
@@ -0,0 +1,1244 @@
+// Generated by `intcode::Program::translate`. Do not edit.
+//
+// 533 words of code in 53 blocks, translated from a 971 word image.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use intcode::{Event, ExitReason, InputSource, Memory, Mode, Opcode, OutputSink, PagedMemory, Pos, Program, VmError};
+
+/// Memory image the module was translated from
+pub const IMAGE: &[isize] = &[
+    1102, 34463338, 34463338, 63, 1007, 63, 34463338, 63, 1005, 63, 53, 1102, 1, 3, 1000, 109,
+    988, 209, 12, 9, 1000, 209, 6, 209, 3, 203, 0, 1008, 1000, 1, 63, 1005,
+    63, 65, 1008, 1000, 2, 63, 1005, 63, 904, 1008, 1000, 0, 63, 1005, 63, 58,
+    4, 25, 104, 0, 99, 4, 0, 104, 0, 99, 4, 17, 104, 0, 99, 0,
+    0, 1102, 1, 21, 1004, 1101, 28, 0, 1016, 1101, 0, 27, 1010, 1102, 36, 1,
+    1008, 1102, 33, 1, 1013, 1101, 0, 22, 1012, 1101, 0, 37, 1011, 1102, 34, 1,
+    1017, 1102, 466, 1, 1027, 1102, 1, 484, 1029, 1102, 1, 699, 1024, 1102, 1, 1,
+    1021, 1101, 0, 0, 1020, 1102, 1, 24, 1015, 1101, 0, 473, 1026, 1101, 653, 0,
+    1022, 1102, 26, 1, 1007, 1102, 25, 1, 1006, 1101, 0, 39, 1014, 1102, 646, 1,
+    1023, 1101, 690, 0, 1025, 1102, 1, 29, 1019, 1101, 32, 0, 1018, 1101, 30, 0,
+    1002, 1101, 0, 20, 1001, 1102, 1, 38, 1005, 1102, 1, 23, 1003, 1101, 0, 31,
+    1000, 1101, 35, 0, 1009, 1101, 0, 493, 1028, 109, 5, 1208, 0, 37, 63, 1005,
+    63, 201, 1001, 64, 1, 64, 1106, 0, 203, 4, 187, 1002, 64, 2, 64, 109,
+    -4, 2107, 36, 8, 63, 1005, 63, 223, 1001, 64, 1, 64, 1105, 1, 225, 4,
+    209, 1002, 64, 2, 64, 109, 18, 21107, 40, 41, -9, 1005, 1010, 243, 4, 231,
+    1105, 1, 247, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 6, 21107, 41, 40,
+    -9, 1005, 1016, 267, 1001, 64, 1, 64, 1106, 0, 269, 4, 253, 1002, 64, 2,
+    64, 109, -19, 21102, 42, 1, 5, 1008, 1011, 42, 63, 1005, 63, 291, 4, 275,
+    1105, 1, 295, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 15, 1205, 0, 309,
+    4, 301, 1105, 1, 313, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -27, 2101,
+    0, 9, 63, 1008, 63, 20, 63, 1005, 63, 333, 1106, 0, 339, 4, 319, 1001,
+    64, 1, 64, 1002, 64, 2, 64, 109, 19, 21102, 43, 1, 6, 1008, 1019, 45,
+    63, 1005, 63, 363, 1001, 64, 1, 64, 1105, 1, 365, 4, 345, 1002, 64, 2,
+    64, 109, 1, 21108, 44, 47, -3, 1005, 1011, 385, 1001, 64, 1, 64, 1106, 0,
+    387, 4, 371, 1002, 64, 2, 64, 109, -22, 1201, 9, 0, 63, 1008, 63, 21,
+    63, 1005, 63, 411, 1001, 64, 1, 64, 1106, 0, 413, 4, 393, 1002, 64, 2,
+    64, 109, 9, 1207, 0, 19, 63, 1005, 63, 433, 1001, 64, 1, 64, 1106, 0,
+    435, 4, 419, 1002, 64, 2, 64, 109, -9, 2107, 30, 8, 63, 1005, 63, 453,
+    4, 441, 1105, 1, 457, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 25, 2106,
+    0, 10, 1001, 64, 1, 64, 1106, 0, 475, 4, 463, 1002, 64, 2, 64, 109,
+    11, 2106, 0, 0, 4, 481, 1001, 64, 1, 64, 1105, 1, 493, 1002, 64, 2,
+    64, 109, -18, 2108, 21, -6, 63, 1005, 63, 511, 4, 499, 1106, 0, 515, 1001,
+    64, 1, 64, 1002, 64, 2, 64, 109, -12, 2108, 18, 6, 63, 1005, 63, 535,
+    1001, 64, 1, 64, 1106, 0, 537, 4, 521, 1002, 64, 2, 64, 109, 19, 21101,
+    45, 0, -7, 1008, 1010, 45, 63, 1005, 63, 563, 4, 543, 1001, 64, 1, 64,
+    1105, 1, 563, 1002, 64, 2, 64, 109, -10, 1207, -5, 31, 63, 1005, 63, 581,
+    4, 569, 1106, 0, 585, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -8, 2102,
+    1, 5, 63, 1008, 63, 21, 63, 1005, 63, 611, 4, 591, 1001, 64, 1, 64,
+    1105, 1, 611, 1002, 64, 2, 64, 109, 5, 1201, 0, 0, 63, 1008, 63, 21,
+    63, 1005, 63, 633, 4, 617, 1106, 0, 637, 1001, 64, 1, 64, 1002, 64, 2,
+    64, 109, 13, 2105, 1, 6, 1001, 64, 1, 64, 1106, 0, 655, 4, 643, 1002,
+    64, 2, 64, 109, -7, 1202, -3, 1, 63, 1008, 63, 26, 63, 1005, 63, 681,
+    4, 661, 1001, 64, 1, 64, 1106, 0, 681, 1002, 64, 2, 64, 109, 12, 2105,
+    1, 2, 4, 687, 1001, 64, 1, 64, 1105, 1, 699, 1002, 64, 2, 64, 109,
+    -28, 1208, 8, 30, 63, 1005, 63, 717, 4, 705, 1106, 0, 721, 1001, 64, 1,
+    64, 1002, 64, 2, 64, 109, 10, 1202, 1, 1, 63, 1008, 63, 40, 63, 1005,
+    63, 745, 1001, 64, 1, 64, 1105, 1, 747, 4, 727, 1002, 64, 2, 64, 109,
+    10, 21108, 46, 46, -2, 1005, 1012, 765, 4, 753, 1105, 1, 769, 1001, 64, 1,
+    64, 1002, 64, 2, 64, 109, -2, 1205, 8, 781, 1106, 0, 787, 4, 775, 1001,
+    64, 1, 64, 1002, 64, 2, 64, 109, -9, 2101, 0, 0, 63, 1008, 63, 23,
+    63, 1005, 63, 809, 4, 793, 1105, 1, 813, 1001, 64, 1, 64, 1002, 64, 2,
+    64, 109, 9, 1206, 8, 831, 4, 819, 1001, 64, 1, 64, 1106, 0, 831, 1002,
+    64, 2, 64, 109, -9, 2102, 1, -2, 63, 1008, 63, 22, 63, 1005, 63, 855,
+    1001, 64, 1, 64, 1106, 0, 857, 4, 837, 1002, 64, 2, 64, 109, 4, 21101,
+    47, 0, 10, 1008, 1017, 50, 63, 1005, 63, 877, 1105, 1, 883, 4, 863, 1001,
+    64, 1, 64, 1002, 64, 2, 64, 109, 18, 1206, -4, 895, 1105, 1, 901, 4,
+    889, 1001, 64, 1, 64, 4, 64, 99, 21101, 0, 27, 1, 21102, 915, 1, 0,
+    1106, 0, 922, 21201, 1, 56639, 1, 204, 1, 99, 109, 3, 1207, -2, 3, 63,
+    1005, 63, 964, 21201, -2, -1, 1, 21102, 1, 942, 0, 1106, 0, 922, 22102, 1,
+    1, -1, 21201, -2, -3, 1, 21101, 0, 957, 0, 1106, 0, 922, 22201, 1, -1,
+    -2, 1106, 0, 968, 22102, 1, -2, -2, 109, -3, 2106,
+];
+
+/// Start and end address of every translated block
+const BLOCKS: &[(Pos, Pos)] = &[
+    (0, 11), (11, 25), (25, 34), (34, 41), (41, 48), (48, 53), (53, 58), (58, 63),
+    (65, 194), (194, 201), (201, 203), (203, 216), (216, 223), (223, 225), (225, 238), (238, 243),
+    (243, 247), (247, 260), (260, 267), (267, 269), (269, 286), (286, 291), (291, 295), (295, 304),
+    (304, 309), (309, 313), (313, 330), (330, 333), (333, 339), (339, 356), (356, 363), (363, 365),
+    (365, 378), (378, 385), (385, 387), (387, 404), (404, 411), (411, 413), (413, 426), (426, 433),
+    (433, 435), (435, 448), (448, 453), (453, 457), (457, 466), (904, 915), (915, 922), (922, 931),
+    (931, 942), (942, 957), (957, 964), (964, 968), (968, 973),
+];
+
+/// Execute the instruction at the IP, or at `$addr`, with the interpreter
+#[allow(unused_macros)]
+macro_rules! interpret {
+    ($p:ident) => {
+        match $p.step()? {
+            Event::NeedsInput => return Ok(ExitReason::NeedsInput),
+            Event::Halted => return Ok(ExitReason::Halted),
+            _ => continue
+        }
+    };
+    ($p:ident, $addr:expr) => {{
+        $p.ip = $addr;
+        interpret!($p)
+    }};
+}
+
+/// Address of a relative parameter of the instruction at `$addr`. Negative addresses and
+/// overflow are left to the interpreter.
+#[allow(unused_macros)]
+macro_rules! rel {
+    ($p:ident, $offset:expr, $addr:expr) => {
+        match $p.relative_base.checked_add($offset) {
+            Some(address) if address >= 0 => address as Pos,
+            _ => interpret!($p, $addr)
+        }
+    };
+}
+
+/// Execute the instruction at the IP with the interpreter, noting writes into translated code
+#[allow(unused_macros)]
+macro_rules! fallback {
+    ($p:ident, $modified:expr) => {{
+        if let Some(block) = written_block($p) {
+            $modified.insert(block);
+        }
+        interpret!($p)
+    }};
+}
+
+/// Start address of the translated block containing `address`
+fn block_at(address: Pos) -> Option<Pos> {
+    let index = BLOCKS.partition_point(|&(start, _)| start <= address).checked_sub(1)?;
+    let (start, end) = BLOCKS[index];
+    if address < end { Some(start) } else { None }
+}
+
+/// Start address of the translated block the instruction at the IP writes into
+fn written_block<I: InputSource, O: OutputSink, M: Memory>(p: &Program<I, O, M>) -> Option<Pos> {
+    let dest = match p.decode(p.ip) {
+        Ok(Opcode::Add(_, _, dest)) | Ok(Opcode::Mul(_, _, dest)) | Ok(Opcode::LessThan(_, _, dest))
+        | Ok(Opcode::Equals(_, _, dest)) | Ok(Opcode::In(dest)) => dest,
+        _ => return None
+    };
+    match dest {
+        Mode::Positional(address) => block_at(address),
+        Mode::Relative(offset) => match p.relative_base.checked_add(offset) {
+            Some(address) if address >= 0 => block_at(address as Pos),
+            _ => None
+        },
+        Mode::Immediate(_) => None
+    }
+}
+
+/// Translated program with the emulator state it runs on
+pub struct Translated<I = VecDeque<isize>, O = Vec<isize>, M = PagedMemory> {
+    /// Emulator state, shared with the interpreter fallback
+    pub program: Program<I, O, M>,
+
+    /// Start addresses of the blocks whose code was overwritten. These only run on the
+    /// interpreter from then on.
+    pub modified: BTreeSet<Pos>
+}
+
+impl Translated {
+    /// Translated program with the image loaded at address 0
+    pub fn new() -> Self {
+        Translated::from_program(Program::from_image(IMAGE))
+    }
+}
+
+impl Default for Translated {
+    fn default() -> Self {
+        Translated::new()
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: Memory> Translated<I, O, M> {
+    /// Run translated code on an existing emulator state. Blocks whose code differs from the
+    /// image, for example after patching the program, are interpreted.
+    pub fn from_program(program: Program<I, O, M>) -> Self {
+        let modified = BLOCKS.iter()
+            .filter(|&&(start, end)| {
+                (start..end).any(|address| program.read(address) != IMAGE.get(address).copied().unwrap_or(0))
+            })
+            .map(|&(start, _)| start)
+            .collect();
+        Translated { program, modified }
+    }
+
+    /// Execute until the program halts or needs input, like `Program::run`
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        loop {
+            let p = &mut self.program;
+            match p.ip {
+                ip if self.modified.contains(&ip) => fallback!(p, self.modified),
+                0 => {
+                    // 0000  mul #34463338, #34463338 -> [63]
+                    let v = match isize::checked_mul(34463338, 34463338) {
+                        Some(v) => v,
+                        None => interpret!(p, 0)
+                    };
+                    p.write(63, v);
+                    // 0004  lt [63], #34463338 -> [63]
+                    let a = p.read(63);
+                    let v = isize::from(a < 34463338);
+                    p.write(63, v);
+                    // 0008  jnz [63], #53
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 53 } else { 11 };
+                    continue;
+                }
+                11 => {
+                    // 0011  mul #1, #3 -> [1000]
+                    let v = match isize::checked_mul(1, 3) {
+                        Some(v) => v,
+                        None => interpret!(p, 11)
+                    };
+                    p.write(1000, v);
+                    // 0015  arb #988
+                    match p.relative_base.checked_add(988) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 15)
+                    }
+                    // 0017  arb [rb+12]
+                    let a = p.read(rel!(p, 12, 17));
+                    match p.relative_base.checked_add(a) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 17)
+                    }
+                    // 0019  arb [1000]
+                    let a = p.read(1000);
+                    match p.relative_base.checked_add(a) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 19)
+                    }
+                    // 0021  arb [rb+6]
+                    let a = p.read(rel!(p, 6, 21));
+                    match p.relative_base.checked_add(a) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 21)
+                    }
+                    // 0023  arb [rb+3]
+                    let a = p.read(rel!(p, 3, 23));
+                    match p.relative_base.checked_add(a) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 23)
+                    }
+                    p.ip = 25;
+                    continue;
+                }
+                25 => {
+                    // 0025  in -> [rb]
+                    let d = rel!(p, 0, 25);
+                    let v = match p.read_input() {
+                        Some(v) => v,
+                        None => return Ok(ExitReason::NeedsInput)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 27;
+                        continue;
+                    }
+                    // 0027  eq [1000], #1 -> [63]
+                    let a = p.read(1000);
+                    let v = isize::from(a == 1);
+                    p.write(63, v);
+                    // 0031  jnz [63], #65
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 65 } else { 34 };
+                    continue;
+                }
+                34 => {
+                    // 0034  eq [1000], #2 -> [63]
+                    let a = p.read(1000);
+                    let v = isize::from(a == 2);
+                    p.write(63, v);
+                    // 0038  jnz [63], #904
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 904 } else { 41 };
+                    continue;
+                }
+                41 => {
+                    // 0041  eq [1000], #0 -> [63]
+                    let a = p.read(1000);
+                    let v = isize::from(a == 0);
+                    p.write(63, v);
+                    // 0045  jnz [63], #58
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 58 } else { 48 };
+                    continue;
+                }
+                48 => {
+                    // 0048  out [25]
+                    let a = p.read(25);
+                    p.write_output(a);
+                    // 0050  out #0
+                    p.write_output(0);
+                    // 0052  halt
+                    p.ip = 52;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                53 => {
+                    // 0053  out [0]
+                    let a = p.read(0);
+                    p.write_output(a);
+                    // 0055  out #0
+                    p.write_output(0);
+                    // 0057  halt
+                    p.ip = 57;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                58 => {
+                    // 0058  out [17]
+                    let a = p.read(17);
+                    p.write_output(a);
+                    // 0060  out #0
+                    p.write_output(0);
+                    // 0062  halt
+                    p.ip = 62;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                65 => {
+                    // 0065  mul #1, #21 -> [1004]
+                    let v = match isize::checked_mul(1, 21) {
+                        Some(v) => v,
+                        None => interpret!(p, 65)
+                    };
+                    p.write(1004, v);
+                    // 0069  add #28, #0 -> [1016]
+                    let v = match isize::checked_add(28, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 69)
+                    };
+                    p.write(1016, v);
+                    // 0073  add #0, #27 -> [1010]
+                    let v = match isize::checked_add(0, 27) {
+                        Some(v) => v,
+                        None => interpret!(p, 73)
+                    };
+                    p.write(1010, v);
+                    // 0077  mul #36, #1 -> [1008]
+                    let v = match isize::checked_mul(36, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 77)
+                    };
+                    p.write(1008, v);
+                    // 0081  mul #33, #1 -> [1013]
+                    let v = match isize::checked_mul(33, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 81)
+                    };
+                    p.write(1013, v);
+                    // 0085  add #0, #22 -> [1012]
+                    let v = match isize::checked_add(0, 22) {
+                        Some(v) => v,
+                        None => interpret!(p, 85)
+                    };
+                    p.write(1012, v);
+                    // 0089  add #0, #37 -> [1011]
+                    let v = match isize::checked_add(0, 37) {
+                        Some(v) => v,
+                        None => interpret!(p, 89)
+                    };
+                    p.write(1011, v);
+                    // 0093  mul #34, #1 -> [1017]
+                    let v = match isize::checked_mul(34, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 93)
+                    };
+                    p.write(1017, v);
+                    // 0097  mul #466, #1 -> [1027]
+                    let v = match isize::checked_mul(466, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 97)
+                    };
+                    p.write(1027, v);
+                    // 0101  mul #1, #484 -> [1029]
+                    let v = match isize::checked_mul(1, 484) {
+                        Some(v) => v,
+                        None => interpret!(p, 101)
+                    };
+                    p.write(1029, v);
+                    // 0105  mul #1, #699 -> [1024]
+                    let v = match isize::checked_mul(1, 699) {
+                        Some(v) => v,
+                        None => interpret!(p, 105)
+                    };
+                    p.write(1024, v);
+                    // 0109  mul #1, #1 -> [1021]
+                    let v = match isize::checked_mul(1, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 109)
+                    };
+                    p.write(1021, v);
+                    // 0113  add #0, #0 -> [1020]
+                    let v = match isize::checked_add(0, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 113)
+                    };
+                    p.write(1020, v);
+                    // 0117  mul #1, #24 -> [1015]
+                    let v = match isize::checked_mul(1, 24) {
+                        Some(v) => v,
+                        None => interpret!(p, 117)
+                    };
+                    p.write(1015, v);
+                    // 0121  add #0, #473 -> [1026]
+                    let v = match isize::checked_add(0, 473) {
+                        Some(v) => v,
+                        None => interpret!(p, 121)
+                    };
+                    p.write(1026, v);
+                    // 0125  add #653, #0 -> [1022]
+                    let v = match isize::checked_add(653, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 125)
+                    };
+                    p.write(1022, v);
+                    // 0129  mul #26, #1 -> [1007]
+                    let v = match isize::checked_mul(26, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 129)
+                    };
+                    p.write(1007, v);
+                    // 0133  mul #25, #1 -> [1006]
+                    let v = match isize::checked_mul(25, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 133)
+                    };
+                    p.write(1006, v);
+                    // 0137  add #0, #39 -> [1014]
+                    let v = match isize::checked_add(0, 39) {
+                        Some(v) => v,
+                        None => interpret!(p, 137)
+                    };
+                    p.write(1014, v);
+                    // 0141  mul #646, #1 -> [1023]
+                    let v = match isize::checked_mul(646, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 141)
+                    };
+                    p.write(1023, v);
+                    // 0145  add #690, #0 -> [1025]
+                    let v = match isize::checked_add(690, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 145)
+                    };
+                    p.write(1025, v);
+                    // 0149  mul #1, #29 -> [1019]
+                    let v = match isize::checked_mul(1, 29) {
+                        Some(v) => v,
+                        None => interpret!(p, 149)
+                    };
+                    p.write(1019, v);
+                    // 0153  add #32, #0 -> [1018]
+                    let v = match isize::checked_add(32, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 153)
+                    };
+                    p.write(1018, v);
+                    // 0157  add #30, #0 -> [1002]
+                    let v = match isize::checked_add(30, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 157)
+                    };
+                    p.write(1002, v);
+                    // 0161  add #0, #20 -> [1001]
+                    let v = match isize::checked_add(0, 20) {
+                        Some(v) => v,
+                        None => interpret!(p, 161)
+                    };
+                    p.write(1001, v);
+                    // 0165  mul #1, #38 -> [1005]
+                    let v = match isize::checked_mul(1, 38) {
+                        Some(v) => v,
+                        None => interpret!(p, 165)
+                    };
+                    p.write(1005, v);
+                    // 0169  mul #1, #23 -> [1003]
+                    let v = match isize::checked_mul(1, 23) {
+                        Some(v) => v,
+                        None => interpret!(p, 169)
+                    };
+                    p.write(1003, v);
+                    // 0173  add #0, #31 -> [1000]
+                    let v = match isize::checked_add(0, 31) {
+                        Some(v) => v,
+                        None => interpret!(p, 173)
+                    };
+                    p.write(1000, v);
+                    // 0177  add #35, #0 -> [1009]
+                    let v = match isize::checked_add(35, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 177)
+                    };
+                    p.write(1009, v);
+                    // 0181  add #0, #493 -> [1028]
+                    let v = match isize::checked_add(0, 493) {
+                        Some(v) => v,
+                        None => interpret!(p, 181)
+                    };
+                    p.write(1028, v);
+                    // 0185  arb #5
+                    match p.relative_base.checked_add(5) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 185)
+                    }
+                    // 0187  eq [rb], #37 -> [63]
+                    let a = p.read(rel!(p, 0, 187));
+                    let v = isize::from(a == 37);
+                    p.write(63, v);
+                    // 0191  jnz [63], #201
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 201 } else { 194 };
+                    continue;
+                }
+                194 => {
+                    // 0194  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 194)
+                    };
+                    p.write(64, v);
+                    // 0198  jz #0, #203
+                    p.ip = 203;
+                    continue;
+                }
+                201 => {
+                    // 0201  out [187]
+                    let a = p.read(187);
+                    p.write_output(a);
+                    p.ip = 203;
+                    continue;
+                }
+                203 => {
+                    // 0203  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 203)
+                    };
+                    p.write(64, v);
+                    // 0207  arb #-4
+                    match p.relative_base.checked_add(-4) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 207)
+                    }
+                    // 0209  lt #36, [rb+8] -> [63]
+                    let b = p.read(rel!(p, 8, 209));
+                    let v = isize::from(36 < b);
+                    p.write(63, v);
+                    // 0213  jnz [63], #223
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 223 } else { 216 };
+                    continue;
+                }
+                216 => {
+                    // 0216  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 216)
+                    };
+                    p.write(64, v);
+                    // 0220  jnz #1, #225
+                    p.ip = 225;
+                    continue;
+                }
+                223 => {
+                    // 0223  out [209]
+                    let a = p.read(209);
+                    p.write_output(a);
+                    p.ip = 225;
+                    continue;
+                }
+                225 => {
+                    // 0225  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 225)
+                    };
+                    p.write(64, v);
+                    // 0229  arb #18
+                    match p.relative_base.checked_add(18) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 229)
+                    }
+                    // 0231  lt #40, #41 -> [rb-9]
+                    let d = rel!(p, -9, 231);
+                    let v = isize::from(40 < 41);
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 235;
+                        continue;
+                    }
+                    // 0235  jnz [1010], #243
+                    let a = p.read(1010);
+                    p.ip = if a != 0 { 243 } else { 238 };
+                    continue;
+                }
+                238 => {
+                    // 0238  out [231]
+                    let a = p.read(231);
+                    p.write_output(a);
+                    // 0240  jnz #1, #247
+                    p.ip = 247;
+                    continue;
+                }
+                243 => {
+                    // 0243  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 243)
+                    };
+                    p.write(64, v);
+                    p.ip = 247;
+                    continue;
+                }
+                247 => {
+                    // 0247  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 247)
+                    };
+                    p.write(64, v);
+                    // 0251  arb #6
+                    match p.relative_base.checked_add(6) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 251)
+                    }
+                    // 0253  lt #41, #40 -> [rb-9]
+                    let d = rel!(p, -9, 253);
+                    let v = isize::from(41 < 40);
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 257;
+                        continue;
+                    }
+                    // 0257  jnz [1016], #267
+                    let a = p.read(1016);
+                    p.ip = if a != 0 { 267 } else { 260 };
+                    continue;
+                }
+                260 => {
+                    // 0260  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 260)
+                    };
+                    p.write(64, v);
+                    // 0264  jz #0, #269
+                    p.ip = 269;
+                    continue;
+                }
+                267 => {
+                    // 0267  out [253]
+                    let a = p.read(253);
+                    p.write_output(a);
+                    p.ip = 269;
+                    continue;
+                }
+                269 => {
+                    // 0269  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 269)
+                    };
+                    p.write(64, v);
+                    // 0273  arb #-19
+                    match p.relative_base.checked_add(-19) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 273)
+                    }
+                    // 0275  mul #42, #1 -> [rb+5]
+                    let d = rel!(p, 5, 275);
+                    let v = match isize::checked_mul(42, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 275)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 279;
+                        continue;
+                    }
+                    // 0279  eq [1011], #42 -> [63]
+                    let a = p.read(1011);
+                    let v = isize::from(a == 42);
+                    p.write(63, v);
+                    // 0283  jnz [63], #291
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 291 } else { 286 };
+                    continue;
+                }
+                286 => {
+                    // 0286  out [275]
+                    let a = p.read(275);
+                    p.write_output(a);
+                    // 0288  jnz #1, #295
+                    p.ip = 295;
+                    continue;
+                }
+                291 => {
+                    // 0291  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 291)
+                    };
+                    p.write(64, v);
+                    p.ip = 295;
+                    continue;
+                }
+                295 => {
+                    // 0295  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 295)
+                    };
+                    p.write(64, v);
+                    // 0299  arb #15
+                    match p.relative_base.checked_add(15) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 299)
+                    }
+                    // 0301  jnz [rb], #309
+                    let a = p.read(rel!(p, 0, 301));
+                    p.ip = if a != 0 { 309 } else { 304 };
+                    continue;
+                }
+                304 => {
+                    // 0304  out [301]
+                    let a = p.read(301);
+                    p.write_output(a);
+                    // 0306  jnz #1, #313
+                    p.ip = 313;
+                    continue;
+                }
+                309 => {
+                    // 0309  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 309)
+                    };
+                    p.write(64, v);
+                    p.ip = 313;
+                    continue;
+                }
+                313 => {
+                    // 0313  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 313)
+                    };
+                    p.write(64, v);
+                    // 0317  arb #-27
+                    match p.relative_base.checked_add(-27) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 317)
+                    }
+                    // 0319  add #0, [rb+9] -> [63]
+                    let b = p.read(rel!(p, 9, 319));
+                    let v = match isize::checked_add(0, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 319)
+                    };
+                    p.write(63, v);
+                    // 0323  eq [63], #20 -> [63]
+                    let a = p.read(63);
+                    let v = isize::from(a == 20);
+                    p.write(63, v);
+                    // 0327  jnz [63], #333
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 333 } else { 330 };
+                    continue;
+                }
+                330 => {
+                    // 0330  jz #0, #339
+                    p.ip = 339;
+                    continue;
+                }
+                333 => {
+                    // 0333  out [319]
+                    let a = p.read(319);
+                    p.write_output(a);
+                    // 0335  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 335)
+                    };
+                    p.write(64, v);
+                    p.ip = 339;
+                    continue;
+                }
+                339 => {
+                    // 0339  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 339)
+                    };
+                    p.write(64, v);
+                    // 0343  arb #19
+                    match p.relative_base.checked_add(19) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 343)
+                    }
+                    // 0345  mul #43, #1 -> [rb+6]
+                    let d = rel!(p, 6, 345);
+                    let v = match isize::checked_mul(43, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 345)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 349;
+                        continue;
+                    }
+                    // 0349  eq [1019], #45 -> [63]
+                    let a = p.read(1019);
+                    let v = isize::from(a == 45);
+                    p.write(63, v);
+                    // 0353  jnz [63], #363
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 363 } else { 356 };
+                    continue;
+                }
+                356 => {
+                    // 0356  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 356)
+                    };
+                    p.write(64, v);
+                    // 0360  jnz #1, #365
+                    p.ip = 365;
+                    continue;
+                }
+                363 => {
+                    // 0363  out [345]
+                    let a = p.read(345);
+                    p.write_output(a);
+                    p.ip = 365;
+                    continue;
+                }
+                365 => {
+                    // 0365  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 365)
+                    };
+                    p.write(64, v);
+                    // 0369  arb #1
+                    match p.relative_base.checked_add(1) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 369)
+                    }
+                    // 0371  eq #44, #47 -> [rb-3]
+                    let d = rel!(p, -3, 371);
+                    let v = isize::from(44 == 47);
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 375;
+                        continue;
+                    }
+                    // 0375  jnz [1011], #385
+                    let a = p.read(1011);
+                    p.ip = if a != 0 { 385 } else { 378 };
+                    continue;
+                }
+                378 => {
+                    // 0378  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 378)
+                    };
+                    p.write(64, v);
+                    // 0382  jz #0, #387
+                    p.ip = 387;
+                    continue;
+                }
+                385 => {
+                    // 0385  out [371]
+                    let a = p.read(371);
+                    p.write_output(a);
+                    p.ip = 387;
+                    continue;
+                }
+                387 => {
+                    // 0387  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 387)
+                    };
+                    p.write(64, v);
+                    // 0391  arb #-22
+                    match p.relative_base.checked_add(-22) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 391)
+                    }
+                    // 0393  add [rb+9], #0 -> [63]
+                    let a = p.read(rel!(p, 9, 393));
+                    let v = match isize::checked_add(a, 0) {
+                        Some(v) => v,
+                        None => interpret!(p, 393)
+                    };
+                    p.write(63, v);
+                    // 0397  eq [63], #21 -> [63]
+                    let a = p.read(63);
+                    let v = isize::from(a == 21);
+                    p.write(63, v);
+                    // 0401  jnz [63], #411
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 411 } else { 404 };
+                    continue;
+                }
+                404 => {
+                    // 0404  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 404)
+                    };
+                    p.write(64, v);
+                    // 0408  jz #0, #413
+                    p.ip = 413;
+                    continue;
+                }
+                411 => {
+                    // 0411  out [393]
+                    let a = p.read(393);
+                    p.write_output(a);
+                    p.ip = 413;
+                    continue;
+                }
+                413 => {
+                    // 0413  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 413)
+                    };
+                    p.write(64, v);
+                    // 0417  arb #9
+                    match p.relative_base.checked_add(9) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 417)
+                    }
+                    // 0419  lt [rb], #19 -> [63]
+                    let a = p.read(rel!(p, 0, 419));
+                    let v = isize::from(a < 19);
+                    p.write(63, v);
+                    // 0423  jnz [63], #433
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 433 } else { 426 };
+                    continue;
+                }
+                426 => {
+                    // 0426  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 426)
+                    };
+                    p.write(64, v);
+                    // 0430  jz #0, #435
+                    p.ip = 435;
+                    continue;
+                }
+                433 => {
+                    // 0433  out [419]
+                    let a = p.read(419);
+                    p.write_output(a);
+                    p.ip = 435;
+                    continue;
+                }
+                435 => {
+                    // 0435  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 435)
+                    };
+                    p.write(64, v);
+                    // 0439  arb #-9
+                    match p.relative_base.checked_add(-9) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 439)
+                    }
+                    // 0441  lt #30, [rb+8] -> [63]
+                    let b = p.read(rel!(p, 8, 441));
+                    let v = isize::from(30 < b);
+                    p.write(63, v);
+                    // 0445  jnz [63], #453
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 453 } else { 448 };
+                    continue;
+                }
+                448 => {
+                    // 0448  out [441]
+                    let a = p.read(441);
+                    p.write_output(a);
+                    // 0450  jnz #1, #457
+                    p.ip = 457;
+                    continue;
+                }
+                453 => {
+                    // 0453  add [64], #1 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_add(a, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 453)
+                    };
+                    p.write(64, v);
+                    p.ip = 457;
+                    continue;
+                }
+                457 => {
+                    // 0457  mul [64], #2 -> [64]
+                    let a = p.read(64);
+                    let v = match isize::checked_mul(a, 2) {
+                        Some(v) => v,
+                        None => interpret!(p, 457)
+                    };
+                    p.write(64, v);
+                    // 0461  arb #25
+                    match p.relative_base.checked_add(25) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 461)
+                    }
+                    // 0463  jz #0, [rb+10]
+                    let b = p.read(rel!(p, 10, 463));
+                    if b < 0 { interpret!(p, 463) }
+                    p.ip = b as Pos;
+                    continue;
+                }
+                904 => {
+                    // 0904  add #0, #27 -> [rb+1]
+                    let d = rel!(p, 1, 904);
+                    let v = match isize::checked_add(0, 27) {
+                        Some(v) => v,
+                        None => interpret!(p, 904)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 908;
+                        continue;
+                    }
+                    // 0908  mul #915, #1 -> [rb]
+                    let d = rel!(p, 0, 908);
+                    let v = match isize::checked_mul(915, 1) {
+                        Some(v) => v,
+                        None => interpret!(p, 908)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 912;
+                        continue;
+                    }
+                    // 0912  jz #0, #922
+                    p.ip = 922;
+                    continue;
+                }
+                915 => {
+                    // 0915  add [rb+1], #56639 -> [rb+1]
+                    let a = p.read(rel!(p, 1, 915));
+                    let d = rel!(p, 1, 915);
+                    let v = match isize::checked_add(a, 56639) {
+                        Some(v) => v,
+                        None => interpret!(p, 915)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 919;
+                        continue;
+                    }
+                    // 0919  out [rb+1]
+                    let a = p.read(rel!(p, 1, 919));
+                    p.write_output(a);
+                    // 0921  halt
+                    p.ip = 921;
+                    p.halted = true;
+                    return Ok(ExitReason::Halted);
+                }
+                922 => {
+                    // 0922  arb #3
+                    match p.relative_base.checked_add(3) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 922)
+                    }
+                    // 0924  lt [rb-2], #3 -> [63]
+                    let a = p.read(rel!(p, -2, 924));
+                    let v = isize::from(a < 3);
+                    p.write(63, v);
+                    // 0928  jnz [63], #964
+                    let a = p.read(63);
+                    p.ip = if a != 0 { 964 } else { 931 };
+                    continue;
+                }
+                931 => {
+                    // 0931  add [rb-2], #-1 -> [rb+1]
+                    let a = p.read(rel!(p, -2, 931));
+                    let d = rel!(p, 1, 931);
+                    let v = match isize::checked_add(a, -1) {
+                        Some(v) => v,
+                        None => interpret!(p, 931)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 935;
+                        continue;
+                    }
+                    // 0935  mul #1, #942 -> [rb]
+                    let d = rel!(p, 0, 935);
+                    let v = match isize::checked_mul(1, 942) {
+                        Some(v) => v,
+                        None => interpret!(p, 935)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 939;
+                        continue;
+                    }
+                    // 0939  jz #0, #922
+                    p.ip = 922;
+                    continue;
+                }
+                942 => {
+                    // 0942  mul #1, [rb+1] -> [rb-1]
+                    let b = p.read(rel!(p, 1, 942));
+                    let d = rel!(p, -1, 942);
+                    let v = match isize::checked_mul(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 942)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 946;
+                        continue;
+                    }
+                    // 0946  add [rb-2], #-3 -> [rb+1]
+                    let a = p.read(rel!(p, -2, 946));
+                    let d = rel!(p, 1, 946);
+                    let v = match isize::checked_add(a, -3) {
+                        Some(v) => v,
+                        None => interpret!(p, 946)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 950;
+                        continue;
+                    }
+                    // 0950  add #0, #957 -> [rb]
+                    let d = rel!(p, 0, 950);
+                    let v = match isize::checked_add(0, 957) {
+                        Some(v) => v,
+                        None => interpret!(p, 950)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 954;
+                        continue;
+                    }
+                    // 0954  jz #0, #922
+                    p.ip = 922;
+                    continue;
+                }
+                957 => {
+                    // 0957  add [rb+1], [rb-1] -> [rb-2]
+                    let a = p.read(rel!(p, 1, 957));
+                    let b = p.read(rel!(p, -1, 957));
+                    let d = rel!(p, -2, 957);
+                    let v = match isize::checked_add(a, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 957)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 961;
+                        continue;
+                    }
+                    // 0961  jz #0, #968
+                    p.ip = 968;
+                    continue;
+                }
+                964 => {
+                    // 0964  mul #1, [rb-2] -> [rb-2]
+                    let b = p.read(rel!(p, -2, 964));
+                    let d = rel!(p, -2, 964);
+                    let v = match isize::checked_mul(1, b) {
+                        Some(v) => v,
+                        None => interpret!(p, 964)
+                    };
+                    p.write(d, v);
+                    if let Some(block) = block_at(d) {
+                        self.modified.insert(block);
+                        p.ip = 968;
+                        continue;
+                    }
+                    p.ip = 968;
+                    continue;
+                }
+                968 => {
+                    // 0968  arb #-3
+                    match p.relative_base.checked_add(-3) {
+                        Some(rb) => p.relative_base = rb,
+                        None => interpret!(p, 968)
+                    }
+                    // 0970  jz #0, [rb]
+                    let b = p.read(rel!(p, 0, 970));
+                    if b < 0 { interpret!(p, 970) }
+                    p.ip = b as Pos;
+                    continue;
+                }
+                _ => fallback!(p, self.modified)
+            }
+        }
+    }
+}